@@ -1,24 +1,43 @@
 use std::str::FromStr;
-use std::net::Ipv4Addr;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
 use std::process;
-use std::sync::Arc;
 use std::path::Path;
 use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use clap::builder::PossibleValue;
+use clap::error::ErrorKind;
 use clap::{Arg, ArgMatches, Command, ArgAction};
 use ipnetwork::IpNetwork;
 use pnet_datalink::MacAddr;
-use pnet::packet::arp::{ArpHardwareType, ArpOperation};
+use pnet::packet::arp::{ArpHardwareType, ArpHardwareTypes, ArpOperation, ArpOperations};
 use pnet::packet::ethernet::EtherType;
 
-use crate::time::parse_to_milliseconds;
+use crate::baseline::Baseline;
+use crate::checkpoint::{self, ScanCheckpoint};
+use crate::filter::HostFilter;
+use crate::deferred::DeferredFile;
+use crate::hosts::HostsFile;
+use crate::inventory::Inventory;
+use crate::notify::{self, NotificationSink};
+use crate::time::{parse_to_milliseconds, parse_to_microseconds};
 
 const TIMEOUT_MS_FAST: u64 = 800;
 const TIMEOUT_MS_DEFAULT: u64 = 2000;
 
 const HOST_RETRY_DEFAULT: usize = 1;
-const REQUEST_MS_INTERVAL: u64 = 10;
+
+const RESOLVE_WORKERS_DEFAULT: usize = 16;
+const RESOLVE_TIMEOUT_MS_DEFAULT: u64 = 2000;
+const RESOLVE_BUDGET_MS_DEFAULT: u64 = 10_000;
+const DNS_PORT: u16 = 53;
+const PORT_TIMEOUT_MS_DEFAULT: u64 = 1000;
+const SCAN_INTERVAL_MS_DEFAULT: u64 = 300_000;
+const HISTORY_WINDOW_MS_DEFAULT: u64 = 86_400_000;
+const NOTIFY_RETRIES_DEFAULT: u32 = 2;
+const NOTIFY_TIMEOUT_MS_DEFAULT: u64 = 5000;
+const REQUEST_US_INTERVAL: u64 = 10_000;
+const BURST_SIZE_DEFAULT: u32 = 1;
 
 const CLI_VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -39,6 +58,62 @@ const EXAMPLES_HELP: &str = "EXAMPLES:
     # Launch a scan on VLAN 45 with JSON output
     arp-scan -Q 45 -o json
 
+    # Scan every minute and serve Prometheus metrics on port 9640
+    arp-scan --metrics 0.0.0.0:9640 --scan-interval 1m
+
+    # Record each scan in a SQLite database, then list hosts seen this week
+    arp-scan --database ./scans.db
+    arp-scan --database ./scans.db --history --since 7d
+
+    # Find where a device has been seen in the scan database
+    arp-scan --database ./scans.db --query b8:27:eb:01:02:03
+
+    # Post results to a webhook and log changes to the local syslog
+    arp-scan --notify webhook:http://10.20.0.5:8080/scans --notify syslog:unix:///dev/log
+
+    # Print the result table and save JSON and CSV exports
+    arp-scan -o plain -o json:results.json -o csv:hosts.csv
+
+    # Write a Markdown report for an audit ticket and an HTML report
+    arp-scan -o markdown:report.md -o html:report.html
+
+    # List Cisco devices by reply latency, with a few columns
+    arp-scan --filter 'vendor contains Cisco' --sort latency --columns ip,mac,latency
+
+    # Export results as nmap XML for vulnerability management imports
+    arp-scan -o xml:hosts.xml
+
+    # Stream hosts as NDJSON records during a long stealth scan
+    arp-scan --profile stealth --stream -o json
+
+    # Print the scan plan of a stealth scan without sending requests
+    arp-scan -n 10.20.0.0/16 --profile stealth --dry-run
+
+    # Find hostnames with reverse DNS, then multicast DNS and NetBIOS
+    arp-scan --name-sources dns,mdns,netbios
+
+    # Resolve hostnames with the lab DNS server and check them
+    arp-scan --dns-server 10.20.0.53 --forward-confirm
+
+    # Also find hosts ignoring ARP broadcasts with ICMP echo requests
+    arp-scan --icmp
+
+    # Check which discovered hosts expose SSH, HTTP or RDP
+    arp-scan --ports 22,80,443,3389
+
+    # Classify discovered hosts with a device fingerprint database
+    arp-scan --fingerprint-file ./data/device-fingerprints.csv
+
+    # Check a known inventory of static devices with unicast ARP requests
+    arp-scan --verify ./inventory.csv
+
+    # Fail (exit code 2) when the lab racks contain unexpected devices
+    arp-scan -n 10.20.0.0/24 --baseline ./lab-baseline.yml
+
+    # Launch a resumable scan and resume it after an interruption
+    arp-scan -n 10.20.0.0/16 --checkpoint scan.json
+    arp-scan --resume scan.json
+
 ";
 
 /**
@@ -82,6 +157,12 @@ pub fn build_args() -> Command {
                 .conflicts_with("network")
                 .help("Read IPv4 addresses from a file")
         )
+        .arg(
+            Arg::new("verify").long("verify")
+                .value_name("FILE_PATH")
+                .conflicts_with_all(["network", "file", "checkpoint", "resume", "destination_mac"])
+                .help("Verify a CSV inventory of IPv4,MAC pairs with unicast ARP requests")
+        )
         .arg(
             Arg::new("timeout").short('t').long("timeout")
                 .value_name("TIMEOUT_DURATION")
@@ -107,6 +188,72 @@ pub fn build_args() -> Command {
                 .action(ArgAction::SetTrue)
                 .help("Numeric mode, no hostname resolution")
         )
+        .arg(
+            Arg::new("resolve_workers").long("resolve-workers")
+                .value_name("WORKER_COUNT")
+                .help("Concurrent hostname lookups (defaults to 16)")
+        )
+        .arg(
+            Arg::new("resolve_timeout").long("resolve-timeout")
+                .value_name("TIMEOUT_DURATION")
+                .help("Maximum duration of a single hostname lookup (2s)")
+        )
+        .arg(
+            Arg::new("resolve_budget").long("resolve-budget")
+                .value_name("TIMEOUT_DURATION")
+                .help("Maximum duration of the whole hostname resolution (10s)")
+        )
+        .arg(
+            Arg::new("name_sources").long("name-sources")
+                .value_name("SOURCES")
+                .help("Hostname sources by priority: hosts, dns, mdns, netbios, llmnr (defaults to dns, or hosts,dns with a hosts file)")
+        )
+        .arg(
+            Arg::new("dns_server").long("dns-server")
+                .value_name("SERVERS")
+                .help("DNS servers for reverse lookups, as IPv4 with optional port (defaults to system resolver)")
+        )
+        .arg(
+            Arg::new("hosts_file").long("hosts-file")
+                .value_name("FILE_PATH")
+                .help("Hosts-style file used by the hosts name source")
+        )
+        .arg(
+            Arg::new("forward_confirm").long("forward-confirm")
+                .action(ArgAction::SetTrue)
+                .help("Check that DNS hostnames resolve back to the host IPv4 and report mismatches")
+        )
+        .arg(
+            Arg::new("dhcp").long("dhcp")
+                .action(ArgAction::SetTrue)
+                .help("Collect DHCP client details during the scan (hostname, vendor class, OS guess)")
+        )
+        .arg(
+            Arg::new("baseline").long("baseline")
+                .value_name("FILE_PATH")
+                .help("Compare results with a YAML allow-list and exit with code 2 on violations")
+        )
+        .arg(
+            Arg::new("icmp").long("icmp")
+                .value_name("PROBE")
+                .num_args(0..=1)
+                .default_missing_value("echo")
+                .value_parser([
+                    PossibleValue::new("echo").help("ICMP echo requests (ping)"),
+                    PossibleValue::new("timestamp").help("ICMP timestamp requests")
+                ])
+                .help("Also send an ICMP request to each target (defaults to echo)")
+        )
+        .arg(
+            Arg::new("ports").long("ports")
+                .value_name("PORTS")
+                .help("TCP ports probed on discovered hosts, as a list with ranges (e.g. 22,80,8000-8080)")
+        )
+        .arg(
+            Arg::new("port_timeout").long("port-timeout")
+                .value_name("TIMEOUT_DURATION")
+                .help("Maximum duration of a single TCP connection attempt (1s)")
+        )
         .arg(
             Arg::new("vlan").short('Q').long("vlan")
                 .value_name("VLAN_ID")
@@ -122,10 +269,15 @@ pub fn build_args() -> Command {
                 .action(ArgAction::SetTrue)
                 .help("Randomize the target list")
         )
+        .arg(
+            Arg::new("seed").long("seed")
+                .value_name("SEED")
+                .help("Seed for the randomized target order (implies --random)")
+        )
         .arg(
             Arg::new("interval").short('I').long("interval")
                 .value_name("INTERVAL_DURATION")
-                .help("Milliseconds between ARP requests (defaults to 10ms)")
+                .help("Time between ARP requests, supports 'us' suffix (defaults to 10ms)")
         )
         .arg(
             Arg::new("bandwidth").short('B').long("bandwidth")
@@ -133,12 +285,71 @@ pub fn build_args() -> Command {
                 .conflicts_with("interval")
                 .help("Limit scan bandwidth (bits/second)")
         )
+        .arg(
+            Arg::new("rate").long("rate")
+                .value_name("PACKETS")
+                .conflicts_with_all(["interval", "bandwidth"])
+                .help("Limit scan rate (packets/second)")
+        )
+        .arg(
+            Arg::new("burst").long("burst")
+                .value_name("PACKETS")
+                .help("Maximum ARP requests sent in a single burst (defaults to 1)")
+        )
+        .arg(
+            Arg::new("checkpoint").long("checkpoint")
+                .value_name("FILE_PATH")
+                .help("Periodically save the scan state in a checkpoint file")
+        )
+        .arg(
+            Arg::new("resume").long("resume")
+                .value_name("FILE_PATH")
+                .conflicts_with_all(["network", "file", "random", "seed", "checkpoint", "profile", "retry_count", "interval", "bandwidth", "rate", "burst", "timeout"])
+                .help("Resume a scan from a checkpoint file")
+        )
+        .arg(
+            Arg::new("database").long("database")
+                .value_name("FILE_PATH")
+                .conflicts_with("dry_run")
+                .help("Record scans and discovered hosts in a SQLite database")
+        )
+        .arg(
+            Arg::new("history").long("history")
+                .action(ArgAction::SetTrue)
+                .requires("database")
+                .conflicts_with("query")
+                .help("List hosts recorded in the database and exit")
+        )
+        .arg(
+            Arg::new("since").long("since")
+                .value_name("DURATION")
+                .requires("history")
+                .help("Start of the history window, as a duration before now (24h)")
+        )
+        .arg(
+            Arg::new("until").long("until")
+                .value_name("DURATION")
+                .requires("history")
+                .help("End of the history window, as a duration before now (defaults to now)")
+        )
+        .arg(
+            Arg::new("query").long("query")
+                .value_name("MAC_ADDRESS")
+                .requires("database")
+                .help("Show where a MAC address was seen in the database and exit")
+        )
         .arg(
             Arg::new("oui-file").long("oui-file")
                 .value_name("FILE_PATH")
                 .default_value("/usr/share/arp-scan/ieee-oui.csv")
                 .help("Path to custom IEEE OUI CSV file for vendor lookup")
         )
+        .arg(
+            Arg::new("fingerprint-file").long("fingerprint-file")
+                .value_name("FILE_PATH")
+                .default_value("/usr/share/arp-scan/device-fingerprints.csv")
+                .help("Path to the device fingerprint CSV file for device classes")
+        )
         .arg(
             Arg::new("list").short('l').long("list")
                 .action(ArgAction::SetTrue)
@@ -147,14 +358,70 @@ pub fn build_args() -> Command {
         )
         .arg(
             Arg::new("output").short('o').long("output")
-                .value_name("FORMAT")
+                .value_name("FORMAT[:FILE]")
+                .action(ArgAction::Append)
+                .help("Define output format (plain/json/yaml/csv/xml/markdown/html), optionally written to a file (repeatable)")
+        )
+        .arg(
+            Arg::new("stream").long("stream")
+                .action(ArgAction::SetTrue)
+                .help("Print hosts as soon as they are discovered (NDJSON for JSON output)")
+        )
+        .arg(
+            Arg::new("sort").long("sort")
+                .value_name("SORT_KEY")
                 .value_parser([
-                    PossibleValue::new("plain").help("Verbose output with table"),
-                    PossibleValue::new("json").help("JSON format"),
-                    PossibleValue::new("yaml").help("YAML format"),
-                    PossibleValue::new("csv").help("CSV format")
+                    PossibleValue::new("ip").help("Sort hosts by IPv4 address (default)"),
+                    PossibleValue::new("mac").help("Sort hosts by MAC address"),
+                    PossibleValue::new("vendor").help("Sort hosts by vendor name"),
+                    PossibleValue::new("hostname").help("Sort hosts by hostname"),
+                    PossibleValue::new("latency").help("Sort hosts by ARP reply latency")
                 ])
-                .help("Define output format")
+                .help("Order of the hosts in the scan results")
+        )
+        .arg(
+            Arg::new("filter").long("filter")
+                .value_name("EXPRESSION")
+                .action(ArgAction::Append)
+                .conflicts_with("stream")
+                .help("Only show hosts matching 'FIELD OPERATOR [VALUE]', e.g. 'vendor contains Cisco' (repeatable)")
+        )
+        .arg(
+            Arg::new("columns").long("columns")
+                .value_name("COLUMN_LIST")
+                .conflicts_with("stream")
+                .help("Columns of the scan results, e.g. ip,mac,vendor,latency")
+        )
+        .arg(
+            Arg::new("metrics").long("metrics")
+                .value_name("LISTEN_ADDRESS")
+                .conflicts_with_all(["checkpoint", "resume", "stream", "dry_run"])
+                .help("Scan periodically and serve Prometheus metrics on http://LISTEN_ADDRESS/metrics")
+        )
+        .arg(
+            Arg::new("scan_interval").long("scan-interval")
+                .value_name("INTERVAL_DURATION")
+                .requires("metrics")
+                .help("Delay between two periodic scans in metrics mode (5m)")
+        )
+        .arg(
+            Arg::new("notify").long("notify")
+                .value_name("KIND:TARGET")
+                .action(ArgAction::Append)
+                .conflicts_with("dry_run")
+                .help("Send results & change events to a sink: webhook:http://..., syslog:udp|tcp|unix://..., exec:COMMAND (repeatable)")
+        )
+        .arg(
+            Arg::new("notify_retries").long("notify-retries")
+                .value_name("RETRY_COUNT")
+                .requires("notify")
+                .help("Retries for each failing notification sink (defaults to 2)")
+        )
+        .arg(
+            Arg::new("notify_timeout").long("notify-timeout")
+                .value_name("TIMEOUT_DURATION")
+                .requires("notify")
+                .help("Timeout of each notification attempt (5s)")
         )
         .arg(
             Arg::new("hw_type").long("hw-type")
@@ -181,6 +448,33 @@ pub fn build_args() -> Command {
                 .value_name("OPERATION_ID")
                 .help("Custom ARP operation ID")
         )
+        .arg(
+            Arg::new("user").long("user")
+                .value_name("USER_NAME")
+                .default_value("nobody")
+                .help("Unprivileged user used once the network socket is opened")
+        )
+        .arg(
+            Arg::new("group").long("group")
+                .value_name("GROUP_NAME")
+                .help("Unprivileged group used once the network socket is opened (defaults to the user group)")
+        )
+        .arg(
+            Arg::new("keep_privileges").long("keep-privileges")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["user", "group", "seccomp"])
+                .help("Keep root privileges and capabilities during the whole scan")
+        )
+        .arg(
+            Arg::new("seccomp").long("seccomp")
+                .action(ArgAction::SetTrue)
+                .help("Restrict system calls with a seccomp filter during the scan (Linux only)")
+        )
+        .arg(
+            Arg::new("dry_run").long("dry-run")
+                .action(ArgAction::SetTrue)
+                .help("Print the scan plan and exit without sending requests")
+        )
         .arg(
             Arg::new("packet_help").long("packet-help")
                 .action(ArgAction::SetTrue)
@@ -190,11 +484,152 @@ pub fn build_args() -> Command {
         .after_help(EXAMPLES_HELP)
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum OutputFormat {
     Plain,
     Json,
     Yaml,
-    Csv
+    Csv,
+    Xml,
+    Markdown,
+    Html
+}
+
+impl OutputFormat {
+
+    pub fn name(&self) -> &str {
+        match self {
+            OutputFormat::Plain => "plain",
+            OutputFormat::Json => "json",
+            OutputFormat::Yaml => "yaml",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Xml => "xml",
+            OutputFormat::Markdown => "markdown",
+            OutputFormat::Html => "html"
+        }
+    }
+}
+
+/**
+ * Order of the hosts in the scan results. Hosts without a value for the sort
+ * key are listed last, ties are ordered by IPv4 address.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortKey {
+    Ip,
+    Mac,
+    Vendor,
+    Hostname,
+    Latency
+}
+
+/**
+ * A column of the scan results. The name is used for exports (JSON keys, CSV
+ * headers) and for the '--columns' option, the title is used for tables.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResultColumn {
+    Ipv4,
+    Mac,
+    Hostname,
+    HostnameSource,
+    HostnameConfirmed,
+    Vendor,
+    DeviceClass,
+    FoundBy,
+    BaselineViolations,
+    OpenPorts,
+    DhcpHostname,
+    DhcpVendorClass,
+    DhcpParameters,
+    OsGuess,
+    Latency
+}
+
+impl ResultColumn {
+
+    /**
+     * Columns of JSON, YAML and CSV exports when no columns are selected.
+     */
+    pub const EXPORT_DEFAULT: [ResultColumn; 14] = [
+        ResultColumn::Ipv4, ResultColumn::Mac, ResultColumn::Hostname, ResultColumn::HostnameSource,
+        ResultColumn::HostnameConfirmed, ResultColumn::Vendor, ResultColumn::DeviceClass, ResultColumn::FoundBy,
+        ResultColumn::BaselineViolations, ResultColumn::OpenPorts, ResultColumn::DhcpHostname,
+        ResultColumn::DhcpVendorClass, ResultColumn::DhcpParameters, ResultColumn::OsGuess
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ResultColumn::Ipv4 => "ipv4",
+            ResultColumn::Mac => "mac",
+            ResultColumn::Hostname => "hostname",
+            ResultColumn::HostnameSource => "hostname_source",
+            ResultColumn::HostnameConfirmed => "hostname_confirmed",
+            ResultColumn::Vendor => "vendor",
+            ResultColumn::DeviceClass => "device_class",
+            ResultColumn::FoundBy => "found_by",
+            ResultColumn::BaselineViolations => "baseline_violations",
+            ResultColumn::OpenPorts => "open_ports",
+            ResultColumn::DhcpHostname => "dhcp_hostname",
+            ResultColumn::DhcpVendorClass => "dhcp_vendor_class",
+            ResultColumn::DhcpParameters => "dhcp_parameters",
+            ResultColumn::OsGuess => "os_guess",
+            ResultColumn::Latency => "latency_us"
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            ResultColumn::Ipv4 => "IPv4",
+            ResultColumn::Mac => "MAC",
+            ResultColumn::Hostname => "Hostname",
+            ResultColumn::HostnameSource => "Hostname source",
+            ResultColumn::HostnameConfirmed => "Confirmed",
+            ResultColumn::Vendor => "Vendor",
+            ResultColumn::DeviceClass => "Device class",
+            ResultColumn::FoundBy => "Found by",
+            ResultColumn::BaselineViolations => "Baseline",
+            ResultColumn::OpenPorts => "Open ports",
+            ResultColumn::DhcpHostname => "DHCP hostname",
+            ResultColumn::DhcpVendorClass => "DHCP vendor class",
+            ResultColumn::DhcpParameters => "DHCP parameters",
+            ResultColumn::OsGuess => "OS guess",
+            ResultColumn::Latency => "Latency"
+        }
+    }
+
+    fn parse(column_text: &str) -> Result<Self, String> {
+
+        let column = match column_text {
+            "ip" => Some(ResultColumn::Ipv4),
+            "class" => Some(ResultColumn::DeviceClass),
+            "baseline" => Some(ResultColumn::BaselineViolations),
+            "ports" => Some(ResultColumn::OpenPorts),
+            "latency" => Some(ResultColumn::Latency),
+            column_text => ResultColumn::EXPORT_DEFAULT.iter().chain([ResultColumn::Latency].iter())
+                .find(|column| column.name() == column_text)
+                .copied()
+        };
+        column.ok_or_else(|| format!("unknown column '{}'", column_text))
+    }
+}
+
+/**
+ * A lookup in the scan database, performed instead of a network scan.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum DatabaseQuery {
+    History { since: u64, until: u64 },
+    MacAddress(MacAddr)
+}
+
+/**
+ * A requested scan output, written to stdout when no file path is given.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputTarget {
+    pub format: OutputFormat,
+    pub path: Option<String>
 }
 
 pub enum ProfileType {
@@ -204,9 +639,85 @@ pub enum ProfileType {
     Chaos
 }
 
+impl ProfileType {
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "default" | "d" => Some(ProfileType::Default),
+            "fast" | "f" => Some(ProfileType::Fast),
+            "stealth" | "s" => Some(ProfileType::Stealth),
+            "chaos" | "c" => Some(ProfileType::Chaos),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            ProfileType::Default => "default",
+            ProfileType::Fast => "fast",
+            ProfileType::Stealth => "stealth",
+            ProfileType::Chaos => "chaos"
+        }
+    }
+}
+
+/**
+ * Sources used to find the hostname of a discovered host: reverse DNS (or a
+ * hosts file checked beforehand), queries sent to the host itself (mDNS,
+ * NetBIOS, LLMNR) and DHCP messages captured during the scan.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NameSource {
+    Dns,
+    Hosts,
+    Dhcp,
+    Mdns,
+    Netbios,
+    Llmnr
+}
+
+impl NameSource {
+
+    pub fn name(&self) -> &str {
+        match self {
+            NameSource::Dns => "dns",
+            NameSource::Hosts => "hosts",
+            NameSource::Dhcp => "dhcp",
+            NameSource::Mdns => "mdns",
+            NameSource::Netbios => "netbios",
+            NameSource::Llmnr => "llmnr"
+        }
+    }
+}
+
+/**
+ * ICMP requests sent to each target in addition to ARP requests. Some hosts
+ * ignore ICMP echo requests but still answer timestamp requests.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IcmpProbe {
+    Echo,
+    Timestamp
+}
+
+impl IcmpProbe {
+
+    pub fn name(&self) -> &str {
+        match self {
+            IcmpProbe::Echo => "echo",
+            IcmpProbe::Timestamp => "timestamp"
+        }
+    }
+}
+
+/**
+ * Scan timing constraints, the interval is expressed in microseconds between
+ * requests, the bandwidth in bits per second and the rate in packets per second.
+ */
 pub enum ScanTiming {
     Interval(u64),
-    Bandwidth(u64)
+    Bandwidth(u64),
+    PacketRate(u64)
 }
 
 pub struct ScanOptions {
@@ -214,23 +725,57 @@ pub struct ScanOptions {
     pub interface_name: Option<String>,
     pub interface_index: Option<u32>,
     pub network_range: Option<Vec<ipnetwork::IpNetwork>>,
+    pub inventory_file: Option<DeferredFile<Inventory>>,
+    pub baseline_file: Option<DeferredFile<Baseline>>,
     pub timeout_ms: u64,
     pub resolve_hostname: bool,
+    pub resolve_workers: usize,
+    pub resolve_timeout_ms: u64,
+    pub resolve_budget_ms: u64,
+    pub name_sources: Vec<NameSource>,
+    pub dns_servers: Vec<SocketAddrV4>,
+    pub hosts_file: Option<DeferredFile<HostsFile>>,
+    pub forward_confirm: bool,
+    pub listen_dhcp: bool,
+    pub icmp_probe: Option<IcmpProbe>,
+    pub probe_ports: Vec<u16>,
+    pub port_timeout_ms: u64,
     pub source_ipv4: Option<Ipv4Addr>,
     pub source_mac: Option<MacAddr>,
     pub destination_mac: Option<MacAddr>,
     pub vlan_id: Option<u16>,
     pub retry_count: usize,
     pub scan_timing: ScanTiming,
+    pub burst_size: u32,
     pub randomize_targets: bool,
-    pub output: OutputFormat,
+    pub random_seed: u64,
+    pub checkpoint_file: Option<String>,
+    pub resume_file: Option<DeferredFile<ScanCheckpoint>>,
+    pub database_file: Option<String>,
+    pub database_query: Option<DatabaseQuery>,
+    pub outputs: Vec<OutputTarget>,
+    pub sort_key: SortKey,
+    pub host_filters: Vec<HostFilter>,
+    pub columns: Option<Vec<ResultColumn>>,
+    pub stream_output: bool,
+    pub metrics_listen: Option<SocketAddr>,
+    pub scan_interval_ms: u64,
+    pub notify_sinks: Vec<NotificationSink>,
+    pub notify_retries: u32,
+    pub notify_timeout_ms: u64,
     pub oui_file: String,
+    pub fingerprint_file: String,
     pub hw_type: Option<ArpHardwareType>,
     pub hw_addr: Option<u8>,
     pub proto_type: Option<EtherType>,
     pub proto_addr: Option<u8>,
     pub arp_operation: Option<ArpOperation>,
     pub packet_help: bool,
+    pub dry_run: bool,
+    pub drop_privileges: bool,
+    pub unprivileged_user: String,
+    pub unprivileged_group: Option<String>,
+    pub seccomp_filter: bool,
 }
 
 impl ScanOptions {
@@ -284,29 +829,146 @@ impl ScanOptions {
         Ok(Some(networks))
     }
 
+    /**
+     * Parse the comma-separated list of hostname sources. The order of the
+     * list is kept, since the first source giving a name wins.
+     */
+    fn parse_name_sources(sources_text: &str) -> Result<Vec<NameSource>, String> {
+
+        let mut name_sources: Vec<NameSource> = vec![];
+        for source_text in sources_text.split(',').map(|source| source.trim()) {
+
+            let name_source = match source_text {
+                "dns" => NameSource::Dns,
+                "hosts" => NameSource::Hosts,
+                "mdns" => NameSource::Mdns,
+                "netbios" => NameSource::Netbios,
+                "llmnr" => NameSource::Llmnr,
+                _ => return Err(format!("unknown source '{}' (hosts/dns/mdns/netbios/llmnr)", source_text))
+            };
+
+            if !name_sources.contains(&name_source) {
+                name_sources.push(name_source);
+            }
+        }
+        Ok(name_sources)
+    }
+
+    /**
+     * Parse the comma-separated list of DNS servers, each server is an IPv4
+     * address with an optional port (53 by default).
+     */
+    fn parse_dns_servers(servers_text: &str) -> Result<Vec<SocketAddrV4>, String> {
+
+        servers_text.split(',').map(|server| server.trim()).map(|server_text| {
+
+            if let Ok(server_ipv4) = server_text.parse::<Ipv4Addr>() {
+                return Ok(SocketAddrV4::new(server_ipv4, DNS_PORT));
+            }
+            server_text.parse::<SocketAddrV4>().map_err(|_| format!("invalid server '{}'", server_text))
+        })
+        .collect()
+    }
+
+    /**
+     * Parse the comma-separated list of TCP ports, each item may be a single
+     * port or an inclusive range ('8000-8080'). Ports are sorted and deduped.
+     */
+    fn parse_ports(ports_text: &str) -> Result<Vec<u16>, String> {
+
+        let parse_port = |port_text: &str| match port_text.trim().parse::<u16>() {
+            Ok(port) if port > 0 => Ok(port),
+            _ => Err(format!("invalid port '{}'", port_text.trim()))
+        };
+
+        let mut ports: Vec<u16> = vec![];
+        for item in ports_text.split(',') {
+
+            match item.split_once('-') {
+                Some((first_text, last_text)) => {
+                    let (first_port, last_port) = (parse_port(first_text)?, parse_port(last_text)?);
+                    if first_port > last_port {
+                        return Err(format!("invalid port range '{}'", item.trim()));
+                    }
+                    ports.extend(first_port..=last_port);
+                },
+                None => ports.push(parse_port(item)?)
+            }
+        }
+
+        ports.sort_unstable();
+        ports.dedup();
+        Ok(ports)
+    }
+
+    /**
+     * Parse the requested outputs, given as 'format' or 'format:file'. Only
+     * one output can be printed on stdout, and each file is written once.
+     */
+    fn parse_outputs(output_requests: &[&String]) -> Result<Vec<OutputTarget>, String> {
+
+        let mut outputs: Vec<OutputTarget> = vec![];
+        for output_request in output_requests {
+
+            let (format_text, path) = match output_request.split_once(':') {
+                Some((_, "")) => return Err(format!("missing file path in '{}'", output_request)),
+                Some((format_text, path)) => (format_text, Some(path.to_string())),
+                None => (output_request.as_str(), None)
+            };
+            let format = match format_text {
+                "json" => OutputFormat::Json,
+                "yaml" => OutputFormat::Yaml,
+                "plain" | "text" => OutputFormat::Plain,
+                "csv" => OutputFormat::Csv,
+                "xml" => OutputFormat::Xml,
+                "markdown" | "md" => OutputFormat::Markdown,
+                "html" => OutputFormat::Html,
+                _ => return Err(format!("unknown format '{}'", format_text))
+            };
+
+            if outputs.iter().any(|output| output.path == path) {
+                return match path {
+                    Some(path) => Err(format!("file {} is used by several outputs", path)),
+                    None => Err("only one output can be printed on stdout".to_string())
+                };
+            }
+            outputs.push(OutputTarget { format, path });
+        }
+        Ok(outputs)
+    }
+
     /**
      * Computes scan timing constraints, as requested by the user through CLI
      * arguments. The scan timing constraints will be either expressed in bandwidth
-     * (bits per second) or interval between ARP requests (in milliseconds).
+     * (bits per second), packet rate (packets per second) or interval between
+     * ARP requests (in microseconds).
      */
     fn compute_scan_timing(matches: &ArgMatches, profile: &ProfileType) -> ScanTiming {
 
-        match (matches.get_one::<String>("bandwidth"), matches.get_one::<String>("interval")) {
-            (Some(bandwidth_text), None) => {
+        let timing_options = (matches.get_one::<String>("bandwidth"), matches.get_one::<String>("interval"), matches.get_one::<String>("rate"));
+        match timing_options {
+            (Some(bandwidth_text), None, None) => {
                 let bits_second: u64 = bandwidth_text.parse().unwrap_or_else(|err| {
                     eprintln!("Expected positive number, {}", err);
                     process::exit(1);
                 });
                 ScanTiming::Bandwidth(bits_second)
             },
-            (None, Some(interval_text)) => parse_to_milliseconds(interval_text).map(ScanTiming::Interval).unwrap_or_else(|err| {
+            (None, Some(interval_text), None) => parse_to_microseconds(interval_text).map(ScanTiming::Interval).unwrap_or_else(|err| {
                 eprintln!("Expected correct interval, {}", err);
                 process::exit(1);
             }),
+            (None, None, Some(rate_text)) => {
+                let packets_second: u64 = rate_text.parse().unwrap_or_else(|err| {
+                    eprintln!("Expected positive number for packet rate, {}", err);
+                    process::exit(1);
+                });
+                ScanTiming::PacketRate(packets_second)
+            },
             _ => match profile {
-                ProfileType::Stealth => ScanTiming::Interval(REQUEST_MS_INTERVAL * 2),
+                ProfileType::Stealth => ScanTiming::Interval(REQUEST_US_INTERVAL * 2),
                 ProfileType::Fast => ScanTiming::Interval(0),
-                _ => ScanTiming::Interval(REQUEST_MS_INTERVAL)
+                _ => ScanTiming::Interval(REQUEST_US_INTERVAL)
             }
         }
     }
@@ -314,27 +976,30 @@ impl ScanOptions {
     /**
      * Build a new 'ScanOptions' struct that will be used in the whole CLI such
      * as the network level, the display details and more. The scan options reflect
-     * user requests for the CLI and should not be mutated.
+     * user requests for the CLI and should not be mutated, apart from the scan
+     * plan of a resumed scan (restored once the files are loaded).
      */
-    pub fn new(matches: &ArgMatches) -> Arc<Self> {
+    pub fn new(matches: &ArgMatches) -> Self {
 
         let profile = match matches.get_one::<String>("profile") {
-            Some(output_request) => {
-
-                match output_request.as_ref() {
-                    "default" | "d" => ProfileType::Default,
-                    "fast" | "f" => ProfileType::Fast,
-                    "stealth" | "s" => ProfileType::Stealth,
-                    "chaos" | "c" => ProfileType::Chaos,
-                    _ => {
-                        eprintln!("Expected correct profile name (default/fast/stealth/chaos)");
-                        process::exit(1);
-                    }
-                }
-            },
+            Some(output_request) => ProfileType::parse(output_request).unwrap_or_else(|| {
+                eprintln!("Expected correct profile name (default/fast/stealth/chaos)");
+                process::exit(1);
+            }),
             None => ProfileType::Default
         };
 
+        // The checkpoint of a resumed scan is opened right away but only
+        // parsed once the privileges are dropped, the scan plan of the
+        // checkpoint is then restored (see 'restore_checkpoint').
+        let resume_file: Option<DeferredFile<ScanCheckpoint>> = matches.get_one::<String>("resume").map(|resume_path| {
+            DeferredFile::open(resume_path, "checkpoint file").unwrap_or_else(|err| {
+                eprintln!("Could not resume the scan");
+                eprintln!("{}", err);
+                process::exit(1);
+            })
+        });
+
         let interface_name = matches.get_one::<String>("interface").cloned();
         let interface_index_str = matches.get_one::<String>("index").cloned();
         let interface_index = interface_index_str.unwrap_or_default().parse::<u32>().ok();
@@ -343,7 +1008,30 @@ impl ScanOptions {
         let file_option = matches.get_one::<String>("file");
         let network_option = matches.get_one::<String>("network");
 
-        let network_range = ScanOptions::compute_networks(file_option, network_option).unwrap_or_else(|err| {
+        // Like the baseline file, the inventory file is opened right away but
+        // only parsed once the privileges are dropped. The scan targets are
+        // then computed from the inventory addresses (see 'target_networks').
+        let inventory_file: Option<DeferredFile<Inventory>> = matches.get_one::<String>("verify").map(|inventory_path| {
+            DeferredFile::open(inventory_path, "inventory file").unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                process::exit(1);
+            })
+        });
+
+        // The baseline file is opened right away but only parsed once the
+        // privileges are dropped (see 'load_files').
+        let baseline_file: Option<DeferredFile<Baseline>> = matches.get_one::<String>("baseline").map(|baseline_path| {
+            DeferredFile::open(baseline_path, "baseline file").unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                process::exit(1);
+            })
+        });
+
+        let network_range = match resume_file.is_some() || inventory_file.is_some() {
+            true => Ok(None),
+            false => ScanOptions::compute_networks(file_option, network_option)
+        };
+        let network_range = network_range.unwrap_or_else(|err| {
             eprintln!("Could not compute requested network range to scan");
             eprintln!("{}", err);
             process::exit(1);
@@ -363,6 +1051,104 @@ impl ScanOptions {
         // Hostnames will not be resolved in numeric mode or stealth profile
         let resolve_hostname = !matches.get_flag("numeric") && !matches!(profile, ProfileType::Stealth);
 
+        let resolve_workers = match matches.get_one::<String>("resolve_workers") {
+            Some(workers_text) => {
+
+                match workers_text.parse::<usize>() {
+                    Ok(worker_count) if worker_count > 0 => worker_count,
+                    _ => {
+                        eprintln!("Expected strictly positive number for hostname lookup workers");
+                        process::exit(1);
+                    }
+                }
+            },
+            None => RESOLVE_WORKERS_DEFAULT
+        };
+
+        let resolve_timeout_ms: u64 = match matches.get_one::<String>("resolve_timeout") {
+            Some(timeout_text) => parse_to_milliseconds(timeout_text).unwrap_or_else(|err| {
+                eprintln!("Expected correct hostname lookup timeout, {}", err);
+                process::exit(1);
+            }),
+            None => RESOLVE_TIMEOUT_MS_DEFAULT
+        };
+
+        let resolve_budget_ms: u64 = match matches.get_one::<String>("resolve_budget") {
+            Some(budget_text) => parse_to_milliseconds(budget_text).unwrap_or_else(|err| {
+                eprintln!("Expected correct hostname resolution budget, {}", err);
+                process::exit(1);
+            }),
+            None => RESOLVE_BUDGET_MS_DEFAULT
+        };
+
+        // The hosts file is read by its own name source, which is checked
+        // before DNS unless other sources are requested.
+        let has_hosts_file = matches.contains_id("hosts_file");
+        let name_sources: Vec<NameSource> = match matches.get_one::<String>("name_sources") {
+            Some(sources_text) => ScanOptions::parse_name_sources(sources_text).unwrap_or_else(|err| {
+                eprintln!("Expected correct hostname sources, {}", err);
+                process::exit(1);
+            }),
+            None if has_hosts_file => vec![NameSource::Hosts, NameSource::Dns],
+            None => vec![NameSource::Dns]
+        };
+
+        if name_sources.contains(&NameSource::Hosts) != has_hosts_file {
+            eprintln!("The hosts name source and --hosts-file should be given together");
+            process::exit(1);
+        }
+
+        let dns_servers: Vec<SocketAddrV4> = match matches.get_one::<String>("dns_server") {
+            Some(servers_text) => ScanOptions::parse_dns_servers(servers_text).unwrap_or_else(|err| {
+                eprintln!("Expected correct DNS servers, {}", err);
+                process::exit(1);
+            }),
+            None => vec![]
+        };
+
+        // The hosts file is opened right away, the scan may not be able to
+        // open it anymore once privileges are dropped.
+        let hosts_file: Option<DeferredFile<HostsFile>> = matches.get_one::<String>("hosts_file").map(|hosts_path| {
+            DeferredFile::open(hosts_path, "hosts file").unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                process::exit(1);
+            })
+        });
+
+        let forward_confirm = matches.get_flag("forward_confirm");
+        let listen_dhcp = matches.get_flag("dhcp");
+
+        let icmp_probe = match matches.get_one::<String>("icmp") {
+            Some(probe_request) => {
+
+                match probe_request.as_ref() {
+                    "echo" => Some(IcmpProbe::Echo),
+                    "timestamp" => Some(IcmpProbe::Timestamp),
+                    _ => {
+                        eprintln!("Expected correct ICMP probe (echo/timestamp)");
+                        process::exit(1);
+                    }
+                }
+            },
+            None => None
+        };
+
+        let probe_ports: Vec<u16> = match matches.get_one::<String>("ports") {
+            Some(ports_text) => ScanOptions::parse_ports(ports_text).unwrap_or_else(|err| {
+                eprintln!("Expected correct TCP ports, {}", err);
+                process::exit(1);
+            }),
+            None => vec![]
+        };
+
+        let port_timeout_ms: u64 = match matches.get_one::<String>("port_timeout") {
+            Some(timeout_text) => parse_to_milliseconds(timeout_text).unwrap_or_else(|err| {
+                eprintln!("Expected correct TCP connection timeout, {}", err);
+                process::exit(1);
+            }),
+            None => PORT_TIMEOUT_MS_DEFAULT
+        };
+
         let source_ipv4: Option<Ipv4Addr> = match matches.get_one::<String>("source_ip") {
             Some(source_ip) => {
                 
@@ -419,6 +1205,16 @@ impl ScanOptions {
             None => None
         };
 
+        // ICMP requests are unicast IP packets, they can only be tagged with a
+        // VLAN when the destination MAC is known (the kernel does not tag them)
+        let has_known_mac = destination_mac.is_some() || matches.contains_id("verify");
+        if icmp_probe.is_some() && vlan_id.is_some() && !has_known_mac {
+            build_args().error(
+                ErrorKind::ArgumentConflict,
+                "ICMP requests with a VLAN need a known destination MAC (--dest-mac or --verify)"
+            ).exit();
+        }
+
         let retry_count = match matches.get_one::<String>("retry_count") {
             Some(retry_count) => {
     
@@ -438,30 +1234,157 @@ impl ScanOptions {
 
         let scan_timing: ScanTiming = ScanOptions::compute_scan_timing(matches, &profile);
 
-        let output = match matches.get_one::<String>("output") {
-            Some(output_request) => {
+        let burst_size = match matches.get_one::<String>("burst") {
+            Some(burst_text) => {
 
-                match output_request.as_ref() {
-                    "json" => OutputFormat::Json,
-                    "yaml" => OutputFormat::Yaml,
-                    "plain" | "text" => OutputFormat::Plain,
-                    "csv" => OutputFormat::Csv,
+                match burst_text.parse::<u32>() {
+                    Ok(burst_size) if burst_size > 0 => burst_size,
                     _ => {
-                        eprintln!("Expected correct output format (json/yaml/plain)");
+                        eprintln!("Expected strictly positive number for burst size");
+                        process::exit(1);
+                    }
+                }
+            },
+            None => BURST_SIZE_DEFAULT
+        };
+
+        let output_requests: Vec<&String> = matches.get_many::<String>("output").unwrap_or_default().collect();
+        let outputs = match output_requests.is_empty() {
+            true => vec![OutputTarget { format: OutputFormat::Plain, path: None }],
+            false => ScanOptions::parse_outputs(&output_requests).unwrap_or_else(|err| {
+                eprintln!("Expected correct output format (json/yaml/plain/csv/xml/markdown/html), {}", err);
+                process::exit(1);
+            })
+        };
+
+        let sort_key = match matches.get_one::<String>("sort").map(|sort_text| sort_text.as_str()) {
+            Some("mac") => SortKey::Mac,
+            Some("vendor") => SortKey::Vendor,
+            Some("hostname") => SortKey::Hostname,
+            Some("latency") => SortKey::Latency,
+            _ => SortKey::Ip
+        };
+
+        let host_filters: Vec<HostFilter> = matches.get_many::<String>("filter").unwrap_or_default().map(|filter_text| {
+            HostFilter::parse(filter_text).unwrap_or_else(|err| {
+                eprintln!("Expected correct host filter, {}", err);
+                process::exit(1);
+            })
+        }).collect();
+
+        let columns: Option<Vec<ResultColumn>> = matches.get_one::<String>("columns").map(|columns_text| {
+            columns_text.split(',').map(|column_text| ResultColumn::parse(column_text.trim())).collect::<Result<Vec<ResultColumn>, String>>().unwrap_or_else(|err| {
+                eprintln!("Expected correct column list, {}", err);
+                process::exit(1);
+            })
+        });
+
+        // Streamed hosts are written line by line on stdout, which is not
+        // possible in a YAML document or a CSV file with a single header.
+        let stream_output = matches.get_flag("stream");
+        let stdout_format = outputs.iter().find(|output| output.path.is_none()).map(|output| output.format);
+        if stream_output && !matches!(stdout_format, Some(OutputFormat::Plain) | Some(OutputFormat::Json)) {
+            eprintln!("Streaming is only available with a plain or JSON output on stdout");
+            process::exit(1);
+        }
+
+        let metrics_listen: Option<SocketAddr> = match matches.get_one::<String>("metrics") {
+            Some(listen_text) => match listen_text.parse::<SocketAddr>() {
+                Ok(listen_address) => Some(listen_address),
+                Err(_) => {
+                    eprintln!("Expected valid listen address for metrics (e.g. 127.0.0.1:9640)");
+                    process::exit(1);
+                }
+            },
+            None => None
+        };
+
+        let scan_interval_ms: u64 = match matches.get_one::<String>("scan_interval") {
+            Some(interval_text) => parse_to_milliseconds(interval_text).unwrap_or_else(|err| {
+                eprintln!("Expected correct scan interval, {}", err);
+                process::exit(1);
+            }),
+            None => SCAN_INTERVAL_MS_DEFAULT
+        };
+
+        let notify_sinks: Vec<NotificationSink> = matches.get_many::<String>("notify").unwrap_or_default().map(|sink_text| {
+            notify::parse_sink(sink_text).unwrap_or_else(|err| {
+                eprintln!("Expected correct notification sink, {}", err);
+                process::exit(1);
+            })
+        }).collect();
+
+        let notify_retries: u32 = match matches.get_one::<String>("notify_retries") {
+            Some(retries_text) => retries_text.parse::<u32>().unwrap_or_else(|err| {
+                eprintln!("Expected positive number for notification retries, {}", err);
+                process::exit(1);
+            }),
+            None => NOTIFY_RETRIES_DEFAULT
+        };
+
+        let notify_timeout_ms: u64 = match matches.get_one::<String>("notify_timeout") {
+            Some(timeout_text) => parse_to_milliseconds(timeout_text).unwrap_or_else(|err| {
+                eprintln!("Expected correct notification timeout, {}", err);
+                process::exit(1);
+            }),
+            None => NOTIFY_TIMEOUT_MS_DEFAULT
+        };
+
+        let random_seed: Option<u64> = match matches.get_one::<String>("seed") {
+            Some(seed_text) => {
+
+                match seed_text.parse::<u64>() {
+                    Ok(seed) => Some(seed),
+                    Err(_) => {
+                        eprintln!("Expected positive number for random seed");
                         process::exit(1);
                     }
                 }
             },
-            None => OutputFormat::Plain
+            None => None
         };
 
-        let randomize_targets = matches.get_flag("random") || matches!(profile, ProfileType::Stealth | ProfileType::Chaos);
+        let randomize_targets = matches.get_flag("random") || random_seed.is_some() || matches!(profile, ProfileType::Stealth | ProfileType::Chaos);
+        let random_seed = random_seed.unwrap_or_else(rand::random);
+
+        let checkpoint_file = matches.get_one::<String>("checkpoint").or(matches.get_one::<String>("resume")).cloned();
+
+        let database_file = matches.get_one::<String>("database").cloned();
+
+        let database_query: Option<DatabaseQuery> = match (matches.get_flag("history"), matches.get_one::<String>("query")) {
+            (true, _) => {
+                let parse_window_start = |arg_name: &str, default_ms: u64| match matches.get_one::<String>(arg_name) {
+                    Some(duration_text) => parse_to_milliseconds(duration_text).unwrap_or_else(|err| {
+                        eprintln!("Expected correct history window for --{}, {}", arg_name, err);
+                        process::exit(1);
+                    }),
+                    None => default_ms
+                };
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0);
+                let since = now.saturating_sub(parse_window_start("since", HISTORY_WINDOW_MS_DEFAULT) / 1000);
+                let until = now.saturating_sub(parse_window_start("until", 0) / 1000);
+                Some(DatabaseQuery::History { since, until })
+            },
+            (false, Some(mac_address)) => match mac_address.parse::<MacAddr>() {
+                Ok(parsed_mac) => Some(DatabaseQuery::MacAddress(parsed_mac)),
+                Err(_) => {
+                    eprintln!("Expected valid MAC address for the database query");
+                    process::exit(1);
+                }
+            },
+            (false, None) => None
+        };
 
         let oui_file: String = match matches.get_one::<String>("oui-file") {
             Some(file) => file.to_string(),
             None => "/usr/share/arp-scan/ieee-oui.csv".to_string()
         };
 
+        let fingerprint_file: String = match matches.get_one::<String>("fingerprint-file") {
+            Some(file) => file.to_string(),
+            None => "/usr/share/arp-scan/device-fingerprints.csv".to_string()
+        };
+
         let hw_type = match matches.get_one::<String>("hw_type") {
             Some(hw_type_text) => {
     
@@ -533,41 +1456,235 @@ impl ScanOptions {
         };
 
         let packet_help = matches.get_flag("packet_help");
+        let dry_run = matches.get_flag("dry_run");
+
+        // Privileges are dropped by default once the datalink channel is opened,
+        // the user & group options define the account used for the rest of the scan.
+        let drop_privileges = !matches.get_flag("keep_privileges");
+        let unprivileged_user = matches.get_one::<String>("user").cloned().unwrap_or_else(|| "nobody".to_string());
+        let unprivileged_group = matches.get_one::<String>("group").cloned();
+        let seccomp_filter = matches.get_flag("seccomp");
+        if seccomp_filter && !cfg!(target_os = "linux") {
+            eprintln!("Seccomp filters are only supported on Linux");
+            process::exit(1);
+        }
+
+        // The seccomp filter denies new programs, command hooks could not run
+        if seccomp_filter && notify_sinks.iter().any(|sink| matches!(sink, NotificationSink::Command(_))) {
+            eprintln!("Command notification hooks are not available with a seccomp filter");
+            process::exit(1);
+        }
     
-        Arc::new(ScanOptions {
+        ScanOptions {
             profile,
             interface_name,
             interface_index,
             network_range,
+            inventory_file,
+            baseline_file,
             timeout_ms,
             resolve_hostname,
+            resolve_workers,
+            resolve_timeout_ms,
+            resolve_budget_ms,
+            name_sources,
+            dns_servers,
+            hosts_file,
+            forward_confirm,
+            listen_dhcp,
+            icmp_probe,
+            probe_ports,
+            port_timeout_ms,
             source_ipv4,
             destination_mac,
             source_mac,
             vlan_id,
             retry_count,
             scan_timing,
+            burst_size,
             randomize_targets,
-            output,
+            random_seed,
+            checkpoint_file,
+            resume_file,
+            database_file,
+            database_query,
+            outputs,
+            sort_key,
+            host_filters,
+            columns,
+            stream_output,
+            metrics_listen,
+            scan_interval_ms,
+            notify_sinks,
+            notify_retries,
+            notify_timeout_ms,
             oui_file,
+            fingerprint_file,
             hw_type,
             hw_addr,
             proto_type,
             proto_addr,
             arp_operation,
             packet_help,
-        })
+            dry_run,
+            drop_privileges,
+            unprivileged_user,
+            unprivileged_group,
+            seccomp_filter,
+        }
+    }
+
+    /**
+     * The output printed on stdout, if any (other outputs are written to
+     * files).
+     */
+    pub fn stdout_format(&self) -> Option<OutputFormat> {
+        self.outputs.iter().find(|output| output.path.is_none()).map(|output| output.format)
+    }
+
+    /**
+     * Columns of JSON, YAML and CSV exports, all host details are exported
+     * unless columns are selected.
+     */
+    pub fn export_columns(&self) -> &[ResultColumn] {
+        match &self.columns {
+            Some(columns) => columns,
+            None => &ResultColumn::EXPORT_DEFAULT
+        }
+    }
+
+    /**
+     * Scan details and progress lines are written on stderr, unless stdout
+     * holds a machine-readable output (which would probably be piped).
+     */
+    pub fn is_verbose(&self) -> bool {
+        matches!(self.stdout_format(), Some(OutputFormat::Plain) | None)
+    }
+
+    /**
+     * Seed used to permute the scan targets, no seed is returned when the
+     * targets should be scanned in ascending order.
+     */
+    pub fn target_seed(&self) -> Option<u64> {
+        match self.randomize_targets {
+            true => Some(self.random_seed),
+            false => None
+        }
     }
 
-    pub fn is_plain_output(&self) -> bool {
+    /**
+     * Parse the files opened with the scan options, this is done once the
+     * privileges are dropped so that file contents are never parsed as root.
+     */
+    pub fn load_files(&mut self) -> Result<(), String> {
+
+        if let Some(baseline_file) = &self.baseline_file {
+            baseline_file.load(Baseline::parse)?;
+        }
+        if let Some(hosts_file) = &self.hosts_file {
+            hosts_file.load(|content| Ok(HostsFile::parse(content)))?;
+        }
+        if let Some(inventory_file) = &self.inventory_file {
+            inventory_file.load(Inventory::parse)?;
+        }
+        if let Some(resume_file) = &self.resume_file {
+            resume_file.load(checkpoint::parse_checkpoint)?;
+            self.restore_checkpoint()?;
+        }
+        Ok(())
+    }
+
+    /**
+     * A resumed scan continues the scan plan of the checkpoint, with the
+     * options of the interrupted scan. The target order must stay the same,
+     * otherwise the checkpoint position would not match the targets already
+     * scanned.
+     */
+    fn restore_checkpoint(&mut self) -> Result<(), String> {
+
+        let checkpoint = match self.resume_checkpoint() {
+            Some(checkpoint) => checkpoint,
+            None => return Ok(())
+        };
+        let network_range = checkpoint.parse_networks()?;
+        let random_seed = checkpoint.random_seed;
+        let options = checkpoint.options.clone();
+        let profile = ProfileType::parse(&options.profile).ok_or_else(|| {
+            format!("Invalid profile '{}' in checkpoint", options.profile)
+        })?;
+
+        self.network_range = Some(network_range);
+        self.randomize_targets = random_seed.is_some();
+        self.random_seed = random_seed.unwrap_or_default();
+        self.scan_timing = options.scan_timing()?;
+        self.burst_size = options.burst_size;
+        self.timeout_ms = options.timeout_ms;
+        self.retry_count = options.retry_count;
+        self.resolve_hostname = self.resolve_hostname && !matches!(profile, ProfileType::Stealth);
+        self.profile = profile;
+        Ok(())
+    }
+
+    /**
+     * A resumed scan continues on the interface of the checkpoint, unless
+     * another interface has been explicitly requested. The interface is
+     * selected before the checkpoint is loaded, since the datalink channel is
+     * opened with root privileges.
+     */
+    pub fn check_resumed_interface(&self, interface_name: &str) -> Result<(), String> {
+
+        let has_requested_interface = self.interface_name.is_some() || self.interface_index.is_some();
+        match self.resume_checkpoint() {
+            Some(checkpoint) if !has_requested_interface && checkpoint.interface != interface_name => {
+                Err(format!("The checkpoint was saved on interface {}, use '-i {}' to resume the scan", checkpoint.interface, checkpoint.interface))
+            },
+            _ => Ok(())
+        }
+    }
+
+    /**
+     * The networks to scan, if any were requested. Inventory targets are only
+     * known once the files are loaded, the interface networks are used when
+     * no network is given.
+     */
+    pub fn target_networks(&self) -> Option<Vec<ipnetwork::IpNetwork>> {
+        match self.inventory() {
+            Some(inventory) => Some(inventory.networks()),
+            None => self.network_range.clone()
+        }
+    }
+
+    pub fn baseline(&self) -> Option<&Baseline> {
+        self.baseline_file.as_ref().and_then(DeferredFile::get)
+    }
 
-        matches!(&self.output, OutputFormat::Plain)
+    pub fn hosts(&self) -> Option<&HostsFile> {
+        self.hosts_file.as_ref().and_then(DeferredFile::get)
+    }
+
+    pub fn inventory(&self) -> Option<&Inventory> {
+        self.inventory_file.as_ref().and_then(DeferredFile::get)
+    }
+
+    pub fn resume_checkpoint(&self) -> Option<&ScanCheckpoint> {
+        self.resume_file.as_ref().and_then(DeferredFile::get)
     }
 
     pub fn has_vlan(&self) -> bool {
         self.vlan_id.is_some()
     }
 
+    /**
+     * Non-standard ARP hardware types or operations are only answered by some
+     * network stacks, which makes the scan a fingerprinting probe.
+     */
+    pub fn uses_custom_probe(&self) -> bool {
+
+        let custom_hw_type = self.hw_type.is_some_and(|hw_type| hw_type != ArpHardwareTypes::Ethernet);
+        let custom_operation = self.arp_operation.is_some_and(|operation| operation != ArpOperations::Request);
+        custom_hw_type || custom_operation
+    }
+
     pub fn request_protocol_print(&self) -> bool {
         self.packet_help
     }
@@ -689,4 +1806,127 @@ mod tests {
         assert_eq!(networks, Err("Expected valid IPv4 network range (invalid address: no-network)".to_string()));
     }
 
+    #[test]
+    fn should_keep_name_source_order() {
+
+        let name_sources = ScanOptions::parse_name_sources("netbios, mdns,dns,mdns");
+
+        assert_eq!(name_sources, Ok(vec![NameSource::Netbios, NameSource::Mdns, NameSource::Dns]));
+        assert_eq!(ScanOptions::parse_name_sources("mdns,hosts"), Ok(vec![NameSource::Mdns, NameSource::Hosts]));
+    }
+
+    #[test]
+    fn should_parse_dns_servers() {
+
+        let dns_servers = ScanOptions::parse_dns_servers("10.0.0.53, 10.0.0.54:5353");
+
+        assert_eq!(dns_servers, Ok(vec![
+            SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 53), 53),
+            SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 54), 5353)
+        ]));
+        assert_eq!(ScanOptions::parse_dns_servers("dns.lab"), Err("invalid server 'dns.lab'".to_string()));
+    }
+
+    #[test]
+    fn should_fail_unknown_name_source() {
+
+        let name_sources = ScanOptions::parse_name_sources("dns,wins");
+
+        assert_eq!(name_sources, Err("unknown source 'wins' (hosts/dns/mdns/netbios/llmnr)".to_string()));
+    }
+
+    #[test]
+    fn should_parse_port_ranges() {
+
+        let ports = ScanOptions::parse_ports("443, 22,8000-8003,80,22");
+
+        assert_eq!(ports, Ok(vec![22, 80, 443, 8000, 8001, 8002, 8003]));
+        assert_eq!(ScanOptions::parse_ports("22,0"), Err("invalid port '0'".to_string()));
+        assert_eq!(ScanOptions::parse_ports("90-80"), Err("invalid port range '90-80'".to_string()));
+    }
+
+    #[test]
+    fn should_parse_output_targets() {
+
+        let output_requests = ["plain".to_string(), "json:results.json".to_string(), "csv:hosts.csv".to_string()];
+        let outputs = ScanOptions::parse_outputs(&output_requests.iter().collect::<Vec<&String>>());
+
+        assert_eq!(outputs, Ok(vec![
+            OutputTarget { format: OutputFormat::Plain, path: None },
+            OutputTarget { format: OutputFormat::Json, path: Some("results.json".to_string()) },
+            OutputTarget { format: OutputFormat::Csv, path: Some("hosts.csv".to_string()) }
+        ]));
+    }
+
+    #[test]
+    fn should_reject_conflicting_outputs() {
+
+        let parse = |output_requests: &[&str]| {
+            let output_requests: Vec<String> = output_requests.iter().map(|request| request.to_string()).collect();
+            ScanOptions::parse_outputs(&output_requests.iter().collect::<Vec<&String>>())
+        };
+
+        assert_eq!(parse(&["json", "yaml"]), Err("only one output can be printed on stdout".to_string()));
+        assert_eq!(parse(&["json:out.txt", "csv:out.txt"]), Err("file out.txt is used by several outputs".to_string()));
+        assert_eq!(parse(&["pdf:out.pdf"]), Err("unknown format 'pdf'".to_string()));
+        assert_eq!(parse(&["csv:"]), Err("missing file path in 'csv:'".to_string()));
+    }
+
+    #[test]
+    fn should_parse_result_columns() {
+
+        assert_eq!(ResultColumn::parse("ip"), Ok(ResultColumn::Ipv4));
+        assert_eq!(ResultColumn::parse("dhcp_vendor_class"), Ok(ResultColumn::DhcpVendorClass));
+        assert_eq!(ResultColumn::parse("latency"), Ok(ResultColumn::Latency));
+        assert_eq!(ResultColumn::parse("latency_us"), Ok(ResultColumn::Latency));
+        assert_eq!(ResultColumn::parse("rtt"), Err("unknown column 'rtt'".to_string()));
+    }
+
+    #[test]
+    fn should_compute_inventory_targets_once_loaded() {
+
+        let path = std::env::temp_dir().join(format!("arp-scan-inventory-{}.csv", process::id()));
+        std::fs::write(&path, "10.20.0.11,00:11:22:33:44:11\n10.20.0.10,00:11:22:33:44:10\n").unwrap();
+
+        let command_line = ["arp-scan", "--verify", path.to_str().unwrap()];
+        let mut options = ScanOptions::new(&build_args().get_matches_from(command_line));
+        assert_eq!(options.target_networks(), None);
+
+        options.load_files().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let target_networks: Vec<IpNetwork> = vec![
+            IpNetwork::V4(Ipv4Network::new(Ipv4Addr::new(10, 20, 0, 10), 32).unwrap()),
+            IpNetwork::V4(Ipv4Network::new(Ipv4Addr::new(10, 20, 0, 11), 32).unwrap())
+        ];
+        assert_eq!(options.target_networks(), Some(target_networks));
+    }
+
+    #[test]
+    fn should_restore_options_of_resumed_scan() {
+
+        let path = std::env::temp_dir().join(format!("arp-scan-resume-{}.json", process::id()));
+        let path_text = path.to_str().unwrap();
+
+        let command_line = ["arp-scan", "-n", "10.20.0.0/24", "-r", "3", "--profile", "stealth", "--burst", "4"];
+        let interrupted_options = ScanOptions::new(&build_args().get_matches_from(command_line));
+        let network: IpNetwork = "10.20.0.0/24".parse().unwrap();
+        let mut writer = checkpoint::CheckpointWriter::new(path_text, "eth0", &[&network], &interrupted_options);
+        writer.try_save(1, 30).unwrap();
+
+        // The resumed scan is started without the retry count or the profile
+        let mut options = ScanOptions::new(&build_args().get_matches_from(["arp-scan", "--resume", path_text]));
+        options.load_files().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(options.retry_count, 3);
+        assert_eq!(options.burst_size, 4);
+        assert_eq!(options.profile.name(), "stealth");
+        assert!(matches!(options.scan_timing, ScanTiming::Interval(interval_us) if interval_us == REQUEST_US_INTERVAL * 2));
+        assert!(!options.resolve_hostname);
+        assert_eq!(options.target_seed(), interrupted_options.target_seed());
+        assert_eq!(options.target_networks(), Some(vec![network]));
+        assert!(options.resume_checkpoint().is_some_and(|checkpoint| checkpoint.retry_round == 1));
+    }
+
 }
@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+
+use ipnetwork::IpNetwork;
+use pnet_datalink::MacAddr;
+use serde::Deserialize;
+
+use crate::network::TargetDetails;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High
+}
+
+impl Severity {
+
+    pub fn name(&self) -> &str {
+        match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high"
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationKind {
+    UnknownMac,
+    UnknownVendor,
+    OutsideRange,
+    Mismatch
+}
+
+impl ViolationKind {
+
+    pub fn name(&self) -> &str {
+        match self {
+            ViolationKind::UnknownMac => "unknown_mac",
+            ViolationKind::UnknownVendor => "unknown_vendor",
+            ViolationKind::OutsideRange => "outside_range",
+            ViolationKind::Mismatch => "mismatch"
+        }
+    }
+
+    /**
+     * Default severities, a pinned host answering with another MAC address
+     * or a host outside the allowed ranges is more suspicious than a new
+     * device from an unexpected vendor.
+     */
+    fn default_severity(&self) -> Severity {
+        match self {
+            ViolationKind::UnknownMac => Severity::Medium,
+            ViolationKind::UnknownVendor => Severity::Low,
+            ViolationKind::OutsideRange => Severity::High,
+            ViolationKind::Mismatch => Severity::High
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BaselineViolation {
+    pub kind: ViolationKind,
+    pub severity: Severity
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PinnedHostEntry {
+    ipv4: Ipv4Addr,
+    mac: String
+}
+
+/**
+ * Content of a baseline YAML file. All lists are optional, an empty list
+ * does not restrict the scan results.
+ */
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BaselineFile {
+    #[serde(default)]
+    networks: Vec<String>,
+    #[serde(default)]
+    macs: Vec<String>,
+    #[serde(default)]
+    vendors: Vec<String>,
+    #[serde(default)]
+    hosts: Vec<PinnedHostEntry>,
+    #[serde(default)]
+    severities: HashMap<ViolationKind, Severity>,
+    fail_on: Option<Severity>
+}
+
+/**
+ * An allow-list of the expected hosts: allowed IPv4 ranges, MAC and vendor
+ * patterns (with '*' wildcards) and pinned IPv4/MAC pairs. Scan results are
+ * compared with the baseline and each violation gets a severity.
+ */
+#[derive(Clone, Debug)]
+pub struct Baseline {
+    networks: Vec<IpNetwork>,
+    mac_patterns: Vec<String>,
+    vendor_patterns: Vec<String>,
+    pinned_hosts: Vec<(Ipv4Addr, MacAddr)>,
+    severities: HashMap<ViolationKind, Severity>,
+    pub fail_on: Severity
+}
+
+impl Baseline {
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+
+        let baseline_file: BaselineFile = serde_yaml::from_str(content).map_err(|err| err.to_string())?;
+
+        let networks = baseline_file.networks.iter().map(|network_text| {
+            network_text.parse::<IpNetwork>().map_err(|err| format!("invalid network '{}' ({})", network_text, err))
+        }).collect::<Result<Vec<IpNetwork>, String>>()?;
+
+        let pinned_hosts = baseline_file.hosts.iter().map(|host| {
+            host.mac.parse::<MacAddr>()
+                .map(|mac| (host.ipv4, mac))
+                .map_err(|_| format!("invalid MAC '{}' for host {}", host.mac, host.ipv4))
+        }).collect::<Result<Vec<(Ipv4Addr, MacAddr)>, String>>()?;
+
+        Ok(Baseline {
+            networks,
+            mac_patterns: baseline_file.macs.iter().map(|pattern| pattern.to_lowercase()).collect(),
+            vendor_patterns: baseline_file.vendors.iter().map(|pattern| pattern.to_lowercase()).collect(),
+            pinned_hosts,
+            severities: baseline_file.severities,
+            fail_on: baseline_file.fail_on.unwrap_or(Severity::Low)
+        })
+    }
+
+    fn build_violation(&self, kind: ViolationKind) -> BaselineViolation {
+        BaselineViolation {
+            kind,
+            severity: self.severities.get(&kind).copied().unwrap_or_else(|| kind.default_severity())
+        }
+    }
+
+    /**
+     * Compare a single host with the baseline. Pinned MAC addresses are
+     * always known, even if they do not match any MAC pattern.
+     */
+    pub fn check_host(&self, target_detail: &TargetDetails) -> Vec<BaselineViolation> {
+
+        let mut violations: Vec<BaselineViolation> = vec![];
+
+        let ipv4 = target_detail.ipv4;
+        let mac = target_detail.mac;
+        let mac_text = mac.to_string();
+
+        // A MAC address may be pinned to several IPv4 addresses (multi-homed
+        // devices), only report a mismatch if this IPv4 address belongs to
+        // another MAC address or if none of the MAC address pins is this one
+        let is_pinned_mac = self.pinned_hosts.iter().any(|(_, pinned_mac)| *pinned_mac == mac);
+        let is_pinned_elsewhere = self.pinned_hosts.iter().any(|(pinned_ipv4, pinned_mac)| {
+            *pinned_ipv4 == ipv4 && *pinned_mac != mac
+        });
+        let is_pinned_here = self.pinned_hosts.iter().any(|(pinned_ipv4, pinned_mac)| {
+            *pinned_ipv4 == ipv4 && *pinned_mac == mac
+        });
+        let is_mismatch = is_pinned_elsewhere || (is_pinned_mac && !is_pinned_here);
+
+        if is_mismatch {
+            violations.push(self.build_violation(ViolationKind::Mismatch));
+        }
+        if !self.networks.is_empty() && !self.networks.iter().any(|network| network.contains(ipv4.into())) {
+            violations.push(self.build_violation(ViolationKind::OutsideRange));
+        }
+        if !self.mac_patterns.is_empty() && !is_pinned_mac && !self.mac_patterns.iter().any(|pattern| matches_pattern(pattern, &mac_text)) {
+            violations.push(self.build_violation(ViolationKind::UnknownMac));
+        }
+
+        let vendor = target_detail.vendor.as_deref().unwrap_or_default().to_lowercase();
+        if !self.vendor_patterns.is_empty() && !self.vendor_patterns.iter().any(|pattern| matches_pattern(pattern, &vendor)) {
+            violations.push(self.build_violation(ViolationKind::UnknownVendor));
+        }
+
+        violations
+    }
+
+    /**
+     * Check if a violation should fail the scan (non-zero exit code).
+     */
+    pub fn is_failing(&self, violation: &BaselineViolation) -> bool {
+        violation.severity >= self.fail_on
+    }
+}
+
+/**
+ * Match a text with a pattern where '*' matches any sequence of characters.
+ * Both texts are expected to be lowercase already.
+ */
+fn matches_pattern(pattern: &str, text: &str) -> bool {
+
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let (first_part, last_part) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first_part) || !text[first_part.len()..].ends_with(last_part) {
+        return false;
+    }
+
+    let mut remaining = &text[first_part.len()..text.len() - last_part.len()];
+    for middle_part in &parts[1..parts.len() - 1] {
+        match remaining.find(middle_part) {
+            Some(position) => remaining = &remaining[position + middle_part.len()..],
+            None => return false
+        }
+    }
+    true
+}
+
+/**
+ * Compare all discovered hosts with the baseline.
+ */
+pub fn check_targets(target_details: &mut [TargetDetails], baseline: &Baseline) {
+
+    for target_detail in target_details.iter_mut() {
+        target_detail.violations = baseline.check_host(target_detail);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const BASELINE_CONTENT: &str = "
+networks:
+  - 10.20.0.0/24
+macs:
+  - '00:11:22:*'
+vendors:
+  - 'dell*'
+  - '*hewlett*'
+hosts:
+  - ipv4: 10.20.0.1
+    mac: 'aa:bb:cc:00:00:01'
+severities:
+  unknown_vendor: medium
+fail_on: medium
+";
+
+    fn build_target(ipv4: Ipv4Addr, mac: &str, vendor: Option<&str>) -> TargetDetails {
+        TargetDetails {
+            vendor: vendor.map(|vendor| vendor.to_string()),
+            ..TargetDetails::build(ipv4, mac.parse().unwrap())
+        }
+    }
+
+    fn list_kinds(violations: Vec<BaselineViolation>) -> Vec<ViolationKind> {
+        violations.iter().map(|violation| violation.kind).collect()
+    }
+
+    #[test]
+    fn should_match_wildcard_patterns() {
+
+        assert!(matches_pattern("00:11:22:*", "00:11:22:33:44:55"));
+        assert!(matches_pattern("*hewlett*", "hewlett packard"));
+        assert!(matches_pattern("d*l*", "dell inc."));
+        assert!(!matches_pattern("dell*", "intel corporate"));
+        assert!(!matches_pattern("a*b*b", "ab"));
+    }
+
+    #[test]
+    fn should_accept_known_hosts() {
+
+        let baseline = Baseline::parse(BASELINE_CONTENT).unwrap();
+
+        let known_host = build_target(Ipv4Addr::new(10, 20, 0, 12), "00:11:22:33:44:55", Some("Dell Inc."));
+        let pinned_host = build_target(Ipv4Addr::new(10, 20, 0, 1), "aa:bb:cc:00:00:01", Some("Hewlett Packard"));
+
+        assert_eq!(baseline.check_host(&known_host), vec![]);
+        assert_eq!(baseline.check_host(&pinned_host), vec![]);
+    }
+
+    #[test]
+    fn should_classify_violations() {
+
+        let baseline = Baseline::parse(BASELINE_CONTENT).unwrap();
+
+        let rogue_host = build_target(Ipv4Addr::new(10, 30, 0, 5), "de:ad:be:ef:00:01", None);
+        let spoofing_host = build_target(Ipv4Addr::new(10, 20, 0, 1), "00:11:22:33:44:66", Some("Dell Inc."));
+
+        assert_eq!(list_kinds(baseline.check_host(&rogue_host)), vec![ViolationKind::OutsideRange, ViolationKind::UnknownMac, ViolationKind::UnknownVendor]);
+        assert_eq!(baseline.check_host(&spoofing_host), vec![BaselineViolation { kind: ViolationKind::Mismatch, severity: Severity::High }]);
+    }
+
+    #[test]
+    fn should_accept_mac_pinned_to_several_addresses() {
+
+        let baseline = Baseline::parse("
+hosts:
+  - ipv4: 10.20.0.1
+    mac: 'aa:bb:cc:00:00:01'
+  - ipv4: 10.20.0.2
+    mac: 'aa:bb:cc:00:00:01'
+").unwrap();
+
+        let first_address = build_target(Ipv4Addr::new(10, 20, 0, 1), "aa:bb:cc:00:00:01", None);
+        let second_address = build_target(Ipv4Addr::new(10, 20, 0, 2), "aa:bb:cc:00:00:01", None);
+        let moved_host = build_target(Ipv4Addr::new(10, 20, 0, 3), "aa:bb:cc:00:00:01", None);
+        let other_host = build_target(Ipv4Addr::new(10, 20, 0, 4), "aa:bb:cc:00:00:02", None);
+
+        assert_eq!(baseline.check_host(&first_address), vec![]);
+        assert_eq!(baseline.check_host(&second_address), vec![]);
+        assert_eq!(list_kinds(baseline.check_host(&moved_host)), vec![ViolationKind::Mismatch]);
+        assert_eq!(baseline.check_host(&other_host), vec![]);
+    }
+
+    #[test]
+    fn should_apply_severity_overrides() {
+
+        let baseline = Baseline::parse(BASELINE_CONTENT).unwrap();
+        let unknown_vendor = baseline.build_violation(ViolationKind::UnknownVendor);
+
+        assert_eq!(unknown_vendor.severity, Severity::Medium);
+        assert!(baseline.is_failing(&unknown_vendor));
+        assert!(!baseline.is_failing(&BaselineViolation { kind: ViolationKind::UnknownVendor, severity: Severity::Low }));
+    }
+
+    #[test]
+    fn should_reject_invalid_baseline() {
+
+        assert!(Baseline::parse("networks: ['10.20.0.0/33']").is_err());
+        assert!(Baseline::parse("hosts: [{ipv4: 10.20.0.1, mac: 'nope'}]").is_err());
+        assert!(Baseline::parse("allowed: []").is_err());
+    }
+
+}
@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant};
+
+use ipnetwork::IpNetwork;
+use pnet_datalink::MacAddr;
+use serde::{Deserialize, Serialize};
+
+use crate::args::{ScanOptions, ScanTiming};
+use crate::fingerprint::ArpBehaviour;
+use crate::network::{DiscoveryMethod, TargetDetails};
+
+const CHECKPOINT_VERSION: u32 = 2;
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct CheckpointHost {
+    pub ipv4: Ipv4Addr,
+    pub mac: String
+}
+
+/**
+ * The effective options of the interrupted scan, with the same fields as the
+ * scan options of JSON results. A resumed scan keeps the same profile, timing
+ * and retry count (a resumed round would otherwise be skipped).
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CheckpointOptions {
+    pub profile: String,
+    pub interval_us: Option<u64>,
+    pub bandwidth_bps: Option<u64>,
+    pub packet_rate: Option<u64>,
+    pub burst_size: u32,
+    pub timeout_ms: u64,
+    pub retry_count: usize
+}
+
+impl CheckpointOptions {
+
+    pub fn new(options: &ScanOptions) -> Self {
+
+        let (interval_us, bandwidth_bps, packet_rate) = match options.scan_timing {
+            ScanTiming::Interval(interval_us) => (Some(interval_us), None, None),
+            ScanTiming::Bandwidth(bits_second) => (None, Some(bits_second), None),
+            ScanTiming::PacketRate(packet_rate) => (None, None, Some(packet_rate))
+        };
+
+        CheckpointOptions {
+            profile: options.profile.name().to_string(),
+            interval_us,
+            bandwidth_bps,
+            packet_rate,
+            burst_size: options.burst_size,
+            timeout_ms: options.timeout_ms,
+            retry_count: options.retry_count
+        }
+    }
+
+    pub fn scan_timing(&self) -> Result<ScanTiming, String> {
+
+        match (self.interval_us, self.bandwidth_bps, self.packet_rate) {
+            (Some(interval_us), None, None) => Ok(ScanTiming::Interval(interval_us)),
+            (None, Some(bits_second), None) => Ok(ScanTiming::Bandwidth(bits_second)),
+            (None, None, Some(packet_rate)) => Ok(ScanTiming::PacketRate(packet_rate)),
+            _ => Err("Invalid scan timing in checkpoint".to_string())
+        }
+    }
+}
+
+/**
+ * A scan checkpoint contains everything needed to continue an interrupted
+ * scan: the scan plan (interface, networks, random seed & scan options), the
+ * position in the target list and all hosts discovered so far.
+ */
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ScanCheckpoint {
+    pub version: u32,
+    pub interface: String,
+    pub networks: Vec<String>,
+    pub random_seed: Option<u64>,
+    pub options: CheckpointOptions,
+    pub retry_round: usize,
+    pub position: u64,
+    pub hosts: Vec<CheckpointHost>
+}
+
+impl ScanCheckpoint {
+
+    /**
+     * Parse the networks stored in the checkpoint, these networks should
+     * always be valid since they were written by a previous scan.
+     */
+    pub fn parse_networks(&self) -> Result<Vec<IpNetwork>, String> {
+
+        self.networks.iter().map(|network_text| {
+            network_text.parse::<IpNetwork>().map_err(|err| format!("Invalid network in checkpoint ({})", err))
+        }).collect()
+    }
+
+    /**
+     * List the hosts found in the checkpoint as target details, ready to be
+     * merged with the results of the resumed scan.
+     */
+    pub fn discovered_targets(&self) -> Result<Vec<TargetDetails>, String> {
+
+        self.hosts.iter().map(|host| {
+
+            let mac = host.mac.parse::<MacAddr>().map_err(|err| format!("Invalid MAC address in checkpoint ({})", err))?;
+            Ok(TargetDetails {
+                ipv4: host.ipv4,
+                mac,
+                hostname: None,
+                hostname_source: None,
+                hostname_confirmed: None,
+                dhcp: None,
+                vendor: None,
+                device_class: None,
+                open_ports: vec![],
+                behaviour: ArpBehaviour::default(),
+                found_by: DiscoveryMethod::Arp,
+                violations: vec![]
+            })
+        }).collect()
+    }
+}
+
+/**
+ * Parse and validate a checkpoint written by a previous scan.
+ */
+pub fn parse_checkpoint(content: &str) -> Result<ScanCheckpoint, String> {
+
+    let checkpoint: ScanCheckpoint = serde_json::from_str(content).map_err(|err| err.to_string())?;
+
+    if checkpoint.version != CHECKPOINT_VERSION {
+        return Err(format!("Unsupported checkpoint version {} (expected {})", checkpoint.version, CHECKPOINT_VERSION));
+    }
+
+    Ok(checkpoint)
+}
+
+/**
+ * Periodically saves the scan state in a checkpoint file. Each save writes a
+ * temporary file next to the checkpoint and renames it over the previous
+ * checkpoint, an interrupted save never leaves a truncated checkpoint.
+ */
+pub struct CheckpointWriter {
+    path: PathBuf,
+    interface: String,
+    networks: Vec<String>,
+    random_seed: Option<u64>,
+    options: CheckpointOptions,
+    hosts: HashMap<Ipv4Addr, MacAddr>,
+    last_save: Instant
+}
+
+impl CheckpointWriter {
+
+    pub fn new(path: &str, interface: &str, networks: &[&IpNetwork], options: &ScanOptions) -> Self {
+
+        CheckpointWriter {
+            path: PathBuf::from(path),
+            interface: interface.to_string(),
+            networks: networks.iter().map(|network| network.to_string()).collect(),
+            random_seed: options.target_seed(),
+            options: CheckpointOptions::new(options),
+            hosts: HashMap::new(),
+            last_save: Instant::now()
+        }
+    }
+
+    pub fn record_host(&mut self, ipv4: Ipv4Addr, mac: MacAddr) {
+        self.hosts.insert(ipv4, mac);
+    }
+
+    pub fn record_targets(&mut self, target_details: &[TargetDetails]) {
+
+        for detail in target_details {
+            self.record_host(detail.ipv4, detail.mac);
+        }
+    }
+
+    /**
+     * Save the checkpoint if the last save is old enough. This should be
+     * called regularly during the scan without impacting the sending rate.
+     */
+    pub fn save_periodically(&mut self, retry_round: usize, position: u64) {
+
+        if self.last_save.elapsed() >= CHECKPOINT_INTERVAL {
+            self.save(retry_round, position);
+        }
+    }
+
+    /**
+     * Save the checkpoint, a failed save is only reported since the scan
+     * itself can continue without checkpoints.
+     */
+    pub fn save(&mut self, retry_round: usize, position: u64) {
+
+        if let Err(err) = self.try_save(retry_round, position) {
+            eprintln!("[warn] Could not write checkpoint file {} ({})", self.path.display(), err);
+        }
+        self.last_save = Instant::now();
+    }
+
+    /**
+     * Save the checkpoint and return any write error. The first save is
+     * done right after dropping privileges, so that a checkpoint directory
+     * which is not writable by the unprivileged user fails the scan early.
+     */
+    pub fn try_save(&mut self, retry_round: usize, position: u64) -> io::Result<()> {
+
+        let mut hosts: Vec<CheckpointHost> = self.hosts.iter().map(|(ipv4, mac)| CheckpointHost {
+            ipv4: *ipv4,
+            mac: mac.to_string()
+        }).collect();
+        hosts.sort_by_key(|host| host.ipv4);
+
+        let checkpoint = ScanCheckpoint {
+            version: CHECKPOINT_VERSION,
+            interface: self.interface.clone(),
+            networks: self.networks.clone(),
+            random_seed: self.random_seed,
+            options: self.options.clone(),
+            retry_round,
+            position,
+            hosts
+        };
+
+        let content = serde_json::to_string(&checkpoint).unwrap_or_else(|err| {
+            eprintln!("Could not serialize checkpoint ({})", err);
+            process::exit(1);
+        });
+
+        write_atomically(&self.path, content.as_bytes())
+    }
+
+}
+
+/**
+ * Replace a file with new content: the content is written and synced in a
+ * temporary file, which is then renamed over the target file. The parent
+ * directory is synced as well, so that the rename survives a crash.
+ */
+fn write_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+
+    let mut temporary_name = path.file_name().unwrap_or_default().to_os_string();
+    temporary_name.push(".tmp");
+    let temporary_path = path.with_file_name(temporary_name);
+
+    let mut temporary_file = File::create(&temporary_path)?;
+    temporary_file.write_all(content)?;
+    temporary_file.sync_all()?;
+    drop(temporary_file);
+
+    fs::rename(&temporary_path, path)?;
+
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new(".")
+    };
+    File::open(directory)?.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::env;
+    use crate::args;
+
+    fn build_options(arguments: &[&str]) -> ScanOptions {
+
+        let mut command_line = vec!["arp-scan"];
+        command_line.extend_from_slice(arguments);
+
+        ScanOptions::new(&args::build_args().get_matches_from(command_line))
+    }
+
+    #[test]
+    fn should_write_and_read_checkpoint() {
+
+        let path = env::temp_dir().join(format!("arp-scan-checkpoint-{}.json", process::id()));
+        let path_text = path.to_str().unwrap();
+
+        let network: IpNetwork = "192.168.1.0/24".parse().unwrap();
+        let options = build_options(&["--seed", "42", "-r", "3", "--rate", "500"]);
+        let mut writer = CheckpointWriter::new(path_text, "eth0", &[&network], &options);
+        writer.record_host(Ipv4Addr::new(192, 168, 1, 20), MacAddr::new(0x40, 0x55, 0x82, 0xc3, 0xe5, 0x5b));
+        writer.try_save(1, 120).unwrap();
+
+        let checkpoint = parse_checkpoint(&fs::read_to_string(&path).unwrap()).unwrap();
+        let has_temporary_file = path.with_extension("json.tmp").exists();
+        fs::remove_file(&path).unwrap();
+
+        assert!(!has_temporary_file);
+
+        assert_eq!(checkpoint, ScanCheckpoint {
+            version: CHECKPOINT_VERSION,
+            interface: "eth0".to_string(),
+            networks: vec!["192.168.1.0/24".to_string()],
+            random_seed: Some(42),
+            options: CheckpointOptions {
+                profile: "default".to_string(),
+                interval_us: None,
+                bandwidth_bps: None,
+                packet_rate: Some(500),
+                burst_size: 1,
+                timeout_ms: 2000,
+                retry_count: 3
+            },
+            retry_round: 1,
+            position: 120,
+            hosts: vec![
+                CheckpointHost {
+                    ipv4: Ipv4Addr::new(192, 168, 1, 20),
+                    mac: "40:55:82:c3:e5:5b".to_string()
+                }
+            ]
+        });
+        assert_eq!(checkpoint.parse_networks(), Ok(vec![network]));
+    }
+
+    #[test]
+    fn should_shrink_rewritten_checkpoint() {
+
+        let path = env::temp_dir().join(format!("arp-scan-checkpoint-shrink-{}.json", process::id()));
+        let path_text = path.to_str().unwrap();
+
+        let network: IpNetwork = "10.0.0.0/16".parse().unwrap();
+        let mut writer = CheckpointWriter::new(path_text, "eth0", &[&network], &build_options(&[]));
+        writer.save(0, 65000);
+        writer.save(0, 1);
+
+        let checkpoint = parse_checkpoint(&fs::read_to_string(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(checkpoint.position, 1);
+    }
+
+    #[test]
+    fn should_fail_invalid_checkpoint() {
+
+        assert!(parse_checkpoint("{\"version\": 1").is_err());
+        assert!(parse_checkpoint("{\"interface\": \"eth0\"}").is_err());
+    }
+
+}
@@ -0,0 +1,372 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+
+use ipnetwork::IpNetwork;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::args::ScanOptions;
+use crate::export;
+use crate::network::{ResponseSummary, TargetDetails};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS scans (
+        id INTEGER PRIMARY KEY,
+        started_at INTEGER NOT NULL,
+        duration_ms INTEGER NOT NULL,
+        interface TEXT NOT NULL,
+        networks TEXT NOT NULL,
+        options TEXT NOT NULL,
+        packet_count INTEGER NOT NULL,
+        arp_count INTEGER NOT NULL,
+        request_count INTEGER NOT NULL,
+        host_count INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS observations (
+        scan_id INTEGER NOT NULL REFERENCES scans(id),
+        ipv4 TEXT NOT NULL,
+        mac TEXT NOT NULL,
+        hostname TEXT,
+        vendor TEXT,
+        seen_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS observations_mac ON observations(mac);
+    CREATE INDEX IF NOT EXISTS observations_seen_at ON observations(seen_at);
+    CREATE TABLE IF NOT EXISTS hosts (
+        mac TEXT PRIMARY KEY,
+        first_seen INTEGER NOT NULL,
+        last_seen INTEGER NOT NULL,
+        last_ipv4 TEXT NOT NULL,
+        vendor TEXT
+    );
+";
+
+/**
+ * A host seen in a time window, for each MAC & IPv4 pair. The first seen
+ * timestamp is the first sighting of the MAC address in the whole database.
+ */
+#[derive(Debug, PartialEq)]
+pub struct HostSighting {
+    pub ipv4: String,
+    pub mac: String,
+    pub hostname: Option<String>,
+    pub vendor: Option<String>,
+    pub first_seen: u64,
+    pub last_seen: u64,
+    pub scan_count: u64
+}
+
+/**
+ * An IPv4 address used by a MAC address on a given interface, with the
+ * period during which the MAC address was seen there.
+ */
+#[derive(Debug, PartialEq)]
+pub struct MacLocation {
+    pub ipv4: String,
+    pub interface: String,
+    pub hostname: Option<String>,
+    pub first_seen: u64,
+    pub last_seen: u64,
+    pub scan_count: u64
+}
+
+/**
+ * SQLite store holding each scan run and the hosts observed by this scan,
+ * with a first-seen & last-seen history per MAC address.
+ */
+pub struct ScanDatabase {
+    connection: Connection
+}
+
+impl ScanDatabase {
+
+    /**
+     * Open (or create) the database and its tables. The database uses a
+     * write-ahead log: the log and its index are created here, before
+     * privileges are dropped, and kept open until the end of the process.
+     * Scans recorded by the unprivileged user then only write to files that
+     * are already open, while keeping the crash safety of the log.
+     */
+    pub fn open(path: &str) -> Result<Self, String> {
+
+        let connection = Connection::open(path).map_err(|err| format!("Could not open database {} ({})", path, err))?;
+
+        let journal_mode: String = connection.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get(0))
+            .map_err(|err| format!("Could not prepare database {} ({})", path, err))?;
+
+        // In-memory databases (used by tests) have no write-ahead log
+        if journal_mode != "wal" && path != ":memory:" {
+            return Err(format!("Could not enable the write-ahead log of database {} (journal mode is {})", path, journal_mode));
+        }
+
+        connection.execute_batch(SCHEMA).map_err(|err| format!("Could not prepare database {} ({})", path, err))?;
+
+        Ok(ScanDatabase { connection })
+    }
+
+    /**
+     * Record a scan run and all hosts found by the scan in a single
+     * transaction, the MAC history is updated with the scan start time. The
+     * effective scan options are stored as JSON (the raw command line may
+     * hold secrets, like tokens in notification URLs).
+     */
+    pub fn record_scan(&mut self, interface_name: &str, ip_networks: &[&IpNetwork], response_summary: &ResponseSummary, target_details: &[TargetDetails], options: &ScanOptions) -> Result<(), rusqlite::Error> {
+
+        let networks = ip_networks.iter().map(|network| network.to_string()).collect::<Vec<String>>().join(",");
+        let scan_options = export::export_scan_options(options);
+        let seen_at = response_summary.start_timestamp as i64;
+
+        let transaction = self.connection.transaction()?;
+
+        transaction.execute(
+            "INSERT INTO scans (started_at, duration_ms, interface, networks, options, packet_count, arp_count, request_count, host_count)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                seen_at,
+                response_summary.duration_ms as i64,
+                interface_name,
+                networks,
+                scan_options,
+                response_summary.packet_count as i64,
+                response_summary.arp_count as i64,
+                response_summary.request_count as i64,
+                target_details.len() as i64
+            ]
+        )?;
+        let scan_id = transaction.last_insert_rowid();
+
+        for detail in target_details {
+
+            let ipv4 = detail.ipv4.to_string();
+            let mac = detail.mac.to_string();
+            transaction.execute(
+                "INSERT INTO observations (scan_id, ipv4, mac, hostname, vendor, seen_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![scan_id, ipv4, mac, detail.hostname, detail.vendor, seen_at]
+            )?;
+            transaction.execute(
+                "INSERT INTO hosts (mac, first_seen, last_seen, last_ipv4, vendor) VALUES (?1, ?2, ?2, ?3, ?4)
+                 ON CONFLICT(mac) DO UPDATE SET
+                    first_seen = MIN(first_seen, excluded.first_seen),
+                    last_seen = MAX(last_seen, excluded.last_seen),
+                    last_ipv4 = excluded.last_ipv4,
+                    vendor = COALESCE(excluded.vendor, vendor)",
+                params![mac, seen_at, ipv4, detail.vendor]
+            )?;
+        }
+
+        transaction.commit()
+    }
+
+    /**
+     * List the hosts seen between two timestamps (inclusive), the hostname
+     * and vendor are taken from the last sighting in the time window.
+     */
+    pub fn list_hosts(&self, since: u64, until: u64) -> Result<Vec<HostSighting>, rusqlite::Error> {
+
+        // SQLite takes bare columns from the row holding the MAX() value, this
+        // gives the details of the last sighting for each group.
+        let mut statement = self.connection.prepare(
+            "SELECT o.ipv4, o.mac, o.hostname, o.vendor, h.first_seen, MAX(o.seen_at), COUNT(DISTINCT o.scan_id)
+             FROM observations o JOIN hosts h ON h.mac = o.mac
+             WHERE o.seen_at BETWEEN ?1 AND ?2
+             GROUP BY o.mac, o.ipv4"
+        )?;
+
+        let sightings = statement.query_map(params![since as i64, until as i64], |row| {
+            Ok(HostSighting {
+                ipv4: row.get(0)?,
+                mac: row.get(1)?,
+                hostname: row.get(2)?,
+                vendor: row.get(3)?,
+                first_seen: row.get::<_, i64>(4)? as u64,
+                last_seen: row.get::<_, i64>(5)? as u64,
+                scan_count: row.get::<_, i64>(6)? as u64
+            })
+        })?;
+
+        let mut sightings = sightings.collect::<Result<Vec<HostSighting>, rusqlite::Error>>()?;
+        sightings.sort_by_key(|sighting| (sighting.ipv4.parse::<Ipv4Addr>().ok(), sighting.mac.clone()));
+        Ok(sightings)
+    }
+
+    /**
+     * Find the first & last sighting of a MAC address in the database.
+     */
+    pub fn find_mac_period(&self, mac: &str) -> Result<Option<(u64, u64)>, rusqlite::Error> {
+
+        self.connection.query_row(
+            "SELECT first_seen, last_seen FROM hosts WHERE mac = ?1",
+            params![mac],
+            |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64))
+        ).optional()
+    }
+
+    /**
+     * List where a MAC address has been seen: each IPv4 address and interface
+     * used by the MAC address, the most recent locations first.
+     */
+    pub fn find_mac_locations(&self, mac: &str) -> Result<Vec<MacLocation>, rusqlite::Error> {
+
+        let mut statement = self.connection.prepare(
+            "SELECT o.ipv4, s.interface, o.hostname, o.seen_at
+             FROM observations o JOIN scans s ON s.id = o.scan_id
+             WHERE o.mac = ?1
+             ORDER BY o.seen_at"
+        )?;
+
+        let observations = statement.query_map(params![mac], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?, row.get::<_, i64>(3)? as u64))
+        })?;
+
+        let mut locations: Vec<MacLocation> = vec![];
+        let mut location_indexes: HashMap<(String, String), usize> = HashMap::new();
+        for observation in observations {
+
+            let (ipv4, interface, hostname, seen_at) = observation?;
+            let location_key = (ipv4.clone(), interface.clone());
+            match location_indexes.get(&location_key) {
+                Some(index) => {
+                    let location = &mut locations[*index];
+                    location.last_seen = seen_at;
+                    location.scan_count += 1;
+                    if hostname.is_some() {
+                        location.hostname = hostname;
+                    }
+                },
+                None => {
+                    location_indexes.insert(location_key, locations.len());
+                    locations.push(MacLocation {
+                        ipv4,
+                        interface,
+                        hostname,
+                        first_seen: seen_at,
+                        last_seen: seen_at,
+                        scan_count: 1
+                    });
+                }
+            }
+        }
+
+        locations.sort_by_key(|location| Reverse(location.last_seen));
+        Ok(locations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use std::env;
+    use std::fs;
+    use std::process;
+    use std::sync::Arc;
+
+    use crate::args;
+
+    fn build_target(ipv4: Ipv4Addr, mac: &str, hostname: Option<&str>) -> TargetDetails {
+        TargetDetails {
+            hostname: hostname.map(|hostname| hostname.to_string()),
+            vendor: Some("Raspberry Pi Trading Ltd".to_string()),
+            ..TargetDetails::build(ipv4, mac.parse().unwrap())
+        }
+    }
+
+    fn build_summary(start_timestamp: u64) -> ResponseSummary {
+        ResponseSummary {
+            packet_count: 8,
+            arp_count: 2,
+            start_timestamp,
+            duration_ms: 1200,
+            target_count: 256,
+            conflict_count: 0,
+            request_count: 256,
+            request_rate: None
+        }
+    }
+
+    fn build_options(arguments: &[&str]) -> Arc<ScanOptions> {
+
+        let mut command_line = vec!["arp-scan"];
+        command_line.extend_from_slice(arguments);
+
+        Arc::new(ScanOptions::new(&args::build_args().get_matches_from(command_line)))
+    }
+
+    #[test]
+    fn should_keep_mac_history() {
+
+        let network: IpNetwork = "10.20.0.0/24".parse().unwrap();
+        let mut database = ScanDatabase::open(":memory:").unwrap();
+        let options = build_options(&[]);
+
+        let first_scan = vec![build_target(Ipv4Addr::new(10, 20, 0, 12), "b8:27:eb:01:02:03", None)];
+        database.record_scan("eth0", &[&network], &build_summary(1_000), &first_scan, &options).unwrap();
+
+        let second_scan = vec![
+            build_target(Ipv4Addr::new(10, 20, 0, 40), "b8:27:eb:01:02:03", Some("sensor")),
+            build_target(Ipv4Addr::new(10, 20, 0, 2), "00:11:22:33:44:55", None)
+        ];
+        database.record_scan("eth0", &[&network], &build_summary(2_000), &second_scan, &options).unwrap();
+        database.record_scan("eth1", &[&network], &build_summary(3_000), &second_scan[..1], &options).unwrap();
+
+        assert_eq!(database.find_mac_period("b8:27:eb:01:02:03").unwrap(), Some((1_000, 3_000)));
+        assert_eq!(database.find_mac_period("aa:bb:cc:dd:ee:ff").unwrap(), None);
+
+        let locations = database.find_mac_locations("b8:27:eb:01:02:03").unwrap();
+        assert_eq!(locations.len(), 3);
+        assert_eq!((locations[0].interface.as_str(), locations[0].last_seen), ("eth1", 3_000));
+        assert_eq!((locations[1].ipv4.as_str(), locations[1].hostname.as_deref()), ("10.20.0.40", Some("sensor")));
+        assert_eq!((locations[2].ipv4.as_str(), locations[2].first_seen), ("10.20.0.12", 1_000));
+    }
+
+    #[test]
+    fn should_list_hosts_in_time_window() {
+
+        let network: IpNetwork = "10.20.0.0/24".parse().unwrap();
+        let mut database = ScanDatabase::open(":memory:").unwrap();
+        let options = build_options(&[]);
+
+        let targets = vec![
+            build_target(Ipv4Addr::new(10, 20, 0, 12), "b8:27:eb:01:02:03", None),
+            build_target(Ipv4Addr::new(10, 20, 0, 2), "00:11:22:33:44:55", Some("gateway"))
+        ];
+        database.record_scan("eth0", &[&network], &build_summary(1_000), &targets, &options).unwrap();
+        database.record_scan("eth0", &[&network], &build_summary(2_000), &targets[..1], &options).unwrap();
+        database.record_scan("eth0", &[&network], &build_summary(5_000), &targets[1..], &options).unwrap();
+
+        let sightings = database.list_hosts(1_500, 2_500).unwrap();
+        assert_eq!(sightings.len(), 1);
+        assert_eq!(sightings[0].first_seen, 1_000);
+        assert_eq!(sightings[0].last_seen, 2_000);
+
+        let sightings = database.list_hosts(0, 10_000).unwrap();
+        assert_eq!(sightings.len(), 2);
+        assert_eq!((sightings[0].ipv4.as_str(), sightings[0].hostname.as_deref(), sightings[0].scan_count), ("10.20.0.2", Some("gateway"), 2));
+        assert_eq!((sightings[1].ipv4.as_str(), sightings[1].last_seen, sightings[1].scan_count), ("10.20.0.12", 2_000, 2));
+    }
+
+    #[test]
+    fn should_store_effective_options() {
+
+        let database_path = env::temp_dir().join(format!("arp-scan-test-{}.db", process::id()));
+        let database_text = database_path.to_string_lossy().into_owned();
+        let network: IpNetwork = "10.20.0.0/24".parse().unwrap();
+        let options = build_options(&["-r", "3", "--notify", "webhook:http://10.20.0.5/hook?token=secret"]);
+
+        let mut database = ScanDatabase::open(&database_text).unwrap();
+        database.record_scan("eth0", &[&network], &build_summary(1_000), &[], &options).unwrap();
+
+        let journal_mode: String = database.connection.query_row("PRAGMA journal_mode", [], |row| row.get(0)).unwrap();
+        let stored_options: String = database.connection.query_row("SELECT options FROM scans", [], |row| row.get(0)).unwrap();
+        drop(database);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = fs::remove_file(format!("{}{}", database_text, suffix));
+        }
+
+        assert_eq!(journal_mode, "wal");
+        assert!(stored_options.contains("\"retry_count\":3"));
+        assert!(!stored_options.contains("secret"));
+    }
+
+}
@@ -0,0 +1,98 @@
+use std::fs::File;
+use std::io::Read;
+use std::sync::{Mutex, OnceLock};
+
+/**
+ * A file opened when parsing the scan options and parsed later on. Files are
+ * opened before privileges are dropped (the unprivileged user may not be able
+ * to open them), but their content is only parsed once the scan runs as the
+ * unprivileged user.
+ */
+#[derive(Debug)]
+pub struct DeferredFile<T> {
+    path: String,
+    description: &'static str,
+    file: Mutex<Option<File>>,
+    content: OnceLock<T>
+}
+
+impl<T> DeferredFile<T> {
+
+    pub fn open(path: &str, description: &'static str) -> Result<Self, String> {
+
+        let file = File::open(path).map_err(|err| {
+            format!("Could not read {} {} ({})", description, path, err)
+        })?;
+
+        Ok(DeferredFile {
+            path: path.to_string(),
+            description,
+            file: Mutex::new(Some(file)),
+            content: OnceLock::new()
+        })
+    }
+
+    /**
+     * Read and parse the opened file, this is done a single time. Parsing
+     * errors are prefixed with the file path.
+     */
+    pub fn load(&self, parse: impl FnOnce(&str) -> Result<T, String>) -> Result<(), String> {
+
+        let file = match self.file.lock() {
+            Ok(mut file) => file.take(),
+            Err(poisoned) => poisoned.into_inner().take()
+        };
+        let mut file = match file {
+            Some(file) => file,
+            None => return Ok(())
+        };
+
+        let mut text = String::new();
+        file.read_to_string(&mut text).map_err(|err| {
+            format!("Could not read {} {} ({})", self.description, self.path, err)
+        })?;
+
+        let content = parse(&text).map_err(|err| {
+            format!("Could not parse {} {} ({})", self.description, self.path, err)
+        })?;
+
+        // The file has been taken above, no other load can set the content
+        let _ = self.content.set(content);
+        Ok(())
+    }
+
+    /**
+     * Get the parsed content, nothing is returned before the file is loaded.
+     */
+    pub fn get(&self) -> Option<&T> {
+        self.content.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn should_parse_file_once_loaded() {
+
+        let deferred: DeferredFile<usize> = DeferredFile::open("./Cargo.toml", "manifest").unwrap();
+        assert_eq!(deferred.get(), None);
+
+        deferred.load(|content| Ok(content.lines().count())).unwrap();
+        assert!(deferred.get().is_some_and(|line_count| *line_count > 0));
+    }
+
+    #[test]
+    fn should_report_file_errors() {
+
+        let missing_file = DeferredFile::<()>::open("./unknown-file.txt", "hosts file");
+        assert!(missing_file.is_err_and(|err| err.starts_with("Could not read hosts file ./unknown-file.txt")));
+
+        let deferred: DeferredFile<()> = DeferredFile::open("./Cargo.toml", "baseline file").unwrap();
+        let load_result = deferred.load(|_| Err("invalid content".to_string()));
+        assert_eq!(load_result, Err("Could not parse baseline file ./Cargo.toml (invalid content)".to_string()));
+    }
+
+}
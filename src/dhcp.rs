@@ -0,0 +1,261 @@
+use pnet_datalink::MacAddr;
+
+pub const DHCP_SERVER_PORT: u16 = 67;
+pub const DHCP_CLIENT_PORT: u16 = 68;
+
+const BOOT_REQUEST: u8 = 1;
+const ETHERNET_HARDWARE_TYPE: u8 = 1;
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+const OPTIONS_OFFSET: usize = 240;
+
+// DHCP options used for fingerprinting, see RFC 2132
+const PAD_OPTION: u8 = 0;
+const HOSTNAME_OPTION: u8 = 12;
+const MESSAGE_TYPE_OPTION: u8 = 53;
+const PARAMETER_LIST_OPTION: u8 = 55;
+const VENDOR_CLASS_OPTION: u8 = 60;
+const END_OPTION: u8 = 255;
+
+// Client messages carrying a fingerprint: DISCOVER, REQUEST and INFORM
+const CLIENT_MESSAGE_TYPES: [u8; 3] = [1, 3, 8];
+
+/**
+ * Operating system guesses based on the DHCP vendor class (option 60). The
+ * vendor class is compared by prefix.
+ */
+const VENDOR_CLASS_GUESSES: [(&str, &str); 6] = [
+    ("MSFT 5.0", "Windows"),
+    ("MSFT 98", "Windows 98"),
+    ("android-dhcp", "Android"),
+    ("dhcpcd", "Linux (dhcpcd)"),
+    ("udhcp", "Embedded Linux (BusyBox)"),
+    ("Cisco Systems", "Cisco IOS")
+];
+
+/**
+ * Operating system guesses based on the parameter request list (option 55),
+ * the order of requested options is specific to each DHCP client. The request
+ * list of the client must start with the given options.
+ */
+const PARAMETER_LIST_GUESSES: [(&[u8], &str); 5] = [
+    (&[1, 121, 3, 6, 15, 108, 114, 119, 252], "macOS / iOS"),
+    (&[1, 121, 3, 6, 15, 119, 252], "macOS / iOS"),
+    (&[1, 3, 6, 15, 31, 33, 43, 44, 46, 47, 119, 121, 249, 252], "Windows"),
+    (&[1, 28, 2, 3, 15, 6, 119, 12, 44, 47, 26, 121, 42], "Linux (dhclient)"),
+    (&[1, 3, 6, 12, 15, 28, 42], "Linux (systemd-networkd)")
+];
+
+/**
+ * Details sent by a DHCP client in its DISCOVER, REQUEST or INFORM messages.
+ * These details are matched with ARP results using the client MAC address.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct DhcpFingerprint {
+    pub mac: MacAddr,
+    pub hostname: Option<String>,
+    pub vendor_class: Option<String>,
+    pub parameter_list: Vec<u8>
+}
+
+impl DhcpFingerprint {
+
+    /**
+     * Guess the operating system of the client, the vendor class is checked
+     * first since it is more specific than the parameter request list.
+     */
+    pub fn guess_os(&self) -> Option<String> {
+
+        let vendor_guess = self.vendor_class.as_ref().and_then(|vendor_class| {
+            VENDOR_CLASS_GUESSES.iter()
+                .find(|(prefix, _)| vendor_class.starts_with(prefix))
+                .map(|(_, os_name)| os_name.to_string())
+        });
+
+        vendor_guess.or_else(|| {
+            PARAMETER_LIST_GUESSES.iter()
+                .find(|(parameter_list, _)| self.parameter_list.starts_with(parameter_list))
+                .map(|(_, os_name)| os_name.to_string())
+        })
+    }
+
+    /**
+     * Merge the details of a newer message from the same client, fields that
+     * are missing in the newer message are kept.
+     */
+    pub fn merge(&mut self, newer: DhcpFingerprint) {
+
+        if newer.hostname.is_some() {
+            self.hostname = newer.hostname;
+        }
+        if newer.vendor_class.is_some() {
+            self.vendor_class = newer.vendor_class;
+        }
+        if !newer.parameter_list.is_empty() {
+            self.parameter_list = newer.parameter_list;
+        }
+    }
+
+    pub fn format_parameter_list(&self) -> String {
+        self.parameter_list.iter().map(|option| option.to_string()).collect::<Vec<String>>().join(",")
+    }
+
+}
+
+/**
+ * Parse the UDP payload of a DHCP client message. Only client messages over
+ * Ethernet are kept (server replies do not describe the client).
+ */
+pub fn parse_client_message(payload: &[u8]) -> Option<DhcpFingerprint> {
+
+    if payload.len() < OPTIONS_OFFSET || payload[0] != BOOT_REQUEST || payload[1] != ETHERNET_HARDWARE_TYPE || payload[2] != 6 {
+        return None;
+    }
+    if payload[236..OPTIONS_OFFSET] != MAGIC_COOKIE {
+        return None;
+    }
+
+    let client_mac = MacAddr::new(payload[28], payload[29], payload[30], payload[31], payload[32], payload[33]);
+
+    let mut fingerprint = DhcpFingerprint {
+        mac: client_mac,
+        hostname: None,
+        vendor_class: None,
+        parameter_list: vec![]
+    };
+    let mut message_type: Option<u8> = None;
+
+    let mut offset = OPTIONS_OFFSET;
+    while offset < payload.len() {
+
+        let option_code = payload[offset];
+        match option_code {
+            PAD_OPTION => {
+                offset += 1;
+                continue;
+            },
+            END_OPTION => break,
+            _ => {}
+        }
+
+        let option_length = *payload.get(offset + 1)? as usize;
+        let option_value = payload.get(offset + 2..offset + 2 + option_length)?;
+
+        match option_code {
+            MESSAGE_TYPE_OPTION => message_type = option_value.first().copied(),
+            HOSTNAME_OPTION => fingerprint.hostname = parse_text_option(option_value),
+            VENDOR_CLASS_OPTION => fingerprint.vendor_class = parse_text_option(option_value),
+            PARAMETER_LIST_OPTION => fingerprint.parameter_list = option_value.to_vec(),
+            _ => {}
+        }
+        offset += 2 + option_length;
+    }
+
+    match message_type {
+        Some(message_type) if CLIENT_MESSAGE_TYPES.contains(&message_type) => Some(fingerprint),
+        _ => None
+    }
+}
+
+/**
+ * Text options are not always null-terminated, and may contain control
+ * characters that should never be printed in a terminal.
+ */
+fn parse_text_option(option_value: &[u8]) -> Option<String> {
+
+    let text: String = String::from_utf8_lossy(option_value)
+        .trim_end_matches('\0')
+        .chars()
+        .filter(|character| !character.is_control())
+        .collect();
+
+    match text.trim().is_empty() {
+        true => None,
+        false => Some(text.trim().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn build_client_message(message_type: u8, options: &[(u8, &[u8])]) -> Vec<u8> {
+
+        let mut payload = vec![0u8; OPTIONS_OFFSET];
+        payload[0] = BOOT_REQUEST;
+        payload[1] = ETHERNET_HARDWARE_TYPE;
+        payload[2] = 6;
+        payload[28..34].copy_from_slice(&[0x3c, 0x22, 0xfb, 0x10, 0x20, 0x30]);
+        payload[236..240].copy_from_slice(&MAGIC_COOKIE);
+
+        payload.extend_from_slice(&[MESSAGE_TYPE_OPTION, 1, message_type, PAD_OPTION]);
+        for (option_code, option_value) in options {
+            payload.push(*option_code);
+            payload.push(option_value.len() as u8);
+            payload.extend_from_slice(option_value);
+        }
+        payload.push(END_OPTION);
+        payload
+    }
+
+    #[test]
+    fn should_parse_discover_message() {
+
+        let payload = build_client_message(1, &[
+            (HOSTNAME_OPTION, b"office-laptop\0"),
+            (VENDOR_CLASS_OPTION, b"MSFT 5.0"),
+            (PARAMETER_LIST_OPTION, &[1, 3, 6, 15, 31, 33, 43, 44, 46, 47, 119, 121, 249, 252])
+        ]);
+        let fingerprint = parse_client_message(&payload).unwrap();
+
+        assert_eq!(fingerprint.mac, MacAddr::new(0x3c, 0x22, 0xfb, 0x10, 0x20, 0x30));
+        assert_eq!(fingerprint.hostname, Some("office-laptop".to_string()));
+        assert_eq!(fingerprint.vendor_class, Some("MSFT 5.0".to_string()));
+        assert_eq!(fingerprint.format_parameter_list(), "1,3,6,15,31,33,43,44,46,47,119,121,249,252");
+        assert_eq!(fingerprint.guess_os(), Some("Windows".to_string()));
+    }
+
+    #[test]
+    fn should_guess_os_from_parameter_list() {
+
+        let payload = build_client_message(3, &[
+            (HOSTNAME_OPTION, b"Johns-iPhone"),
+            (PARAMETER_LIST_OPTION, &[1, 121, 3, 6, 15, 108, 114, 119, 252, 95, 44, 46])
+        ]);
+        let fingerprint = parse_client_message(&payload).unwrap();
+
+        assert_eq!(fingerprint.vendor_class, None);
+        assert_eq!(fingerprint.guess_os(), Some("macOS / iOS".to_string()));
+    }
+
+    #[test]
+    fn should_ignore_server_messages() {
+
+        // DHCP OFFER (type 2) and truncated options are ignored
+        let offer_payload = build_client_message(2, &[(HOSTNAME_OPTION, b"server")]);
+        assert_eq!(parse_client_message(&offer_payload), None);
+
+        let mut truncated_payload = build_client_message(1, &[(HOSTNAME_OPTION, b"truncated")]);
+        truncated_payload.truncate(truncated_payload.len() - 5);
+        assert_eq!(parse_client_message(&truncated_payload), None);
+    }
+
+    #[test]
+    fn should_merge_client_messages() {
+
+        let mut fingerprint = parse_client_message(&build_client_message(1, &[
+            (HOSTNAME_OPTION, b"nas"),
+            (VENDOR_CLASS_OPTION, b"udhcp 1.30.1")
+        ])).unwrap();
+        let request = parse_client_message(&build_client_message(3, &[
+            (PARAMETER_LIST_OPTION, &[1, 3, 6, 12, 15, 28, 42])
+        ])).unwrap();
+
+        fingerprint.merge(request);
+
+        assert_eq!(fingerprint.hostname, Some("nas".to_string()));
+        assert_eq!(fingerprint.parameter_list, vec![1, 3, 6, 12, 15, 28, 42]);
+        assert_eq!(fingerprint.guess_os(), Some("Embedded Linux (BusyBox)".to_string()));
+    }
+
+}
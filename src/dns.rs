@@ -0,0 +1,418 @@
+use std::net::Ipv4Addr;
+
+// Record types & classes, as defined in RFC 1035
+pub const A_RECORD_TYPE: u16 = 1;
+pub const CNAME_RECORD_TYPE: u16 = 5;
+pub const PTR_RECORD_TYPE: u16 = 12;
+const IN_CLASS: u16 = 1;
+
+// Unicast DNS servers should resolve the query recursively
+pub const RECURSION_DESIRED_FLAG: u16 = 0x0100;
+
+// The top bit of the question class asks for a unicast response (mDNS)
+const UNICAST_RESPONSE_BIT: u16 = 0x8000;
+
+const HEADER_SIZE: usize = 12;
+const RESPONSE_FLAG: u16 = 0x8000;
+const RESPONSE_CODE_MASK: u16 = 0x000F;
+
+// Compressed names should never need more jumps than this, a higher count
+// means that the packet contains a pointer loop.
+const MAX_NAME_POINTERS: usize = 32;
+
+/**
+ * Build the reverse lookup name of an IPv4 address, the address bytes are
+ * given in reverse order in the 'in-addr.arpa' domain.
+ */
+pub fn reverse_name(ipv4: Ipv4Addr) -> String {
+
+    let octets = ipv4.octets();
+    format!("{}.{}.{}.{}.in-addr.arpa", octets[3], octets[2], octets[1], octets[0])
+}
+
+/**
+ * Build a query with a single question for the given name and record type.
+ * The same wire format is used by unicast DNS, multicast DNS and LLMNR.
+ */
+pub fn build_query(transaction_id: u16, name: &str, record_type: u16, flags: u16, query_class: u16) -> Vec<u8> {
+
+    let mut packet: Vec<u8> = Vec::with_capacity(64);
+    packet.extend_from_slice(&transaction_id.to_be_bytes());
+    packet.extend_from_slice(&flags.to_be_bytes());
+    packet.extend_from_slice(&1u16.to_be_bytes());
+    packet.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+
+    for label in name.trim_end_matches('.').split('.').filter(|label| !label.is_empty()) {
+        let label = &label.as_bytes()[..label.len().min(63)];
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label);
+    }
+    packet.push(0);
+
+    packet.extend_from_slice(&record_type.to_be_bytes());
+    packet.extend_from_slice(&query_class.to_be_bytes());
+
+    packet
+}
+
+/**
+ * Build a PTR query for the reverse name of an IPv4 address, multicast DNS
+ * queries may ask for a unicast response.
+ */
+pub fn build_ptr_query(transaction_id: u16, ipv4: Ipv4Addr, unicast_response: bool) -> Vec<u8> {
+
+    let query_class = match unicast_response {
+        true => IN_CLASS | UNICAST_RESPONSE_BIT,
+        false => IN_CLASS
+    };
+    build_query(transaction_id, &reverse_name(ipv4), PTR_RECORD_TYPE, 0, query_class)
+}
+
+/**
+ * Build a recursive query for a unicast DNS server.
+ */
+pub fn build_recursive_query(transaction_id: u16, name: &str, record_type: u16) -> Vec<u8> {
+    build_query(transaction_id, name, record_type, RECURSION_DESIRED_FLAG, IN_CLASS)
+}
+
+/**
+ * Find the hostname in the response to a PTR query. The response must match
+ * the query transaction and contain a PTR record for the reverse name of the
+ * given IPv4 address, any other packet is ignored. Aliases (CNAME records, used
+ * for classless reverse delegations) are followed.
+ */
+pub fn parse_ptr_response(packet: &[u8], transaction_id: u16, ipv4: Ipv4Addr) -> Option<String> {
+
+    let mut known_names = vec![reverse_name(ipv4)];
+
+    let answers = read_answers(packet, transaction_id)?;
+    for (record_name, record_type, rdata_offset) in answers {
+
+        let is_known_name = known_names.iter().any(|known_name| known_name.eq_ignore_ascii_case(&record_name));
+        if !is_known_name {
+            continue;
+        }
+
+        let (target_name, _) = read_name(packet, rdata_offset)?;
+        match record_type {
+            PTR_RECORD_TYPE if !target_name.is_empty() => return Some(target_name),
+            CNAME_RECORD_TYPE => known_names.push(target_name),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/**
+ * Find all IPv4 addresses in the response to an A query for the given name.
+ * Aliases (CNAME records) are followed, an empty list is returned if the
+ * response does not match the query transaction.
+ */
+pub fn parse_a_response(packet: &[u8], transaction_id: u16, name: &str) -> Vec<Ipv4Addr> {
+
+    let mut known_names = vec![name.trim_end_matches('.').to_string()];
+    let mut addresses: Vec<Ipv4Addr> = vec![];
+
+    let answers = read_answers(packet, transaction_id).unwrap_or_default();
+    for (record_name, record_type, rdata_offset) in answers {
+
+        let is_known_name = known_names.iter().any(|known_name| known_name.eq_ignore_ascii_case(&record_name));
+        if !is_known_name {
+            continue;
+        }
+
+        match record_type {
+            A_RECORD_TYPE => {
+                if let Some(octets) = packet.get(rdata_offset..rdata_offset + 4) {
+                    addresses.push(Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]));
+                }
+            },
+            CNAME_RECORD_TYPE => {
+                if let Some((target_name, _)) = read_name(packet, rdata_offset) {
+                    known_names.push(target_name);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    addresses
+}
+
+/**
+ * List the answer records of a response with their name, type and the offset
+ * of their data. No list is returned if a record is malformed.
+ */
+fn read_answers(packet: &[u8], transaction_id: u16) -> Option<Vec<(String, u16, usize)>> {
+
+    let (answer_offset, answer_count) = read_response_header(packet, transaction_id)?;
+
+    let mut answers: Vec<(String, u16, usize)> = vec![];
+    let mut offset = answer_offset;
+    for _ in 0..answer_count {
+
+        let (record_name, record_type, rdata_offset, rdata_end) = read_record(packet, offset)?;
+        answers.push((record_name, record_type, rdata_offset));
+        offset = rdata_end;
+    }
+
+    Some(answers)
+}
+
+/**
+ * Check that a packet is a response to the given query transaction, whatever
+ * its response code.
+ */
+pub fn is_response(packet: &[u8], transaction_id: u16) -> bool {
+
+    match (read_u16(packet, 0), read_u16(packet, 2)) {
+        (Some(packet_id), Some(flags)) => packet.len() >= HEADER_SIZE && packet_id == transaction_id && flags & RESPONSE_FLAG != 0,
+        _ => false
+    }
+}
+
+/**
+ * Check the header of a response packet and skip its question section. The
+ * offset of the first answer record and the answer count are returned.
+ */
+pub fn read_response_header(packet: &[u8], transaction_id: u16) -> Option<(usize, u16)> {
+
+    if !is_response(packet, transaction_id) || read_u16(packet, 2)? & RESPONSE_CODE_MASK != 0 {
+        return None;
+    }
+
+    let question_count = read_u16(packet, 4)?;
+    let answer_count = read_u16(packet, 6)?;
+
+    let mut offset = HEADER_SIZE;
+    for _ in 0..question_count {
+        let (_, name_end) = read_name(packet, offset)?;
+        offset = name_end + 4;
+    }
+
+    Some((offset, answer_count))
+}
+
+/**
+ * Read a resource record at the given offset. The record name, type and the
+ * boundaries of the record data are returned.
+ */
+pub fn read_record(packet: &[u8], offset: usize) -> Option<(String, u16, usize, usize)> {
+
+    let (record_name, name_end) = read_name(packet, offset)?;
+    let record_type = read_u16(packet, name_end)?;
+    let rdata_length = read_u16(packet, name_end + 8)? as usize;
+
+    let rdata_offset = name_end + 10;
+    let rdata_end = rdata_offset + rdata_length;
+    if rdata_end > packet.len() {
+        return None;
+    }
+
+    Some((record_name, record_type, rdata_offset, rdata_end))
+}
+
+/**
+ * Read a domain name at the given offset, following compression pointers.
+ * The dotted name and the offset right after the name are returned.
+ */
+pub fn read_name(packet: &[u8], offset: usize) -> Option<(String, usize)> {
+
+    let mut labels: Vec<String> = vec![];
+    let mut position = offset;
+    let mut name_end: Option<usize> = None;
+    let mut pointer_count = 0;
+
+    loop {
+
+        let length = *packet.get(position)? as usize;
+
+        match length {
+            0 => {
+                name_end.get_or_insert(position + 1);
+                break;
+            },
+            _ if length & 0xC0 == 0xC0 => {
+
+                pointer_count += 1;
+                if pointer_count > MAX_NAME_POINTERS {
+                    return None;
+                }
+
+                let pointer = (read_u16(packet, position)? & 0x3FFF) as usize;
+                name_end.get_or_insert(position + 2);
+                position = pointer;
+            },
+            _ if length & 0xC0 == 0 => {
+                // Names are printed in terminals and written in reports, labels
+                // holding control characters (escape sequences, newlines, ...)
+                // are rejected.
+                let label = String::from_utf8_lossy(packet.get(position + 1..position + 1 + length)?).into_owned();
+                if label.chars().any(|character| character.is_control()) {
+                    return None;
+                }
+                labels.push(label);
+                position += 1 + length;
+            },
+            _ => return None
+        }
+    }
+
+    Some((labels.join("."), name_end?))
+}
+
+pub fn read_u16(packet: &[u8], offset: usize) -> Option<u16> {
+    let bytes = packet.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/**
+ * Build a PTR response for a query, used by the tests of the name sources.
+ */
+#[cfg(test)]
+pub fn build_ptr_response(transaction_id: u16, ipv4: Ipv4Addr, hostname: &str) -> Vec<u8> {
+
+    // The response repeats the question, the answer name is a pointer to
+    // the question name (offset 12).
+    let mut packet = build_ptr_query(transaction_id, ipv4, false);
+    packet[2] = 0x84;
+    packet[7] = 1;
+
+    packet.extend_from_slice(&[0xC0, 0x0C]);
+    packet.extend_from_slice(&PTR_RECORD_TYPE.to_be_bytes());
+    packet.extend_from_slice(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x78]);
+
+    let mut rdata: Vec<u8> = vec![];
+    for label in hostname.split('.') {
+        rdata.push(label.len() as u8);
+        rdata.extend_from_slice(label.as_bytes());
+    }
+    rdata.push(0);
+    packet.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+    packet.extend_from_slice(&rdata);
+
+    packet
+}
+
+/**
+ * Build an A response with the given addresses, used by the tests of the
+ * forward confirmation.
+ */
+#[cfg(test)]
+pub fn build_a_response(transaction_id: u16, name: &str, addresses: &[Ipv4Addr]) -> Vec<u8> {
+
+    let mut packet = build_recursive_query(transaction_id, name, A_RECORD_TYPE);
+    packet[2] = 0x81;
+    packet[3] = 0x80;
+    packet[7] = addresses.len() as u8;
+
+    for address in addresses {
+        packet.extend_from_slice(&[0xC0, 0x0C]);
+        packet.extend_from_slice(&A_RECORD_TYPE.to_be_bytes());
+        packet.extend_from_slice(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x78, 0x00, 0x04]);
+        packet.extend_from_slice(&address.octets());
+    }
+
+    packet
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn should_build_reverse_name() {
+
+        assert_eq!(reverse_name(Ipv4Addr::new(192, 168, 1, 20)), "20.1.168.192.in-addr.arpa");
+    }
+
+    #[test]
+    fn should_build_ptr_query() {
+
+        let packet = build_ptr_query(0x1234, Ipv4Addr::new(10, 0, 0, 1), true);
+
+        assert_eq!(&packet[0..2], &[0x12, 0x34]);
+        assert_eq!(packet[13..14], [b'1']);
+        assert_eq!(&packet[packet.len() - 4..], &[0x00, 0x0C, 0x80, 0x01]);
+    }
+
+    #[test]
+    fn should_parse_ptr_response() {
+
+        let ipv4 = Ipv4Addr::new(192, 168, 1, 20);
+        let packet = build_ptr_response(0x4242, ipv4, "printer.local");
+
+        assert_eq!(parse_ptr_response(&packet, 0x4242, ipv4), Some("printer.local".to_string()));
+        assert_eq!(parse_ptr_response(&packet, 0x4243, ipv4), None);
+        assert_eq!(parse_ptr_response(&packet, 0x4242, Ipv4Addr::new(192, 168, 1, 21)), None);
+    }
+
+    #[test]
+    fn should_reject_malformed_packets() {
+
+        let ipv4 = Ipv4Addr::new(192, 168, 1, 20);
+        let packet = build_ptr_response(0x4242, ipv4, "printer.local");
+
+        assert_eq!(parse_ptr_response(&packet[..packet.len() - 3], 0x4242, ipv4), None);
+        assert_eq!(parse_ptr_response(&[0x42, 0x42, 0x84], 0x4242, ipv4), None);
+
+        // A compression pointer to itself must not loop forever
+        let mut looping_packet = packet.clone();
+        let answer_offset = build_ptr_query(0x4242, ipv4, false).len();
+        looping_packet[answer_offset..answer_offset + 2].copy_from_slice(&[0xC0, answer_offset as u8]);
+        assert_eq!(parse_ptr_response(&looping_packet, 0x4242, ipv4), None);
+    }
+
+
+    #[test]
+    fn should_parse_a_response() {
+
+        let addresses = vec![Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2)];
+        let packet = build_a_response(0x0808, "host.lab", &addresses);
+
+        assert_eq!(parse_a_response(&packet, 0x0808, "host.lab."), addresses);
+        assert_eq!(parse_a_response(&packet, 0x0808, "other.lab"), Vec::<Ipv4Addr>::new());
+        assert_eq!(parse_a_response(&packet, 0x0809, "host.lab"), Vec::<Ipv4Addr>::new());
+    }
+
+    #[test]
+    fn should_follow_ptr_aliases() {
+
+        // Classless reverse delegation (RFC 2317): the reverse name is an alias
+        // to a name in the delegated zone, which holds the PTR record.
+        let ipv4 = Ipv4Addr::new(192, 0, 2, 10);
+        let mut packet = build_ptr_query(0x0707, ipv4, false);
+        packet[2] = 0x81;
+        packet[3] = 0x80;
+        packet[7] = 2;
+
+        let alias_name = [&[2u8][..], b"10", &[5], b"0-127", &[0xC0, 0x0F]].concat();
+        packet.extend_from_slice(&[0xC0, 0x0C]);
+        packet.extend_from_slice(&CNAME_RECORD_TYPE.to_be_bytes());
+        packet.extend_from_slice(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x78]);
+        packet.extend_from_slice(&(alias_name.len() as u16).to_be_bytes());
+        let alias_offset = packet.len();
+        packet.extend_from_slice(&alias_name);
+
+        let hostname = [&[3u8][..], b"web", &[3], b"lab", &[0]].concat();
+        packet.extend_from_slice(&[0xC0, alias_offset as u8]);
+        packet.extend_from_slice(&PTR_RECORD_TYPE.to_be_bytes());
+        packet.extend_from_slice(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x78]);
+        packet.extend_from_slice(&(hostname.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&hostname);
+
+        assert_eq!(parse_ptr_response(&packet, 0x0707, ipv4), Some("web.lab".to_string()));
+    }
+
+    #[test]
+    fn should_reject_control_characters() {
+
+        let ipv4 = Ipv4Addr::new(192, 168, 1, 20);
+        let packet = build_ptr_response(0x4242, ipv4, "\x1B[2J\x1B[31mrouter\n.local");
+
+        assert_eq!(parse_ptr_response(&packet, 0x4242, ipv4), None);
+    }
+
+}
@@ -0,0 +1,773 @@
+use std::cmp::Ordering;
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::net::Ipv4Addr;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ipnetwork::IpNetwork;
+use pnet_datalink::MacAddr;
+use serde::Serialize;
+use serde::ser::{SerializeMap, Serializer};
+
+use crate::args::{IcmpProbe, NameSource, OutputFormat, OutputTarget, ResultColumn, ScanOptions, ScanTiming, SortKey};
+use crate::filter;
+use crate::inventory::InventoryCheck;
+use crate::network::{DiscoveryMethod, ResponseSummary, TargetDetails};
+use crate::report;
+use crate::time;
+use crate::utils;
+
+/**
+ * Version of the JSON and YAML result structure, increased on each change
+ * of the published JSON Schema.
+ */
+pub const RESULT_SCHEMA_VERSION: u32 = 2;
+
+/**
+ * A value of an exported host, confirmations and latencies keep their type
+ * in JSON and YAML exports. Absent values are exported as null.
+ */
+#[derive(Serialize)]
+#[serde(untagged)]
+enum SerializableValue {
+    Text(Option<String>),
+    Flag(Option<bool>),
+    Number(Option<u64>)
+}
+
+impl SerializableValue {
+
+    fn to_csv_field(&self) -> String {
+        match self {
+            SerializableValue::Text(text) => text.clone().unwrap_or_default(),
+            SerializableValue::Flag(flag) => flag.map(|flag| flag.to_string()).unwrap_or_default(),
+            SerializableValue::Number(number) => number.map(|number| number.to_string()).unwrap_or_default()
+        }
+    }
+}
+
+/**
+ * An exported host, holding the selected columns in their selection order.
+ */
+struct SerializableResultItem {
+    fields: Vec<(&'static str, SerializableValue)>
+}
+
+impl Serialize for SerializableResultItem {
+
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+
+        let mut map = serializer.serialize_map(Some(self.fields.len()))?;
+        for (name, value) in &self.fields {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+fn get_column_value(detail: &TargetDetails, column: ResultColumn) -> SerializableValue {
+
+    let dhcp = detail.dhcp.as_ref();
+    let text = match column {
+        ResultColumn::Ipv4 => Some(detail.ipv4.to_string()),
+        ResultColumn::Mac => Some(detail.mac.to_string()),
+        ResultColumn::Hostname => detail.hostname.clone(),
+        ResultColumn::HostnameSource => detail.hostname_source.map(|name_source| name_source.name().to_string()),
+        ResultColumn::HostnameConfirmed => return SerializableValue::Flag(detail.hostname_confirmed),
+        ResultColumn::Vendor => detail.vendor.clone(),
+        ResultColumn::DeviceClass => detail.device_class.clone(),
+        ResultColumn::FoundBy => Some(detail.found_by.name().to_string()),
+        ResultColumn::BaselineViolations => Some(utils::join_violations(&detail.violations)),
+        ResultColumn::OpenPorts => Some(utils::format_port_list(&detail.open_ports)),
+        ResultColumn::DhcpHostname => dhcp.and_then(|fingerprint| fingerprint.hostname.clone()),
+        ResultColumn::DhcpVendorClass => dhcp.and_then(|fingerprint| fingerprint.vendor_class.clone()),
+        ResultColumn::DhcpParameters => dhcp.map(|fingerprint| fingerprint.format_parameter_list()),
+        ResultColumn::OsGuess => dhcp.and_then(|fingerprint| fingerprint.guess_os()),
+        ResultColumn::Latency => return SerializableValue::Number(detail.behaviour.min_latency_us)
+    };
+
+    // Empty lists (violations, ports, ...) are absent values as well
+    SerializableValue::Text(text.filter(|text| !text.is_empty()))
+}
+
+#[derive(Serialize)]
+struct SerializableInventoryItem {
+    ipv4: String,
+    expected_mac: String,
+    found_mac: Option<String>,
+    status: String
+}
+
+/**
+ * The interface and networks of a scan, written in the metadata of exports
+ * and reports.
+ */
+pub struct ScanContext<'a> {
+    pub interface_name: &'a str,
+    pub interface_mac: Option<MacAddr>,
+    pub source_ipv4: Ipv4Addr,
+    pub ip_networks: &'a [&'a IpNetwork]
+}
+
+#[derive(Serialize)]
+struct SerializableInterface {
+    name: String,
+    mac: Option<String>,
+    ipv4: String
+}
+
+/**
+ * Effective scan options, exactly one of the interval, bandwidth and packet
+ * rate is set (depending on the timing option).
+ */
+#[derive(Serialize)]
+struct SerializableScanOptions {
+    profile: String,
+    interval_us: Option<u64>,
+    bandwidth_bps: Option<u64>,
+    packet_rate: Option<u64>,
+    burst_size: u32,
+    timeout_ms: u64,
+    retry_count: usize,
+    vlan_id: Option<u16>
+}
+
+#[derive(Serialize)]
+struct SerializableScanMetadata {
+    started_at: String,
+    finished_at: String,
+    interface: SerializableInterface,
+    networks: Vec<String>,
+    options: SerializableScanOptions
+}
+
+#[derive(Serialize)]
+struct SerializableGlobalResult {
+    schema_version: u32,
+    tool_version: &'static str,
+    scan: SerializableScanMetadata,
+    packet_count: usize,
+    arp_count: usize,
+    duration_ms: u128,
+    request_count: u64,
+    request_rate: Option<f64>,
+    results: Vec<SerializableResultItem>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inventory: Option<Vec<SerializableInventoryItem>>
+}
+
+fn get_serializable_inventory(inventory_checks: &[InventoryCheck]) -> Vec<SerializableInventoryItem> {
+
+    inventory_checks.iter().map(|check| SerializableInventoryItem {
+        ipv4: check.ipv4.to_string(),
+        expected_mac: check.expected_mac.to_string(),
+        found_mac: check.found_mac.map(|found_mac| found_mac.to_string()),
+        status: check.status.name().to_string()
+    }).collect()
+}
+
+fn get_serializable_options(options: &ScanOptions) -> SerializableScanOptions {
+
+    let (interval_us, bandwidth_bps, packet_rate) = match options.scan_timing {
+        ScanTiming::Interval(interval_us) => (Some(interval_us), None, None),
+        ScanTiming::Bandwidth(bits_second) => (None, Some(bits_second), None),
+        ScanTiming::PacketRate(packet_rate) => (None, None, Some(packet_rate))
+    };
+
+    SerializableScanOptions {
+        profile: options.profile.name().to_string(),
+        interval_us,
+        bandwidth_bps,
+        packet_rate,
+        burst_size: options.burst_size,
+        timeout_ms: options.timeout_ms,
+        retry_count: options.retry_count,
+        vlan_id: options.vlan_id
+    }
+}
+
+/**
+ * Export the effective scan options as a JSON object, with the same fields
+ * as the 'scan.options' object of JSON results.
+ */
+pub fn export_scan_options(options: &ScanOptions) -> String {
+
+    serde_json::to_string(&get_serializable_options(options)).unwrap_or_else(|err| {
+        eprintln!("Could not export scan options ({})", err);
+        process::exit(1);
+    })
+}
+
+fn get_serializable_metadata(response_summary: &ResponseSummary, context: &ScanContext, options: &ScanOptions) -> SerializableScanMetadata {
+
+    let duration_seconds = u64::try_from(response_summary.duration_ms / 1000).unwrap_or(u64::MAX);
+
+    SerializableScanMetadata {
+        started_at: time::format_rfc3339(response_summary.start_timestamp),
+        finished_at: time::format_rfc3339(response_summary.start_timestamp.saturating_add(duration_seconds)),
+        interface: SerializableInterface {
+            name: context.interface_name.to_string(),
+            mac: options.source_mac.or(context.interface_mac).map(|mac| mac.to_string()),
+            ipv4: context.source_ipv4.to_string()
+        },
+        networks: context.ip_networks.iter().map(|ip_network| ip_network.to_string()).collect(),
+        options: get_serializable_options(options)
+    }
+}
+
+/**
+ * Transforms an ARP scan result (including KPI and target details) to a structure
+ * that can be serialized for export (JSON, YAML, CSV, ...), hosts only hold
+ * the selected columns. The structure follows the published JSON Schema
+ * (see 'schema/arp-scan-results.schema.json').
+ */
+fn get_serializable_result(response_summary: &ResponseSummary, target_details: &[TargetDetails], inventory_checks: Option<&[InventoryCheck]>, context: &ScanContext, options: &ScanOptions) -> SerializableGlobalResult {
+
+    let columns = options.export_columns();
+    let exportable_results: Vec<SerializableResultItem> = target_details.iter()
+        .map(|detail| SerializableResultItem {
+            fields: columns.iter().map(|column| (column.name(), get_column_value(detail, *column))).collect()
+        })
+        .collect();
+
+    SerializableGlobalResult {
+        schema_version: RESULT_SCHEMA_VERSION,
+        tool_version: env!("CARGO_PKG_VERSION"),
+        scan: get_serializable_metadata(response_summary, context, options),
+        packet_count: response_summary.packet_count,
+        arp_count: response_summary.arp_count,
+        duration_ms: response_summary.duration_ms,
+        request_count: response_summary.request_count,
+        request_rate: response_summary.request_rate,
+        results: exportable_results,
+        inventory: inventory_checks.map(get_serializable_inventory)
+    }
+}
+
+/**
+ * Export the scan results as a JSON string with response details (timings, ...)
+ * and ARP results from the local network.
+ */
+pub fn export_to_json(response_summary: &ResponseSummary, target_details: &[TargetDetails], inventory_checks: Option<&[InventoryCheck]>, context: &ScanContext, options: &ScanOptions) -> String {
+
+    let global_result = get_serializable_result(response_summary, target_details, inventory_checks, context, options);
+
+    serde_json::to_string(&global_result).unwrap_or_else(|err| {
+        eprintln!("Could not export JSON results ({})", err);
+        process::exit(1);
+    })
+}
+
+#[derive(Serialize)]
+struct SerializableStreamSummary {
+    #[serde(rename = "type")]
+    record_type: &'static str,
+    #[serde(flatten)]
+    result: SerializableGlobalResult
+}
+
+/**
+ * Export the final summary of a streamed scan as a single JSON line (NDJSON),
+ * written after the host records. The summary holds all scan results, with
+ * the details found after the capture (hostnames, ports, ...).
+ */
+pub fn export_to_ndjson_summary(response_summary: &ResponseSummary, target_details: &[TargetDetails], inventory_checks: Option<&[InventoryCheck]>, context: &ScanContext, options: &ScanOptions) -> String {
+
+    let stream_summary = SerializableStreamSummary {
+        record_type: "summary",
+        result: get_serializable_result(response_summary, target_details, inventory_checks, context, options)
+    };
+
+    serde_json::to_string(&stream_summary).unwrap_or_else(|err| {
+        eprintln!("Could not export JSON summary ({})", err);
+        process::exit(1);
+    })
+}
+
+/**
+ * Export the scan results as a YAML string with response details (timings, ...)
+ * and ARP results from the local network.
+ */
+pub fn export_to_yaml(response_summary: &ResponseSummary, target_details: &[TargetDetails], inventory_checks: Option<&[InventoryCheck]>, context: &ScanContext, options: &ScanOptions) -> String {
+
+    let global_result = get_serializable_result(response_summary, target_details, inventory_checks, context, options);
+
+    serde_yaml::to_string(&global_result).unwrap_or_else(|err| {
+        eprintln!("Could not export YAML results ({})", err);
+        process::exit(1);
+    })
+}
+
+/**
+ * Export the scan results as a CSV string with response details (timings, ...)
+ * and ARP results from the local network.
+ */
+pub fn export_to_csv(response_summary: &ResponseSummary, target_details: &[TargetDetails], inventory_checks: Option<&[InventoryCheck]>, context: &ScanContext, options: &ScanOptions) -> String {
+
+    let global_result = get_serializable_result(response_summary, target_details, inventory_checks, context, options);
+    let columns = options.export_columns();
+
+    let mut wtr = csv::Writer::from_writer(vec![]);
+
+    // CSV exports hold a single table: the inventory verification replaces
+    // the host list when an inventory is verified.
+    let serialize_result = match global_result.inventory {
+        Some(inventory_items) => inventory_items.into_iter().try_for_each(|item| wtr.serialize(item)),
+        None if global_result.results.is_empty() => Ok(()),
+        None => wtr.write_record(columns.iter().map(|column| column.name())).and_then(|_| {
+            global_result.results.iter().try_for_each(|result| {
+                wtr.write_record(result.fields.iter().map(|(_, value)| value.to_csv_field()))
+            })
+        })
+    };
+    serialize_result.unwrap_or_else(|err| {
+        eprintln!("Could not serialize result to CSV ({})", err);
+        process::exit(1);
+    });
+    wtr.flush().unwrap_or_else(|err| {
+        eprintln!("Could not flush CSV writer buffer ({})", err);
+        process::exit(1);
+    });
+
+    let convert_writer = wtr.into_inner().unwrap_or_else(|err| {
+        eprintln!("Could not convert final CSV result ({})", err);
+        process::exit(1);
+    });
+    String::from_utf8(convert_writer).unwrap_or_else(|err| {
+        eprintln!("Could not convert final CSV result to text ({})", err);
+        process::exit(1);
+    })
+}
+
+/**
+ * Escape a text for XML attribute values.
+ */
+pub fn escape_xml(text: &str) -> String {
+
+    text.chars().fold(String::with_capacity(text.len()), |mut escaped, character| {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters are not allowed in XML 1.0 documents
+            character if character.is_control() && !matches!(character, '\t' | '\n' | '\r') => {},
+            character => escaped.push(character)
+        };
+        escaped
+    })
+}
+
+/**
+ * Format a discovered host as an nmap 'host' element. The status reason is
+ * the reply that revealed the host, like nmap does for ARP ping scans.
+ */
+fn format_nmap_host(detail: &TargetDetails, icmp_probe: Option<IcmpProbe>) -> String {
+
+    let reason = match (detail.found_by, icmp_probe) {
+        (DiscoveryMethod::Icmp, Some(IcmpProbe::Timestamp)) => "timestamp-reply",
+        (DiscoveryMethod::Icmp, _) => "echo-reply",
+        _ => "arp-response"
+    };
+
+    let mut host_xml = format!("<host><status state=\"up\" reason=\"{}\" reason_ttl=\"0\"/>\n", reason);
+    host_xml.push_str(&format!("<address addr=\"{}\" addrtype=\"ipv4\"/>\n", detail.ipv4));
+
+    // MAC addresses are written in uppercase in nmap reports, the vendor
+    // attribute is only written when the vendor is known.
+    let mac_text = detail.mac.to_string().to_uppercase();
+    match &detail.vendor {
+        Some(vendor) => host_xml.push_str(&format!("<address addr=\"{}\" addrtype=\"mac\" vendor=\"{}\"/>\n", mac_text, escape_xml(vendor))),
+        None => host_xml.push_str(&format!("<address addr=\"{}\" addrtype=\"mac\"/>\n", mac_text))
+    };
+
+    host_xml.push_str("<hostnames>\n");
+    if let Some(hostname) = &detail.hostname {
+        let hostname_type = match detail.hostname_source {
+            Some(NameSource::Dns) | None => "PTR",
+            Some(_) => "user"
+        };
+        host_xml.push_str(&format!("<hostname name=\"{}\" type=\"{}\"/>\n", escape_xml(hostname), hostname_type));
+    }
+    host_xml.push_str("</hostnames>\n");
+
+    if !detail.open_ports.is_empty() {
+        host_xml.push_str("<ports>");
+        for port in &detail.open_ports {
+            host_xml.push_str(&format!("<port protocol=\"tcp\" portid=\"{}\"><state state=\"open\" reason=\"syn-ack\" reason_ttl=\"0\"/></port>\n", port));
+        }
+        host_xml.push_str("</ports>\n");
+    }
+
+    host_xml.push_str("</host>\n");
+    host_xml
+}
+
+/**
+ * Export the scan results as an XML document following the nmap XML format,
+ * the document is similar to the output of an nmap ARP ping scan ('nmap -sn
+ * -PR'). Tools importing nmap reports can then read the ARP scan results.
+ */
+pub fn export_to_nmap_xml(response_summary: &ResponseSummary, target_details: &[TargetDetails], options: &ScanOptions) -> String {
+
+    let command_line = env::args().collect::<Vec<String>>().join(" ");
+    let start_timestamp = response_summary.start_timestamp;
+    let finished_timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(start_timestamp);
+    let elapsed_seconds = (response_summary.duration_ms as f64) / 1000.0;
+
+    let up_count = target_details.len();
+    let total_count = response_summary.target_count.max(up_count as u128);
+    let down_count = total_count - up_count as u128;
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<!DOCTYPE nmaprun>\n");
+    xml.push_str(&format!(
+        "<nmaprun scanner=\"arp-scan\" args=\"{}\" start=\"{}\" startstr=\"{}\" version=\"{}\" xmloutputversion=\"1.05\">\n",
+        escape_xml(&command_line),
+        start_timestamp,
+        time::format_timestamp(start_timestamp),
+        env!("CARGO_PKG_VERSION")
+    ));
+    xml.push_str("<verbose level=\"0\"/>\n");
+    xml.push_str("<debugging level=\"0\"/>\n");
+
+    for detail in target_details {
+        xml.push_str(&format_nmap_host(detail, options.icmp_probe));
+    }
+
+    let finished_text = time::format_timestamp(finished_timestamp);
+    let address_text = match total_count {
+        1 => "1 IP address".to_string(),
+        _ => format!("{} IP addresses", total_count)
+    };
+    let up_text = match up_count {
+        1 => "1 host up".to_string(),
+        _ => format!("{} hosts up", up_count)
+    };
+    xml.push_str(&format!(
+        "<runstats><finished time=\"{}\" timestr=\"{}\" summary=\"arp-scan done at {}; {} ({}) scanned in {:.2} seconds\" elapsed=\"{:.2}\" exit=\"success\"/>",
+        finished_timestamp,
+        finished_text,
+        finished_text,
+        address_text,
+        up_text,
+        elapsed_seconds,
+        elapsed_seconds
+    ));
+    xml.push_str(&format!("<hosts up=\"{}\" down=\"{}\" total=\"{}\"/>\n", up_count, down_count, total_count));
+    xml.push_str("</runstats>\n");
+    xml.push_str("</nmaprun>\n");
+    xml
+}
+
+/**
+ * Render the scan results in the requested format. Plain results written to
+ * a file are not colored, since ANSI escape codes would pollute the file.
+ */
+fn render_output(output: &OutputTarget, response_summary: &ResponseSummary, target_details: &[TargetDetails], inventory_checks: Option<&[InventoryCheck]>, context: &ScanContext, options: &ScanOptions) -> String {
+
+    match output.format {
+        OutputFormat::Plain => {
+            let mut plain_output: Vec<u8> = vec![];
+            utils::write_scan_results(&mut plain_output, response_summary, target_details, inventory_checks, options, output.path.is_none()).unwrap_or_else(|err| {
+                eprintln!("Could not render plain results ({})", err);
+                process::exit(1);
+            });
+            String::from_utf8_lossy(&plain_output).into_owned()
+        },
+        OutputFormat::Json if output.path.is_none() && options.stream_output => format!("{}\n", export_to_ndjson_summary(response_summary, target_details, inventory_checks, context, options)),
+        OutputFormat::Json => format!("{}\n", export_to_json(response_summary, target_details, inventory_checks, context, options)),
+        OutputFormat::Yaml => format!("{}\n", export_to_yaml(response_summary, target_details, inventory_checks, context, options)),
+        OutputFormat::Csv => export_to_csv(response_summary, target_details, inventory_checks, context, options),
+        OutputFormat::Xml => export_to_nmap_xml(response_summary, target_details, options),
+        OutputFormat::Markdown => report::export_to_markdown(response_summary, target_details, context.interface_name, context.ip_networks, options),
+        OutputFormat::Html => report::export_to_html(response_summary, target_details, context.interface_name, context.ip_networks, options)
+    }
+}
+
+/**
+ * Sort the hosts with the requested key. Hosts without a value for the key
+ * are listed last, ties are ordered by IPv4 address.
+ */
+fn sort_target_details(target_details: &mut [TargetDetails], sort_key: SortKey) {
+
+    target_details.sort_by(|first, second| {
+        let key_order = match sort_key {
+            SortKey::Ip => Ordering::Equal,
+            SortKey::Mac => first.mac.cmp(&second.mac),
+            SortKey::Vendor => compare_missing_last(first.vendor.as_ref().map(|vendor| vendor.to_lowercase()), second.vendor.as_ref().map(|vendor| vendor.to_lowercase())),
+            SortKey::Hostname => compare_missing_last(first.hostname.as_ref().map(|hostname| hostname.to_lowercase()), second.hostname.as_ref().map(|hostname| hostname.to_lowercase())),
+            SortKey::Latency => compare_missing_last(first.behaviour.min_latency_us, second.behaviour.min_latency_us)
+        };
+        key_order.then(first.ipv4.cmp(&second.ipv4))
+    });
+}
+
+fn compare_missing_last<T: Ord>(first: Option<T>, second: Option<T>) -> Ordering {
+    match (first, second) {
+        (Some(first), Some(second)) => first.cmp(&second),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal
+    }
+}
+
+/**
+ * Files of the '-o FORMAT:FILE' outputs, opened (and created) before dropping
+ * privileges: the unprivileged user may not be allowed to create them. The
+ * files are kept open and rewritten after each scan.
+ */
+pub struct OutputFiles {
+    files: Vec<Option<File>>
+}
+
+impl OutputFiles {
+
+    pub fn open(options: &ScanOptions) -> Result<Self, String> {
+
+        let files = options.outputs.iter().map(|output| match &output.path {
+            Some(path) => OpenOptions::new().write(true).create(true).truncate(true).open(path).map(Some).map_err(|err| {
+                format!("Could not open {} output file {} ({})", output.format.name(), path, err)
+            }),
+            None => Ok(None)
+        }).collect::<Result<Vec<Option<File>>, String>>()?;
+
+        Ok(OutputFiles { files })
+    }
+}
+
+/**
+ * Replace the content of an output file, the file may hold the results of a
+ * previous periodic scan.
+ */
+fn rewrite_file(file: &mut File, content: &str) -> io::Result<()> {
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(content.as_bytes())?;
+    file.flush()
+}
+
+/**
+ * Write the scan results to all requested outputs, in the order given on the
+ * command line. At most one output is printed on stdout, the other outputs
+ * are written to their files. A failing output is reported and the other
+ * outputs are still written, the function returns false if any output failed.
+ */
+pub fn write_outputs(response_summary: &ResponseSummary, target_details: &mut [TargetDetails], inventory_checks: Option<&[InventoryCheck]>, context: &ScanContext, options: &ScanOptions, output_files: &mut OutputFiles) -> bool {
+
+    sort_target_details(target_details, options.sort_key);
+
+    // Filtered hosts are only hidden from the outputs, notifications and
+    // scan records still use all hosts.
+    let filtered_details: Vec<TargetDetails> = match options.host_filters.is_empty() {
+        true => vec![],
+        false => target_details.iter().filter(|detail| filter::matches_all(&options.host_filters, detail)).cloned().collect()
+    };
+    let displayed_details: &[TargetDetails] = match options.host_filters.is_empty() {
+        true => target_details,
+        false => &filtered_details
+    };
+
+    let mut is_successful = true;
+    for (output, output_file) in options.outputs.iter().zip(output_files.files.iter_mut()) {
+
+        let content = render_output(output, response_summary, displayed_details, inventory_checks, context, options);
+        let write_result = match output_file {
+            Some(file) => rewrite_file(file, &content),
+            None => {
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(content.as_bytes()).and_then(|_| stdout.flush())
+            }
+        };
+
+        if let Err(err) = write_result {
+            match &output.path {
+                Some(path) => eprintln!("Could not write {} results to {} ({})", output.format.name(), path, err),
+                None => eprintln!("Could not print {} results ({})", output.format.name(), err)
+            };
+            is_successful = false;
+        }
+    }
+    is_successful
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use std::sync::Arc;
+
+    use crate::args;
+
+    fn build_summary() -> ResponseSummary {
+        ResponseSummary {
+            packet_count: 2,
+            arp_count: 1,
+            start_timestamp: 1_700_000_000,
+            duration_ms: 1200,
+            target_count: 256,
+            conflict_count: 0,
+            request_count: 256,
+            request_rate: None
+        }
+    }
+
+    fn build_options(arguments: &[&str]) -> Arc<ScanOptions> {
+
+        let mut command_line = vec!["arp-scan"];
+        command_line.extend_from_slice(arguments);
+
+        Arc::new(ScanOptions::new(&args::build_args().get_matches_from(command_line)))
+    }
+
+    fn build_context<'a>(ip_networks: &'a [&'a IpNetwork]) -> ScanContext<'a> {
+        ScanContext {
+            interface_name: "eth0",
+            interface_mac: Some(MacAddr::new(2, 0, 0, 0, 0, 1)),
+            source_ipv4: Ipv4Addr::new(10, 20, 0, 5),
+            ip_networks
+        }
+    }
+
+    fn build_target(found_by: DiscoveryMethod) -> TargetDetails {
+        TargetDetails {
+            hostname: Some("printer.lab".to_string()),
+            hostname_source: Some(NameSource::Dns),
+            vendor: Some("Smith & Sons".to_string()),
+            found_by,
+            ..TargetDetails::build(Ipv4Addr::new(10, 20, 0, 12), "00:11:22:aa:bb:cc".parse().unwrap())
+        }
+    }
+
+    #[test]
+    fn should_escape_xml_attributes() {
+
+        assert_eq!(escape_xml("<\"R&D\" 'lab'>"), "&lt;&quot;R&amp;D&quot; &apos;lab&apos;&gt;");
+        assert_eq!(escape_xml("lab\x1B[31m\x00"), "lab[31m");
+    }
+
+    #[test]
+    fn should_format_nmap_host() {
+
+        let host_xml = format_nmap_host(&build_target(DiscoveryMethod::Arp), None);
+
+        assert_eq!(host_xml, "<host><status state=\"up\" reason=\"arp-response\" reason_ttl=\"0\"/>
+<address addr=\"10.20.0.12\" addrtype=\"ipv4\"/>
+<address addr=\"00:11:22:AA:BB:CC\" addrtype=\"mac\" vendor=\"Smith &amp; Sons\"/>
+<hostnames>
+<hostname name=\"printer.lab\" type=\"PTR\"/>
+</hostnames>
+</host>
+");
+    }
+
+    #[test]
+    fn should_use_icmp_reply_as_status_reason() {
+
+        let mut target = build_target(DiscoveryMethod::Icmp);
+        target.open_ports = vec![22];
+
+        let host_xml = format_nmap_host(&target, Some(IcmpProbe::Timestamp));
+
+        assert!(host_xml.contains("reason=\"timestamp-reply\""));
+        assert!(host_xml.contains("<port protocol=\"tcp\" portid=\"22\"><state state=\"open\""));
+    }
+
+    #[test]
+    fn should_sort_hosts_with_missing_values_last() {
+
+        let mut first = build_target(DiscoveryMethod::Arp);
+        first.vendor = None;
+        first.behaviour.min_latency_us = Some(900);
+        let mut second = build_target(DiscoveryMethod::Arp);
+        second.ipv4 = Ipv4Addr::new(10, 20, 0, 30);
+        second.vendor = Some("Apple, Inc".to_string());
+        let mut third = build_target(DiscoveryMethod::Arp);
+        third.ipv4 = Ipv4Addr::new(10, 20, 0, 2);
+        third.behaviour.min_latency_us = Some(300);
+
+        let mut target_details = vec![first, second, third];
+
+        sort_target_details(&mut target_details, SortKey::Vendor);
+        let sorted_ips: Vec<Ipv4Addr> = target_details.iter().map(|detail| detail.ipv4).collect();
+        assert_eq!(sorted_ips, vec![Ipv4Addr::new(10, 20, 0, 30), Ipv4Addr::new(10, 20, 0, 2), Ipv4Addr::new(10, 20, 0, 12)]);
+
+        sort_target_details(&mut target_details, SortKey::Latency);
+        let sorted_ips: Vec<Ipv4Addr> = target_details.iter().map(|detail| detail.ipv4).collect();
+        assert_eq!(sorted_ips, vec![Ipv4Addr::new(10, 20, 0, 2), Ipv4Addr::new(10, 20, 0, 12), Ipv4Addr::new(10, 20, 0, 30)]);
+    }
+
+    #[test]
+    fn should_export_selected_columns() {
+
+        let network: IpNetwork = "10.20.0.0/24".parse().unwrap();
+        let mut target = build_target(DiscoveryMethod::Arp);
+        target.behaviour.min_latency_us = Some(1250);
+        let options = build_options(&["--columns", "mac,ip,latency,hostname_confirmed"]);
+
+        let json_text = export_to_json(&build_summary(), &[target.clone()], None, &build_context(&[&network]), &options);
+        assert!(json_text.contains("\"results\":[{\"mac\":\"00:11:22:aa:bb:cc\",\"ipv4\":\"10.20.0.12\",\"latency_us\":1250,\"hostname_confirmed\":null}]"));
+
+        let csv_text = export_to_csv(&build_summary(), &[target], None, &build_context(&[&network]), &options);
+        assert_eq!(csv_text, "mac,ipv4,latency_us,hostname_confirmed\n00:11:22:aa:bb:cc,10.20.0.12,1250,\n");
+    }
+
+    #[test]
+    fn should_export_scan_metadata() {
+
+        let network: IpNetwork = "10.20.0.0/24".parse().unwrap();
+        let mut target = build_target(DiscoveryMethod::Arp);
+        target.hostname = None;
+        let options = build_options(&["-r", "3", "-Q", "42", "--rate", "500"]);
+
+        let json_text = export_to_json(&build_summary(), &[target], None, &build_context(&[&network]), &options);
+        let result: serde_json::Value = serde_json::from_str(&json_text).unwrap();
+
+        assert_eq!(result["schema_version"], RESULT_SCHEMA_VERSION);
+        assert_eq!(result["scan"]["started_at"], "2023-11-14T22:13:20Z");
+        assert_eq!(result["scan"]["finished_at"], "2023-11-14T22:13:21Z");
+        assert_eq!(result["scan"]["interface"], serde_json::json!({ "name": "eth0", "mac": "02:00:00:00:00:01", "ipv4": "10.20.0.5" }));
+        assert_eq!(result["scan"]["networks"], serde_json::json!(["10.20.0.0/24"]));
+        assert_eq!(result["scan"]["options"]["packet_rate"], 500);
+        assert_eq!(result["scan"]["options"]["interval_us"], serde_json::Value::Null);
+        assert_eq!(result["scan"]["options"]["retry_count"], 3);
+        assert_eq!(result["scan"]["options"]["vlan_id"], 42);
+        assert_eq!(result["results"][0]["hostname"], serde_json::Value::Null);
+        assert_eq!(result["results"][0]["open_ports"], serde_json::Value::Null);
+    }
+
+    /**
+     * The published JSON Schema must describe every exported field, the
+     * schema version is checked against the exported version.
+     */
+    #[test]
+    fn should_follow_published_schema() {
+
+        let schema: serde_json::Value = serde_json::from_str(include_str!("../schema/arp-scan-results.schema.json")).unwrap();
+        let network: IpNetwork = "10.20.0.0/24".parse().unwrap();
+        let options = build_options(&["--columns", &ResultColumn::EXPORT_DEFAULT.iter().chain([ResultColumn::Latency].iter()).map(|column| column.name()).collect::<Vec<&str>>().join(",")]);
+        let json_text = export_to_json(&build_summary(), &[build_target(DiscoveryMethod::Arp)], None, &build_context(&[&network]), &options);
+        let result: serde_json::Value = serde_json::from_str(&json_text).unwrap();
+
+        let assert_described = |value: &serde_json::Value, schema_object: &serde_json::Value| {
+            for key in value.as_object().unwrap().keys() {
+                assert!(schema_object["properties"].get(key).is_some(), "field '{}' is missing in the schema", key);
+            }
+            for key in schema_object["required"].as_array().into_iter().flatten() {
+                assert!(value.get(key.as_str().unwrap()).is_some(), "required field '{}' is not exported", key);
+            }
+        };
+        assert_described(&result, &schema);
+        assert_described(&result["scan"], &schema["properties"]["scan"]);
+        assert_described(&result["scan"]["interface"], &schema["properties"]["scan"]["properties"]["interface"]);
+        assert_described(&result["scan"]["options"], &schema["properties"]["scan"]["properties"]["options"]);
+        assert_described(&result["results"][0], &schema["properties"]["results"]["items"]);
+        assert_eq!(schema["properties"]["schema_version"]["const"], RESULT_SCHEMA_VERSION);
+    }
+
+}
@@ -0,0 +1,213 @@
+use ipnetwork::Ipv4Network;
+
+use crate::network::TargetDetails;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FilterField {
+    Ip,
+    Mac,
+    Vendor,
+    Hostname,
+    DeviceClass
+}
+
+impl FilterField {
+
+    fn parse(field_text: &str) -> Result<Self, String> {
+        match field_text {
+            "ip" | "ipv4" => Ok(FilterField::Ip),
+            "mac" => Ok(FilterField::Mac),
+            "vendor" => Ok(FilterField::Vendor),
+            "hostname" => Ok(FilterField::Hostname),
+            "class" | "device_class" => Ok(FilterField::DeviceClass),
+            _ => Err(format!("unknown field '{}'", field_text))
+        }
+    }
+
+    fn value(&self, detail: &TargetDetails) -> Option<String> {
+        match self {
+            FilterField::Ip => Some(detail.ipv4.to_string()),
+            FilterField::Mac => Some(detail.mac.to_string()),
+            FilterField::Vendor => detail.vendor.clone(),
+            FilterField::Hostname => detail.hostname.clone(),
+            FilterField::DeviceClass => detail.device_class.clone()
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FilterCondition {
+    Missing,
+    Present,
+    Contains(String),
+    Is(String),
+    InNetwork(Ipv4Network),
+    InOui(String)
+}
+
+/**
+ * A filter on discovered hosts, written as 'FIELD OPERATOR [VALUE]' and
+ * optionally negated with a leading 'not' (e.g. 'vendor contains Cisco',
+ * 'hostname missing', 'mac in 00:11:22' or 'not ip in 10.20.0.0/28').
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct HostFilter {
+    pub field: FilterField,
+    pub condition: FilterCondition,
+    pub negated: bool
+}
+
+/**
+ * Normalize a MAC address prefix (OUI) to the lowercase and colon-separated
+ * notation used by MAC addresses.
+ */
+fn normalize_mac_prefix(prefix_text: &str) -> Result<String, String> {
+
+    let prefix = prefix_text.to_lowercase().replace('-', ":");
+    let is_valid = !prefix.is_empty() && prefix.split(':').all(|part| {
+        (1..=2).contains(&part.len()) && part.chars().all(|character| character.is_ascii_hexdigit())
+    });
+
+    match is_valid {
+        true => Ok(prefix.split(':').map(|part| format!("{:0>2}", part)).collect::<Vec<String>>().join(":")),
+        false => Err(format!("invalid MAC prefix '{}'", prefix_text))
+    }
+}
+
+impl HostFilter {
+
+    pub fn parse(filter_text: &str) -> Result<Self, String> {
+
+        let mut remaining_text = filter_text.trim();
+        let negated = match remaining_text.strip_prefix("not ") {
+            Some(filter_text) => {
+                remaining_text = filter_text.trim_start();
+                true
+            },
+            None => false
+        };
+
+        let (field_text, remaining_text) = remaining_text.split_once(' ').unwrap_or((remaining_text, ""));
+        let remaining_text = remaining_text.trim_start();
+        let (operator_text, value_text) = remaining_text.split_once(' ').unwrap_or((remaining_text, ""));
+
+        // Values may be quoted, to keep the spaces of a vendor name
+        let value_text = value_text.trim();
+        let value_text = value_text.strip_prefix('"').and_then(|value| value.strip_suffix('"')).unwrap_or(value_text);
+
+        let field = FilterField::parse(field_text)?;
+        let condition = match (operator_text, value_text.is_empty()) {
+            ("missing", true) => FilterCondition::Missing,
+            ("present", true) => FilterCondition::Present,
+            ("missing" | "present", false) => return Err(format!("operator '{}' expects no value", operator_text)),
+            ("contains" | "is" | "in", true) => return Err(format!("operator '{}' expects a value", operator_text)),
+            ("contains", false) => FilterCondition::Contains(value_text.to_lowercase()),
+            ("is", false) => FilterCondition::Is(value_text.to_lowercase()),
+            ("in", false) => match field {
+                FilterField::Ip => FilterCondition::InNetwork(value_text.parse::<Ipv4Network>().map_err(|err| format!("invalid network '{}' ({})", value_text, err))?),
+                FilterField::Mac => FilterCondition::InOui(normalize_mac_prefix(value_text)?),
+                _ => return Err("operator 'in' is only available for ip and mac fields".to_string())
+            },
+            ("", _) => return Err(format!("missing operator in '{}'", filter_text)),
+            _ => return Err(format!("unknown operator '{}'", operator_text))
+        };
+
+        Ok(HostFilter { field, condition, negated })
+    }
+
+    pub fn matches(&self, detail: &TargetDetails) -> bool {
+
+        let value = self.field.value(detail);
+        let is_matching = match (&self.condition, value) {
+            (FilterCondition::Missing, value) => value.map_or(true, |value| value.is_empty()),
+            (FilterCondition::Present, value) => value.is_some_and(|value| !value.is_empty()),
+            (_, None) => false,
+            (FilterCondition::Contains(text), Some(value)) => value.to_lowercase().contains(text.as_str()),
+            (FilterCondition::Is(text), Some(value)) => value.to_lowercase() == *text,
+            (FilterCondition::InNetwork(network), Some(_)) => network.contains(detail.ipv4),
+            (FilterCondition::InOui(prefix), Some(value)) => value.starts_with(prefix.as_str())
+        };
+
+        is_matching != self.negated
+    }
+}
+
+/**
+ * Check a host against all filters, hosts are kept when every filter matches.
+ */
+pub fn matches_all(filters: &[HostFilter], detail: &TargetDetails) -> bool {
+    filters.iter().all(|filter| filter.matches(detail))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use std::net::Ipv4Addr;
+
+    fn build_target(ipv4: Ipv4Addr, mac: &str, vendor: Option<&str>, hostname: Option<&str>) -> TargetDetails {
+        TargetDetails {
+            hostname: hostname.map(|hostname| hostname.to_string()),
+            vendor: vendor.map(|vendor| vendor.to_string()),
+            ..TargetDetails::build(ipv4, mac.parse().unwrap())
+        }
+    }
+
+    #[test]
+    fn should_parse_filters() {
+
+        assert_eq!(HostFilter::parse("vendor contains \"Cisco Systems\""), Ok(HostFilter {
+            field: FilterField::Vendor,
+            condition: FilterCondition::Contains("cisco systems".to_string()),
+            negated: false
+        }));
+        assert_eq!(HostFilter::parse("not hostname missing"), Ok(HostFilter {
+            field: FilterField::Hostname,
+            condition: FilterCondition::Missing,
+            negated: true
+        }));
+        assert_eq!(HostFilter::parse("mac in B8-27-EB").map(|filter| filter.condition), Ok(FilterCondition::InOui("b8:27:eb".to_string())));
+        assert_eq!(HostFilter::parse("ip in 10.20.0.0/28").map(|filter| filter.condition), Ok(FilterCondition::InNetwork("10.20.0.0/28".parse().unwrap())));
+
+        assert!(HostFilter::parse("vendor").is_err());
+        assert!(HostFilter::parse("vendor contains").is_err());
+        assert!(HostFilter::parse("hostname missing now").is_err());
+        assert!(HostFilter::parse("vendor in Cisco").is_err());
+        assert!(HostFilter::parse("mac in 00:zz").is_err());
+        assert!(HostFilter::parse("latency is 3").is_err());
+        assert!(HostFilter::parse("vendor like Cisco").is_err());
+    }
+
+    #[test]
+    fn should_match_filters() {
+
+        let router = build_target(Ipv4Addr::new(10, 20, 0, 1), "00:1b:54:aa:bb:cc", Some("Cisco Systems, Inc"), Some("gateway.lab"));
+        let sensor = build_target(Ipv4Addr::new(10, 20, 0, 40), "b8:27:eb:01:02:03", Some("Raspberry Pi Foundation"), None);
+        let unknown = build_target(Ipv4Addr::new(10, 20, 0, 41), "02:00:00:00:00:01", None, None);
+
+        let vendor_filter = HostFilter::parse("vendor contains cisco").unwrap();
+        assert!(vendor_filter.matches(&router));
+        assert!(!vendor_filter.matches(&sensor));
+        assert!(!vendor_filter.matches(&unknown));
+
+        let hostname_filter = HostFilter::parse("hostname missing").unwrap();
+        assert!(!hostname_filter.matches(&router));
+        assert!(hostname_filter.matches(&sensor));
+
+        let oui_filter = HostFilter::parse("mac in b8:27:eb").unwrap();
+        assert!(oui_filter.matches(&sensor));
+        assert!(!oui_filter.matches(&router));
+
+        let network_filter = HostFilter::parse("not ip in 10.20.0.32/27").unwrap();
+        assert!(network_filter.matches(&router));
+        assert!(!network_filter.matches(&sensor));
+
+        let filters = vec![HostFilter::parse("vendor present").unwrap(), HostFilter::parse("hostname missing").unwrap()];
+        assert!(matches_all(&filters, &sensor));
+        assert!(!matches_all(&filters, &router));
+        assert!(!matches_all(&filters, &unknown));
+        assert!(matches_all(&[], &unknown));
+    }
+
+}
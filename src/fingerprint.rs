@@ -0,0 +1,378 @@
+use std::fs::File;
+use std::process;
+
+use csv::Reader;
+use serde::Deserialize;
+
+use crate::args::ScanOptions;
+use crate::network::TargetDetails;
+
+// A matching vendor is a stronger hint than a single behaviour signal
+const VENDOR_WEIGHT: i32 = 3;
+const SIGNAL_WEIGHT: i32 = 1;
+
+/**
+ * Content of the Ethernet padding in ARP replies. ARP packets are shorter than
+ * the Ethernet minimum frame size, some network stacks fill the remaining
+ * bytes with zeros while others leak memory content.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PaddingKind {
+    Absent,
+    Zero,
+    NonZero
+}
+
+impl PaddingKind {
+
+    pub fn from_padding(padding: &[u8]) -> Self {
+        match padding {
+            [] => PaddingKind::Absent,
+            _ if padding.iter().all(|byte| *byte == 0) => PaddingKind::Zero,
+            _ => PaddingKind::NonZero
+        }
+    }
+
+    fn parse(padding_text: &str) -> Result<Self, String> {
+        match padding_text {
+            "absent" => Ok(PaddingKind::Absent),
+            "zero" => Ok(PaddingKind::Zero),
+            "nonzero" => Ok(PaddingKind::NonZero),
+            _ => Err(format!("invalid padding '{}' (absent/zero/nonzero)", padding_text))
+        }
+    }
+}
+
+/**
+ * ARP behaviour of a host observed during the scan: the fastest reply delay,
+ * the Ethernet padding of its replies, the amount of replies compared to the
+ * requests sent and the gratuitous ARP packets it sent.
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ArpBehaviour {
+    pub min_latency_us: Option<u64>,
+    pub padding: Option<PaddingKind>,
+    pub request_count: u32,
+    pub reply_count: u32,
+    pub gratuitous_count: u32
+}
+
+impl ArpBehaviour {
+
+    pub fn has_duplicate_replies(&self) -> bool {
+        self.request_count > 0 && self.reply_count > self.request_count
+    }
+
+    pub fn record_latency(&mut self, latency_us: u64) {
+        self.min_latency_us = Some(self.min_latency_us.map_or(latency_us, |min_latency_us| min_latency_us.min(latency_us)));
+    }
+}
+
+/**
+ * A fingerprint rule, as written in the fingerprint database. Empty fields
+ * are not checked, boolean fields are written as 'yes' or 'no'.
+ */
+#[derive(Deserialize)]
+struct FingerprintRecord {
+    device_class: String,
+    vendor: String,
+    min_latency_us: String,
+    max_latency_us: String,
+    padding: String,
+    gratuitous: String,
+    duplicates: String,
+    custom_probe: String,
+    local_mac: String
+}
+
+#[derive(Debug, PartialEq)]
+pub struct FingerprintRule {
+    device_class: String,
+    vendor: Option<String>,
+    min_latency_us: Option<u64>,
+    max_latency_us: Option<u64>,
+    padding: Option<PaddingKind>,
+    gratuitous: Option<bool>,
+    duplicates: Option<bool>,
+    custom_probe: Option<bool>,
+    local_mac: Option<bool>
+}
+
+impl FingerprintRule {
+
+    fn from_record(record: FingerprintRecord) -> Result<Self, String> {
+
+        let optional_text = |text: &str| match text.trim() {
+            "" => None,
+            text => Some(text.to_string())
+        };
+        let parse_number = |text: &str| match text.trim() {
+            "" => Ok(None),
+            text => text.parse::<u64>().map(Some).map_err(|err| format!("invalid latency '{}' ({})", text, err))
+        };
+        let parse_flag = |text: &str| match text.trim() {
+            "" => Ok(None),
+            "yes" => Ok(Some(true)),
+            "no" => Ok(Some(false)),
+            text => Err(format!("invalid flag '{}' (yes/no)", text))
+        };
+
+        Ok(FingerprintRule {
+            device_class: record.device_class.trim().to_string(),
+            vendor: optional_text(&record.vendor).map(|vendor| vendor.to_lowercase()),
+            min_latency_us: parse_number(&record.min_latency_us)?,
+            max_latency_us: parse_number(&record.max_latency_us)?,
+            padding: optional_text(&record.padding).map(|padding| PaddingKind::parse(&padding)).transpose()?,
+            gratuitous: parse_flag(&record.gratuitous)?,
+            duplicates: parse_flag(&record.duplicates)?,
+            custom_probe: parse_flag(&record.custom_probe)?,
+            local_mac: parse_flag(&record.local_mac)?
+        })
+    }
+
+    /**
+     * Score the host against this rule: each matching condition adds its
+     * weight, each failing condition removes it. Conditions on signals that
+     * were not observed (no latency for a resumed host, ...) are skipped.
+     */
+    fn score(&self, signals: &HostSignals) -> i32 {
+
+        let mut conditions: Vec<(Option<bool>, i32)> = vec![];
+
+        if let Some(vendor_pattern) = &self.vendor {
+            let vendor_match = signals.vendor.as_ref().map(|vendor| vendor.contains(vendor_pattern.as_str()));
+            conditions.push((Some(vendor_match.unwrap_or(false)), VENDOR_WEIGHT));
+        }
+        if let Some(min_latency_us) = self.min_latency_us {
+            conditions.push((signals.min_latency_us.map(|latency_us| latency_us >= min_latency_us), SIGNAL_WEIGHT));
+        }
+        if let Some(max_latency_us) = self.max_latency_us {
+            conditions.push((signals.min_latency_us.map(|latency_us| latency_us <= max_latency_us), SIGNAL_WEIGHT));
+        }
+        if let Some(padding) = self.padding {
+            conditions.push((signals.padding.map(|host_padding| host_padding == padding), SIGNAL_WEIGHT));
+        }
+        if let Some(gratuitous) = self.gratuitous {
+            conditions.push((Some(signals.gratuitous == gratuitous), SIGNAL_WEIGHT));
+        }
+        if let Some(duplicates) = self.duplicates {
+            conditions.push((Some(signals.duplicates == duplicates), SIGNAL_WEIGHT));
+        }
+        if let Some(custom_probe) = self.custom_probe {
+            conditions.push((signals.custom_probe.map(|host_probe| host_probe == custom_probe), SIGNAL_WEIGHT));
+        }
+        if let Some(local_mac) = self.local_mac {
+            conditions.push((Some(signals.local_mac == local_mac), SIGNAL_WEIGHT));
+        }
+
+        conditions.iter().map(|(condition_match, weight)| match condition_match {
+            Some(true) => *weight,
+            Some(false) => -weight,
+            None => 0
+        }).sum()
+    }
+}
+
+/**
+ * Signals of a discovered host, compared with the fingerprint rules. The
+ * custom probe signal is only known if the scan used non-standard ARP
+ * hardware types or operations.
+ */
+pub struct HostSignals {
+    vendor: Option<String>,
+    min_latency_us: Option<u64>,
+    padding: Option<PaddingKind>,
+    gratuitous: bool,
+    duplicates: bool,
+    custom_probe: Option<bool>,
+    local_mac: bool
+}
+
+impl HostSignals {
+
+    pub fn new(target_detail: &TargetDetails, options: &ScanOptions) -> Self {
+
+        let behaviour = &target_detail.behaviour;
+        HostSignals {
+            vendor: target_detail.vendor.as_ref().map(|vendor| vendor.to_lowercase()),
+            min_latency_us: behaviour.min_latency_us,
+            padding: behaviour.padding,
+            gratuitous: behaviour.gratuitous_count > 0,
+            duplicates: behaviour.has_duplicate_replies(),
+            custom_probe: match options.uses_custom_probe() {
+                true => Some(behaviour.reply_count > 0),
+                false => None
+            },
+            // Locally administered addresses are used by virtual machines and
+            // by phones with private (randomized) MAC addresses.
+            local_mac: target_detail.mac.0 & 0x02 != 0
+        }
+    }
+}
+
+/**
+ * The device fingerprint database, a CSV file with one rule per line. The
+ * whole database is loaded in memory since it only holds a few rules.
+ */
+pub struct FingerprintDatabase {
+    rules: Vec<FingerprintRule>
+}
+
+impl FingerprintDatabase {
+
+    /**
+     * Load the fingerprint database from a file opened before dropping the
+     * privileges. A missing file will not throw an error, but leave the
+     * database without rules.
+     */
+    pub fn new(file: Option<File>, path: &str) -> Self {
+
+        let rules = match file {
+            Some(file) => FingerprintDatabase::parse_rules(Reader::from_reader(file)).unwrap_or_else(|err| {
+                eprintln!("Could not read fingerprint database {} ({})", path, err);
+                process::exit(1);
+            }),
+            None => vec![]
+        };
+
+        FingerprintDatabase { rules }
+    }
+
+    fn parse_rules<R: std::io::Read>(mut reader: Reader<R>) -> Result<Vec<FingerprintRule>, String> {
+
+        reader.deserialize::<FingerprintRecord>()
+            .map(|record_result| {
+                let record = record_result.map_err(|err| err.to_string())?;
+                FingerprintRule::from_record(record)
+            })
+            .collect()
+    }
+
+    pub fn has_fingerprint_db(&self) -> bool {
+        !self.rules.is_empty()
+    }
+
+    /**
+     * Find the device class of the best matching rule, the first rule wins
+     * in case of equal scores. Rules with a null or negative score are never
+     * selected.
+     */
+    pub fn classify(&self, signals: &HostSignals) -> Option<String> {
+
+        let mut best_match: Option<(&FingerprintRule, i32)> = None;
+        for rule in self.rules.iter() {
+
+            let score = rule.score(signals);
+            let is_better = match best_match {
+                Some((_, best_score)) => score > best_score,
+                None => score > 0
+            };
+            if is_better {
+                best_match = Some((rule, score));
+            }
+        }
+
+        best_match.map(|(rule, _)| rule.device_class.clone())
+    }
+}
+
+/**
+ * Classify all discovered hosts with the fingerprint database, hosts that do
+ * not match any rule are left without device class.
+ */
+pub fn classify_targets(target_details: &mut [TargetDetails], database: &FingerprintDatabase, options: &ScanOptions) {
+
+    if !database.has_fingerprint_db() {
+        return;
+    }
+
+    for target_detail in target_details.iter_mut() {
+        let signals = HostSignals::new(target_detail, options);
+        target_detail.device_class = database.classify(&signals);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const FINGERPRINT_CONTENT: &str = "device_class,vendor,min_latency_us,max_latency_us,padding,gratuitous,duplicates,custom_probe,local_mac
+Smartphone,Apple,20000,,,,,,
+Computer,Apple,,20000,,,,,
+Smartphone,,20000,,,,,,yes
+Embedded device,,,,nonzero,,,,
+Proxy ARP device,,,,,,yes,,
+";
+
+    fn load_database(content: &str) -> FingerprintDatabase {
+        let rules = FingerprintDatabase::parse_rules(Reader::from_reader(content.as_bytes())).unwrap();
+        FingerprintDatabase { rules }
+    }
+
+    fn build_signals(vendor: Option<&str>, min_latency_us: Option<u64>, padding: Option<PaddingKind>) -> HostSignals {
+        HostSignals {
+            vendor: vendor.map(|vendor| vendor.to_lowercase()),
+            min_latency_us,
+            padding,
+            gratuitous: false,
+            duplicates: false,
+            custom_probe: None,
+            local_mac: false
+        }
+    }
+
+    #[test]
+    fn should_detect_padding_kinds() {
+
+        assert_eq!(PaddingKind::from_padding(&[]), PaddingKind::Absent);
+        assert_eq!(PaddingKind::from_padding(&[0; 18]), PaddingKind::Zero);
+        assert_eq!(PaddingKind::from_padding(&[0, 0, 0x42, 0]), PaddingKind::NonZero);
+    }
+
+    #[test]
+    fn should_use_latency_to_split_vendor_rules() {
+
+        let database = load_database(FINGERPRINT_CONTENT);
+
+        let phone_signals = build_signals(Some("Apple, Inc."), Some(85_000), Some(PaddingKind::Zero));
+        let laptop_signals = build_signals(Some("Apple, Inc."), Some(900), Some(PaddingKind::Zero));
+
+        assert_eq!(database.classify(&phone_signals), Some("Smartphone".to_string()));
+        assert_eq!(database.classify(&laptop_signals), Some("Computer".to_string()));
+    }
+
+    #[test]
+    fn should_classify_from_behaviour_only() {
+
+        let database = load_database(FINGERPRINT_CONTENT);
+
+        let embedded_signals = build_signals(None, Some(3_000), Some(PaddingKind::NonZero));
+        let mut proxy_signals = build_signals(None, None, None);
+        proxy_signals.duplicates = true;
+
+        assert_eq!(database.classify(&embedded_signals), Some("Embedded device".to_string()));
+        assert_eq!(database.classify(&proxy_signals), Some("Proxy ARP device".to_string()));
+        assert_eq!(database.classify(&build_signals(None, Some(3_000), Some(PaddingKind::Zero))), None);
+    }
+
+    #[test]
+    fn should_reject_invalid_rules() {
+
+        let invalid_content = "device_class,vendor,min_latency_us,max_latency_us,padding,gratuitous,duplicates,custom_probe,local_mac
+Printer,,,,,maybe,,,
+";
+        let rules = FingerprintDatabase::parse_rules(Reader::from_reader(invalid_content.as_bytes()));
+
+        assert_eq!(rules, Err("invalid flag 'maybe' (yes/no)".to_string()));
+    }
+
+    #[test]
+    fn should_load_default_database() {
+
+        let database = FingerprintDatabase::new(File::open("./data/device-fingerprints.csv").ok(), "./data/device-fingerprints.csv");
+
+        assert!(database.has_fingerprint_db());
+    }
+
+}
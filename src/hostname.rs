@@ -0,0 +1,798 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use dns_lookup::{lookup_addr, lookup_host};
+
+use crate::args::{NameSource, ScanOptions};
+use crate::dns;
+use crate::hosts::HostsFile;
+use crate::netbios;
+use crate::network::TargetDetails;
+use crate::progress::ProgressCounter;
+
+const MDNS_ADDRESS: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+const LLMNR_PORT: u16 = 5355;
+
+// Multicast DNS responses may exceed the usual Ethernet MTU
+const UDP_BUFFER_SIZE: usize = 9000;
+
+/**
+ * Settings of the hostname resolution phase: the amount of concurrent
+ * lookups, the maximum time for a single lookup and the maximum time for the
+ * whole resolution phase (the global budget).
+ */
+pub struct ResolveSettings {
+    pub worker_count: usize,
+    pub lookup_timeout: Duration,
+    pub budget: Duration
+}
+
+impl ResolveSettings {
+
+    pub fn new(options: &ScanOptions) -> Self {
+        ResolveSettings {
+            worker_count: options.resolve_workers,
+            lookup_timeout: Duration::from_millis(options.resolve_timeout_ms),
+            budget: Duration::from_millis(options.resolve_budget_ms)
+        }
+    }
+}
+
+/**
+ * A hostname found for a host, with the source that produced it. The forward
+ * confirmation is only set when requested, for names found in DNS or in the
+ * hosts file.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolvedName {
+    pub hostname: String,
+    pub source: NameSource,
+    pub confirmed: Option<bool>
+}
+
+/**
+ * The name sources and resolver configuration used for each host lookup:
+ * the hosts source reads the hosts file, and the DNS source queries the given
+ * DNS servers (or the system resolver if no server is given).
+ */
+pub struct NameResolver {
+    name_sources: Vec<NameSource>,
+    local_ipv4: Ipv4Addr,
+    source_timeout: Duration,
+    dns_servers: Vec<SocketAddrV4>,
+    hosts_file: Option<HostsFile>,
+    forward_confirm: bool
+}
+
+impl NameResolver {
+
+    pub fn new(options: &ScanOptions, local_ipv4: Ipv4Addr) -> Self {
+        NameResolver {
+            name_sources: options.name_sources.clone(),
+            local_ipv4,
+            source_timeout: Duration::from_millis(options.resolve_timeout_ms),
+            dns_servers: options.dns_servers.clone(),
+            hosts_file: options.hosts().cloned(),
+            forward_confirm: options.forward_confirm
+        }
+    }
+
+    /**
+     * Maximum amount of queries for a single host, each of them limited by
+     * the lookup timeout (one per source and the forward confirmation).
+     */
+    fn max_query_count(&self) -> u32 {
+        (self.name_sources.len().max(1) + usize::from(self.forward_confirm)) as u32
+    }
+
+    /**
+     * Query each name source in order until a hostname is found, then check
+     * the hostname with a forward lookup if requested.
+     */
+    pub fn find_name(&self, ipv4: Ipv4Addr) -> Option<ResolvedName> {
+
+        let (hostname, source) = self.name_sources.iter().find_map(|name_source| match name_source {
+            NameSource::Dns => self.find_dns_name(ipv4).map(|hostname| (hostname, NameSource::Dns)),
+            NameSource::Hosts => {
+                let hostname = self.hosts_file.as_ref().and_then(|hosts_file| hosts_file.find_name(ipv4));
+                hostname.map(|hostname| (hostname, NameSource::Hosts))
+            },
+            NameSource::Mdns => {
+                let responder = SocketAddrV4::new(MDNS_ADDRESS, MDNS_PORT);
+                query_mdns(ipv4, self.local_ipv4, responder, self.source_timeout).map(|hostname| (hostname, NameSource::Mdns))
+            },
+            NameSource::Netbios => {
+                let responder = SocketAddrV4::new(ipv4, netbios::NETBIOS_PORT);
+                query_netbios(responder, self.source_timeout).map(|hostname| (hostname, NameSource::Netbios))
+            },
+            NameSource::Llmnr => {
+                let responder = SocketAddrV4::new(ipv4, LLMNR_PORT);
+                query_llmnr(ipv4, responder, self.source_timeout).map(|hostname| (hostname, NameSource::Llmnr))
+            },
+            // DHCP hostnames are captured during the scan, not queried
+            NameSource::Dhcp => None
+        })?;
+
+        let confirmed = self.confirm_name(ipv4, &hostname, source);
+        Some(ResolvedName { hostname, source, confirmed })
+    }
+
+    fn find_dns_name(&self, ipv4: Ipv4Addr) -> Option<String> {
+
+        match self.dns_servers.is_empty() {
+            true => find_hostname(ipv4),
+            false => self.query_dns_servers(|server, timeout| query_dns_ptr(ipv4, server, timeout)).flatten()
+        }
+    }
+
+    /**
+     * Check that the hostname resolves back to the host IPv4 address (forward
+     * confirmation). Names from other sources than DNS are not checked.
+     */
+    fn confirm_name(&self, ipv4: Ipv4Addr, hostname: &str, source: NameSource) -> Option<bool> {
+
+        if !self.forward_confirm {
+            return None;
+        }
+
+        let addresses = match source {
+            NameSource::Hosts => self.hosts_file.as_ref().map(|hosts_file| hosts_file.find_addresses(hostname)).unwrap_or_default(),
+            NameSource::Dns if self.dns_servers.is_empty() => find_host_addresses(hostname).unwrap_or_default(),
+            NameSource::Dns => self.query_dns_servers(|server, timeout| query_dns_addresses(hostname, server, timeout)).unwrap_or_default(),
+            _ => return None
+        };
+
+        Some(addresses.contains(&ipv4))
+    }
+
+    /**
+     * Query the DNS servers in order, the next server is only queried if the
+     * previous one did not answer. The lookup timeout is shared by all servers.
+     */
+    fn query_dns_servers<T, Q>(&self, query: Q) -> Option<T>
+    where
+        Q: Fn(SocketAddrV4, Duration) -> Option<T>
+    {
+        let server_timeout = self.source_timeout / self.dns_servers.len().max(1) as u32;
+        self.dns_servers.iter().find_map(|server| query(*server, server_timeout))
+    }
+
+}
+
+/**
+ * Resolve the hostnames of all discovered targets with the requested name
+ * sources, then fall back on the hostnames sent by DHCP clients. Hostnames
+ * from DHCP are also used when the resolution is disabled, since they were
+ * captured during the scan without sending any query.
+ */
+pub fn resolve_target_hostnames(target_details: &mut [TargetDetails], options: &ScanOptions, local_ipv4: Ipv4Addr) {
+
+    if options.resolve_hostname && !target_details.is_empty() {
+        resolve_network_names(target_details, options, local_ipv4);
+    }
+    assign_dhcp_hostnames(target_details);
+}
+
+/**
+ * Query the requested name sources for all targets. Multicast DNS queries are
+ * sent from the given local IPv4 address, which selects the scanned interface.
+ * In plain output mode, the resolution progress is displayed on stderr.
+ */
+fn resolve_network_names(target_details: &mut [TargetDetails], options: &ScanOptions, local_ipv4: Ipv4Addr) {
+
+    let name_resolver = Arc::new(NameResolver::new(options, local_ipv4));
+
+    // Each query is limited by the lookup timeout, a host may therefore be
+    // queried for the lookup timeout multiplied by the amount of queries.
+    let mut settings = ResolveSettings::new(options);
+    settings.lookup_timeout *= name_resolver.max_query_count();
+
+    let lookup = move |ipv4: Ipv4Addr| name_resolver.find_name(ipv4);
+
+    let ipv4_list: Vec<Ipv4Addr> = target_details.iter().map(|detail| detail.ipv4).collect();
+    let progress = ProgressCounter::new("Resolving hostnames", options);
+
+    let resolved_names = resolve_all(&ipv4_list, &settings, lookup, |resolved_count, total_count| {
+        progress.update(resolved_count, total_count);
+    });
+    progress.finish();
+
+    for detail in target_details.iter_mut() {
+
+        let resolved_name = resolved_names.get(&detail.ipv4);
+        detail.hostname = resolved_name.map(|resolved_name| resolved_name.hostname.clone());
+        detail.hostname_source = resolved_name.map(|resolved_name| resolved_name.source);
+        detail.hostname_confirmed = resolved_name.and_then(|resolved_name| resolved_name.confirmed);
+    }
+}
+
+/**
+ * Hostnames sent by DHCP clients are only used when no other source gave a
+ * name, since they are chosen by the device itself.
+ */
+fn assign_dhcp_hostnames(target_details: &mut [TargetDetails]) {
+
+    for detail in target_details.iter_mut() {
+
+        let dhcp_hostname = detail.dhcp.as_ref().and_then(|fingerprint| fingerprint.hostname.clone());
+        if detail.hostname.is_none() && dhcp_hostname.is_some() {
+            detail.hostname = dhcp_hostname;
+            detail.hostname_source = Some(NameSource::Dhcp);
+        }
+    }
+}
+
+/**
+ * Events sent by the resolution workers, a lookup is announced when started
+ * so that its timeout can be tracked.
+ */
+enum LookupEvent<T> {
+    Started(Ipv4Addr),
+    Finished(Ipv4Addr, Option<T>)
+}
+
+/**
+ * Resolve a list of IPv4 addresses with a bounded pool of workers, lookups
+ * are run directly on the workers. Each lookup is limited in time, and no new
+ * lookup is started once the global budget is exhausted. Lookups that did not
+ * finish in time are left unresolved: the system resolver can not be
+ * cancelled, the worker stays busy until the lookup returns and its late
+ * result is ignored.
+ */
+pub fn resolve_all<T, L, P>(ipv4_list: &[Ipv4Addr], settings: &ResolveSettings, lookup: L, mut on_progress: P) -> HashMap<Ipv4Addr, T>
+where
+    T: Send + 'static,
+    L: Fn(Ipv4Addr) -> Option<T> + Send + Sync + Clone + 'static,
+    P: FnMut(usize, usize)
+{
+    let deadline = Instant::now() + settings.budget;
+    let pending_queue = Arc::new(Mutex::new(VecDeque::from(ipv4_list.to_vec())));
+    let (event_tx, event_rx) = mpsc::channel::<LookupEvent<T>>();
+
+    let worker_count = settings.worker_count.clamp(1, ipv4_list.len().max(1));
+    for _ in 0..worker_count {
+
+        let pending_queue = Arc::clone(&pending_queue);
+        let event_tx = event_tx.clone();
+        let lookup = lookup.clone();
+
+        thread::spawn(move || loop {
+
+            if Instant::now() >= deadline {
+                break;
+            }
+
+            let next_ipv4 = pending_queue.lock().ok().and_then(|mut queue| queue.pop_front());
+            let ipv4 = match next_ipv4 {
+                Some(ipv4) => ipv4,
+                None => break
+            };
+
+            if event_tx.send(LookupEvent::Started(ipv4)).is_err() {
+                break;
+            }
+            let hostname = lookup(ipv4);
+            if event_tx.send(LookupEvent::Finished(ipv4, hostname)).is_err() {
+                break;
+            }
+        });
+    }
+    drop(event_tx);
+
+    let total_count = ipv4_list.len();
+    let mut resolved_count = 0;
+    let mut running_lookups: HashMap<Ipv4Addr, Instant> = HashMap::new();
+    let mut hostnames: HashMap<Ipv4Addr, T> = HashMap::new();
+    on_progress(resolved_count, total_count);
+
+    while resolved_count < total_count {
+
+        // The next wake-up is the earliest lookup timeout, or the end of the
+        // global budget when no lookup is running.
+        let next_expiry = running_lookups.values().min().copied().unwrap_or(deadline).min(deadline);
+        let event = event_rx.recv_timeout(next_expiry.saturating_duration_since(Instant::now()));
+
+        match event {
+            Ok(LookupEvent::Started(ipv4)) => {
+                running_lookups.insert(ipv4, Instant::now() + settings.lookup_timeout);
+            },
+            Ok(LookupEvent::Finished(ipv4, hostname)) => {
+
+                // Lookups finishing after their timeout were already counted
+                if running_lookups.remove(&ipv4).is_none() {
+                    continue;
+                }
+                if let Some(hostname) = hostname {
+                    hostnames.insert(ipv4, hostname);
+                }
+                resolved_count += 1;
+                on_progress(resolved_count, total_count);
+            },
+            Err(RecvTimeoutError::Timeout) => {
+
+                if Instant::now() >= deadline {
+                    break;
+                }
+                let now = Instant::now();
+                let expired_count = running_lookups.len();
+                running_lookups.retain(|_, expiry| *expiry > now);
+                resolved_count += expired_count - running_lookups.len();
+                on_progress(resolved_count, total_count);
+            },
+            Err(RecvTimeoutError::Disconnected) => break
+        }
+    }
+
+    hostnames
+}
+
+/**
+ * Find the local hostname linked to an IPv4 address. This will perform a
+ * reverse DNS request in the local network to find the IPv4 hostname.
+ */
+pub fn find_hostname(ipv4: Ipv4Addr) -> Option<String> {
+
+    let ip: IpAddr = ipv4.into();
+    match lookup_addr(&ip) {
+        Ok(hostname) => {
+
+            // The 'lookup_addr' function returns an IP address if no hostname
+            // was found. If this is the case, we prefer switching to None.
+            if hostname.parse::<IpAddr>().is_ok() {
+                return None;
+            }
+
+            Some(hostname)
+        },
+        Err(_) => None
+    }
+}
+
+/**
+ * Find the IPv4 addresses of a hostname with the system resolver, this is
+ * used to confirm the hostnames found with reverse DNS.
+ */
+fn find_host_addresses(hostname: &str) -> Option<Vec<Ipv4Addr>> {
+
+    let addresses = lookup_host(hostname).ok()?;
+    Some(addresses.into_iter().filter_map(|address| match address {
+        IpAddr::V4(ipv4) => Some(ipv4),
+        IpAddr::V6(_) => None
+    }).collect())
+}
+
+/**
+ * Send a recursive PTR query to a DNS server. A negative answer from the
+ * server is returned as an empty result, the next server is then not queried.
+ */
+fn query_dns_ptr(ipv4: Ipv4Addr, server: SocketAddrV4, timeout: Duration) -> Option<Option<String>> {
+
+    let transaction_id: u16 = rand::random();
+    let request = dns::build_recursive_query(transaction_id, &dns::reverse_name(ipv4), dns::PTR_RECORD_TYPE);
+
+    exchange_datagrams(Ipv4Addr::UNSPECIFIED, server, &request, timeout, |packet| {
+        dns::is_response(packet, transaction_id).then(|| dns::parse_ptr_response(packet, transaction_id, ipv4))
+    })
+}
+
+/**
+ * Send a recursive A query to a DNS server, this is used to confirm the
+ * hostnames found with reverse DNS.
+ */
+fn query_dns_addresses(hostname: &str, server: SocketAddrV4, timeout: Duration) -> Option<Vec<Ipv4Addr>> {
+
+    let transaction_id: u16 = rand::random();
+    let request = dns::build_recursive_query(transaction_id, hostname, dns::A_RECORD_TYPE);
+
+    exchange_datagrams(Ipv4Addr::UNSPECIFIED, server, &request, timeout, |packet| {
+        dns::is_response(packet, transaction_id).then(|| dns::parse_a_response(packet, transaction_id, hostname))
+    })
+}
+
+/**
+ * Send a reverse PTR query to the multicast DNS group. Since the query is not
+ * sent from port 5353, responders answer directly to the query socket (legacy
+ * unicast response). Binding the socket to the interface address makes the
+ * system send the multicast query on this interface.
+ */
+fn query_mdns(ipv4: Ipv4Addr, local_ipv4: Ipv4Addr, responder: SocketAddrV4, timeout: Duration) -> Option<String> {
+
+    let transaction_id: u16 = rand::random();
+    let request = dns::build_ptr_query(transaction_id, ipv4, true);
+
+    exchange_datagrams(local_ipv4, responder, &request, timeout, |packet| {
+        dns::parse_ptr_response(packet, transaction_id, ipv4)
+    })
+}
+
+/**
+ * Send a reverse PTR query to the LLMNR responder of the host. Reverse
+ * queries are sent in unicast to the host itself (RFC 4795).
+ */
+fn query_llmnr(ipv4: Ipv4Addr, responder: SocketAddrV4, timeout: Duration) -> Option<String> {
+
+    let transaction_id: u16 = rand::random();
+    let request = dns::build_ptr_query(transaction_id, ipv4, false);
+
+    exchange_datagrams(Ipv4Addr::UNSPECIFIED, responder, &request, timeout, |packet| {
+        dns::parse_ptr_response(packet, transaction_id, ipv4)
+    })
+}
+
+/**
+ * Send a NetBIOS node status request to the host, and keep the workstation
+ * name from its name table.
+ */
+fn query_netbios(responder: SocketAddrV4, timeout: Duration) -> Option<String> {
+
+    let transaction_id: u16 = rand::random();
+    let request = netbios::build_node_status_request(transaction_id);
+
+    exchange_datagrams(Ipv4Addr::UNSPECIFIED, responder, &request, timeout, |packet| {
+        netbios::parse_node_status_response(packet, transaction_id)
+    })
+}
+
+/**
+ * Send a single UDP request and wait for a matching response until the
+ * timeout. Unrelated datagrams (other transactions, malformed packets) are
+ * ignored by the parse function and the socket keeps listening. Unicast
+ * requests only accept datagrams sent back by the responder.
+ */
+fn exchange_datagrams<T, P>(local_ipv4: Ipv4Addr, responder: SocketAddrV4, request: &[u8], timeout: Duration, parse: P) -> Option<T>
+where
+    P: Fn(&[u8]) -> Option<T>
+{
+    let socket = UdpSocket::bind(SocketAddrV4::new(local_ipv4, 0)).ok()?;
+    if responder.ip().is_multicast() {
+        socket.set_multicast_ttl_v4(255).ok()?;
+    }
+    socket.send_to(request, responder).ok()?;
+
+    let deadline = Instant::now() + timeout;
+    let mut buffer = vec![0u8; UDP_BUFFER_SIZE];
+    loop {
+
+        let remaining_time = deadline.saturating_duration_since(Instant::now());
+        if remaining_time.is_zero() {
+            return None;
+        }
+        socket.set_read_timeout(Some(remaining_time)).ok()?;
+
+        let (packet_size, sender) = socket.recv_from(&mut buffer).ok()?;
+        if !responder.ip().is_multicast() && sender != SocketAddr::V4(responder) {
+            continue;
+        }
+        if let Some(response) = parse(&buffer[..packet_size]) {
+            return Some(response);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use std::env;
+
+    fn build_settings(worker_count: usize, lookup_timeout_ms: u64, budget_ms: u64) -> ResolveSettings {
+        ResolveSettings {
+            worker_count,
+            lookup_timeout: Duration::from_millis(lookup_timeout_ms),
+            budget: Duration::from_millis(budget_ms)
+        }
+    }
+
+    fn slow_lookup(ipv4: Ipv4Addr) -> Option<String> {
+
+        // Addresses ending with .99 never answer in time, others are slow
+        let delay_ms = match ipv4.octets()[3] {
+            99 => 5000,
+            _ => 100
+        };
+        thread::sleep(Duration::from_millis(delay_ms));
+        Some(format!("host-{}", ipv4.octets()[3]))
+    }
+
+    #[test]
+    fn should_resolve_public_ip() {
+
+        // Sometimes, we do not have access to public networks in the test
+        // environment and can pass the OFFLINE environment variable.
+        if env::var("OFFLINE").is_ok() {
+            assert_eq!(true, true);
+        }
+        else {
+            let ipv4 = Ipv4Addr::new(1,1,1,1);
+            assert_eq!(find_hostname(ipv4), Some("one.one.one.one".to_string()));
+        }
+    }
+
+    #[test]
+    fn should_resolve_localhost() {
+
+        let ipv4 = Ipv4Addr::new(127,0,0,1);
+
+        assert_eq!(find_hostname(ipv4), Some("localhost".to_string()));
+    }
+
+    #[test]
+    fn should_not_resolve_unknown_ip() {
+
+        let ipv4 = Ipv4Addr::new(10,254,254,254);
+
+        assert_eq!(find_hostname(ipv4), None);
+    }
+
+    #[test]
+    fn should_resolve_concurrently() {
+
+        let ipv4_list: Vec<Ipv4Addr> = (1..=40).map(|index| Ipv4Addr::new(10, 0, 0, index)).collect();
+        let settings = build_settings(20, 1000, 10_000);
+
+        let start = Instant::now();
+        let hostnames = resolve_all(&ipv4_list, &settings, slow_lookup, |_, _| {});
+
+        // 40 lookups of 100ms with 20 workers should take about 200ms
+        assert!(start.elapsed() < Duration::from_millis(1500));
+        assert_eq!(hostnames.len(), 40);
+        assert_eq!(hostnames.get(&Ipv4Addr::new(10, 0, 0, 7)), Some(&"host-7".to_string()));
+    }
+
+    #[test]
+    fn should_skip_slow_lookups() {
+
+        let ipv4_list = vec![Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 99)];
+        let settings = build_settings(2, 300, 10_000);
+
+        let start = Instant::now();
+        let hostnames = resolve_all(&ipv4_list, &settings, slow_lookup, |_, _| {});
+
+        assert!(start.elapsed() < Duration::from_millis(2000));
+        assert_eq!(hostnames.len(), 1);
+        assert_eq!(hostnames.get(&Ipv4Addr::new(10, 0, 0, 99)), None);
+    }
+
+    #[test]
+    fn should_respect_global_budget() {
+
+        let ipv4_list: Vec<Ipv4Addr> = (1..=50).map(|index| Ipv4Addr::new(10, 0, 1, index)).collect();
+        let settings = build_settings(1, 1000, 350);
+
+        let start = Instant::now();
+        let hostnames = resolve_all(&ipv4_list, &settings, slow_lookup, |_, _| {});
+
+        assert!(start.elapsed() < Duration::from_millis(1500));
+        assert!(hostnames.len() < 10);
+    }
+
+    /**
+     * Start a local UDP responder answering all requests. The responder first
+     * sends an unrelated datagram, which must be ignored by the query.
+     */
+    fn start_responder<R>(respond: R) -> SocketAddrV4
+    where
+        R: Fn(&[u8]) -> Vec<u8> + Send + 'static
+    {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let responder_address = match socket.local_addr().unwrap() {
+            SocketAddr::V4(address) => address,
+            _ => panic!("Expected IPv4 responder")
+        };
+
+        thread::spawn(move || loop {
+            let mut buffer = [0u8; 1500];
+            let (request_size, client_address) = socket.recv_from(&mut buffer).unwrap();
+            socket.send_to(&[0x00, 0x01, 0x02], client_address).unwrap();
+            socket.send_to(&respond(&buffer[..request_size]), client_address).unwrap();
+        });
+
+        responder_address
+    }
+
+    #[test]
+    fn should_query_mdns_responder() {
+
+        let ipv4 = Ipv4Addr::new(192, 168, 1, 20);
+        let responder = start_responder(move |request| {
+            let transaction_id = dns::read_u16(request, 0).unwrap();
+            dns::build_ptr_response(transaction_id, ipv4, "printer.local")
+        });
+
+        let hostname = query_mdns(ipv4, Ipv4Addr::LOCALHOST, responder, Duration::from_millis(1000));
+
+        assert_eq!(hostname, Some("printer.local".to_string()));
+    }
+
+    #[test]
+    fn should_query_llmnr_responder() {
+
+        let ipv4 = Ipv4Addr::new(192, 168, 1, 21);
+        let responder = start_responder(move |request| {
+            let transaction_id = dns::read_u16(request, 0).unwrap();
+            dns::build_ptr_response(transaction_id, ipv4, "laptop")
+        });
+
+        let hostname = query_llmnr(ipv4, responder, Duration::from_millis(1000));
+
+        assert_eq!(hostname, Some("laptop".to_string()));
+    }
+
+    #[test]
+    fn should_query_netbios_responder() {
+
+        let responder = start_responder(|request| {
+            let transaction_id = dns::read_u16(request, 0).unwrap();
+            let entries = vec![
+                netbios::build_name_entry("WORKGROUP", 0x00, 0x8400),
+                netbios::build_name_entry("OFFICE-PC", 0x00, 0x0400)
+            ];
+            netbios::build_node_status_response(transaction_id, &entries)
+        });
+
+        let hostname = query_netbios(responder, Duration::from_millis(1000));
+
+        assert_eq!(hostname, Some("OFFICE-PC".to_string()));
+    }
+
+    #[test]
+    fn should_timeout_silent_responder() {
+
+        let silent_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let responder = match silent_socket.local_addr().unwrap() {
+            SocketAddr::V4(address) => address,
+            _ => panic!("Expected IPv4 responder")
+        };
+
+        let start = Instant::now();
+        let hostname = query_netbios(responder, Duration::from_millis(200));
+
+        assert_eq!(hostname, None);
+        assert!(start.elapsed() < Duration::from_millis(1000));
+    }
+
+    fn build_resolver(dns_servers: Vec<SocketAddrV4>, hosts_file: Option<HostsFile>, forward_confirm: bool) -> NameResolver {
+        NameResolver {
+            name_sources: vec![NameSource::Hosts, NameSource::Dns],
+            local_ipv4: Ipv4Addr::LOCALHOST,
+            source_timeout: Duration::from_millis(1000),
+            dns_servers,
+            hosts_file,
+            forward_confirm
+        }
+    }
+
+    /**
+     * Start a local DNS server answering PTR queries with 'web.lab', and A
+     * queries for 'web.lab' with the given address.
+     */
+    fn start_dns_server(forward_ipv4: Ipv4Addr) -> SocketAddrV4 {
+
+        start_responder(move |request| {
+
+            let transaction_id = dns::read_u16(request, 0).unwrap();
+            let (question_name, question_end) = dns::read_name(request, 12).unwrap();
+
+            match dns::read_u16(request, question_end) {
+                Some(dns::PTR_RECORD_TYPE) => {
+                    let ipv4_text = question_name.trim_end_matches(".in-addr.arpa").split('.').rev().collect::<Vec<&str>>().join(".");
+                    dns::build_ptr_response(transaction_id, ipv4_text.parse().unwrap(), "web.lab")
+                },
+                _ => dns::build_a_response(transaction_id, &question_name, &[forward_ipv4])
+            }
+        })
+    }
+
+    #[test]
+    fn should_record_name_source() {
+
+        let resolver = build_resolver(vec![], None, false);
+        let resolved_name = resolver.find_name(Ipv4Addr::new(127, 0, 0, 1));
+
+        assert_eq!(resolved_name, Some(ResolvedName {
+            hostname: "localhost".to_string(),
+            source: NameSource::Dns,
+            confirmed: None
+        }));
+    }
+
+    #[test]
+    fn should_query_dns_server_with_confirmation() {
+
+        let ipv4 = Ipv4Addr::new(10, 20, 0, 80);
+        let dns_server = start_dns_server(ipv4);
+
+        let resolver = build_resolver(vec![dns_server], None, true);
+        let resolved_name = resolver.find_name(ipv4).unwrap();
+
+        assert_eq!(resolved_name.hostname, "web.lab");
+        assert_eq!(resolved_name.source, NameSource::Dns);
+        assert_eq!(resolved_name.confirmed, Some(true));
+    }
+
+    #[test]
+    fn should_report_forward_mismatch() {
+
+        let ipv4 = Ipv4Addr::new(10, 20, 0, 81);
+        let dns_server = start_dns_server(Ipv4Addr::new(10, 20, 0, 99));
+
+        let resolver = build_resolver(vec![dns_server], None, true);
+        let resolved_name = resolver.find_name(ipv4).unwrap();
+
+        assert_eq!(resolved_name.hostname, "web.lab");
+        assert_eq!(resolved_name.confirmed, Some(false));
+    }
+
+    #[test]
+    fn should_fail_over_dns_servers() {
+
+        let ipv4 = Ipv4Addr::new(10, 20, 0, 82);
+        let silent_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let silent_server = match silent_socket.local_addr().unwrap() {
+            SocketAddr::V4(address) => address,
+            _ => panic!("Expected IPv4 server")
+        };
+        let dns_server = start_dns_server(ipv4);
+
+        let resolver = build_resolver(vec![silent_server, dns_server], None, false);
+        let resolved_name = resolver.find_name(ipv4).unwrap();
+
+        assert_eq!(resolved_name.hostname, "web.lab");
+    }
+
+    #[test]
+    fn should_prefer_hosts_file() {
+
+        let hosts_file = HostsFile::parse("10.20.0.83 db.lab\n10.20.0.84 db.lab\n");
+
+        let resolver = build_resolver(vec![], Some(hosts_file), true);
+        let resolved_name = resolver.find_name(Ipv4Addr::new(10, 20, 0, 84)).unwrap();
+
+        assert_eq!(resolved_name.hostname, "db.lab");
+        assert_eq!(resolved_name.source, NameSource::Hosts);
+        assert_eq!(resolved_name.confirmed, Some(true));
+    }
+
+    #[test]
+    fn should_report_progress() {
+
+        let ipv4_list: Vec<Ipv4Addr> = (1..=5).map(|index| Ipv4Addr::new(10, 0, 2, index)).collect();
+        let settings = build_settings(5, 1000, 5000);
+
+        let mut progress_updates: Vec<(usize, usize)> = vec![];
+        resolve_all(&ipv4_list, &settings, slow_lookup, |resolved_count, total_count| {
+            progress_updates.push((resolved_count, total_count));
+        });
+
+        assert_eq!(progress_updates.first(), Some(&(0, 5)));
+        assert_eq!(progress_updates.last(), Some(&(5, 5)));
+    }
+
+    #[test]
+    fn should_use_dhcp_hostname_without_resolution() {
+
+        let options = ScanOptions::new(&crate::args::build_args().get_matches_from(["arp-scan", "--numeric"]));
+
+        let mac = "00:11:22:33:44:55".parse().unwrap();
+        let mut target_details = vec![TargetDetails {
+            dhcp: Some(crate::dhcp::DhcpFingerprint {
+                mac,
+                hostname: Some("laptop".to_string()),
+                vendor_class: None,
+                parameter_list: vec![]
+            }),
+            ..TargetDetails::build(Ipv4Addr::new(10, 20, 0, 40), mac)
+        }];
+        resolve_target_hostnames(&mut target_details, &options, Ipv4Addr::LOCALHOST);
+
+        assert_eq!(target_details[0].hostname.as_deref(), Some("laptop"));
+        assert_eq!(target_details[0].hostname_source, Some(NameSource::Dhcp));
+    }
+
+}
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+
+/**
+ * Names read from a hosts-style file ('/etc/hosts' format): each line holds
+ * an IP address followed by a canonical name and optional aliases. Comments
+ * start with '#', IPv6 addresses are ignored.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct HostsFile {
+    names_by_address: HashMap<Ipv4Addr, String>,
+    addresses_by_name: HashMap<String, Vec<Ipv4Addr>>
+}
+
+impl HostsFile {
+
+    pub fn parse(content: &str) -> Self {
+
+        let mut hosts_file = HostsFile::default();
+
+        for line in content.lines() {
+
+            let line = line.split('#').next().unwrap_or_default();
+            let mut fields = line.split_whitespace();
+
+            let ipv4 = match fields.next().and_then(|address| address.parse::<Ipv4Addr>().ok()) {
+                Some(ipv4) => ipv4,
+                None => continue
+            };
+
+            for (index, name) in fields.enumerate() {
+
+                // The first name of a line is the canonical name, it is used
+                // for reverse lookups (the first line wins, like resolvers do).
+                if index == 0 {
+                    hosts_file.names_by_address.entry(ipv4).or_insert_with(|| name.to_string());
+                }
+                hosts_file.addresses_by_name.entry(name.to_lowercase()).or_default().push(ipv4);
+            }
+        }
+
+        hosts_file
+    }
+
+    pub fn find_name(&self, ipv4: Ipv4Addr) -> Option<String> {
+        self.names_by_address.get(&ipv4).cloned()
+    }
+
+    pub fn find_addresses(&self, name: &str) -> Vec<Ipv4Addr> {
+        self.addresses_by_name.get(&name.trim_end_matches('.').to_lowercase()).cloned().unwrap_or_default()
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const HOSTS_CONTENT: &str = "# Lab segment
+10.20.0.1   gateway.lab gw
+10.20.0.12  printer.lab   # second floor
+10.20.0.12  old-printer.lab
+fe80::1     ipv6-host.lab
+10.20.0.13  PRINTER.lab
+not-an-ip   broken.lab
+";
+
+    #[test]
+    fn should_find_canonical_names() {
+
+        let hosts_file = HostsFile::parse(HOSTS_CONTENT);
+
+        assert_eq!(hosts_file.find_name(Ipv4Addr::new(10, 20, 0, 1)), Some("gateway.lab".to_string()));
+        assert_eq!(hosts_file.find_name(Ipv4Addr::new(10, 20, 0, 12)), Some("printer.lab".to_string()));
+        assert_eq!(hosts_file.find_name(Ipv4Addr::new(10, 20, 0, 99)), None);
+    }
+
+    #[test]
+    fn should_find_addresses_by_name() {
+
+        let hosts_file = HostsFile::parse(HOSTS_CONTENT);
+
+        assert_eq!(hosts_file.find_addresses("gw"), vec![Ipv4Addr::new(10, 20, 0, 1)]);
+        assert_eq!(hosts_file.find_addresses("printer.lab."), vec![Ipv4Addr::new(10, 20, 0, 12), Ipv4Addr::new(10, 20, 0, 13)]);
+        assert_eq!(hosts_file.find_addresses("ipv6-host.lab"), Vec::<Ipv4Addr>::new());
+    }
+
+}
@@ -0,0 +1,173 @@
+use std::net::Ipv4Addr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use pnet::packet::Packet;
+use pnet::packet::ip::IpNextHeaderProtocols;
+use pnet::packet::ipv4::{self, Ipv4Packet, MutableIpv4Packet};
+use pnet::packet::util;
+
+use crate::args::IcmpProbe;
+
+const IPV4_HEADER_SIZE: usize = 20;
+const IPV4_DEFAULT_TTL: u8 = 64;
+
+// ICMP message types, see RFC 792
+const ECHO_REPLY_TYPE: u8 = 0;
+const ECHO_REQUEST_TYPE: u8 = 8;
+const TIMESTAMP_REQUEST_TYPE: u8 = 13;
+const TIMESTAMP_REPLY_TYPE: u8 = 14;
+
+const ECHO_MESSAGE_SIZE: usize = 8;
+const TIMESTAMP_MESSAGE_SIZE: usize = 20;
+
+/**
+ * Identifier of the ICMP requests sent by this process, replies carry the
+ * same identifier and are matched with it.
+ */
+pub fn compute_identifier() -> u16 {
+    (std::process::id() & 0xFFFF) as u16
+}
+
+/**
+ * Size of the IPv4 packet holding an ICMP request (without Ethernet header).
+ */
+pub fn compute_request_size(probe: IcmpProbe) -> usize {
+    match probe {
+        IcmpProbe::Echo => IPV4_HEADER_SIZE + ECHO_MESSAGE_SIZE,
+        IcmpProbe::Timestamp => IPV4_HEADER_SIZE + TIMESTAMP_MESSAGE_SIZE
+    }
+}
+
+/**
+ * Build the ICMP message of a request. The timestamp request carries the
+ * originate timestamp (milliseconds since midnight UT), the receive and
+ * transmit timestamps are filled by the target.
+ */
+fn build_icmp_message(probe: IcmpProbe, identifier: u16, sequence: u16) -> Vec<u8> {
+
+    let mut message: Vec<u8> = Vec::with_capacity(TIMESTAMP_MESSAGE_SIZE);
+    let message_type = match probe {
+        IcmpProbe::Echo => ECHO_REQUEST_TYPE,
+        IcmpProbe::Timestamp => TIMESTAMP_REQUEST_TYPE
+    };
+    message.extend_from_slice(&[message_type, 0, 0, 0]);
+    message.extend_from_slice(&identifier.to_be_bytes());
+    message.extend_from_slice(&sequence.to_be_bytes());
+
+    if probe == IcmpProbe::Timestamp {
+        let day_milliseconds = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|elapsed| (elapsed.as_millis() % 86_400_000) as u32)
+            .unwrap_or(0);
+        message.extend_from_slice(&day_milliseconds.to_be_bytes());
+        message.extend_from_slice(&[0u8; 8]);
+    }
+
+    let message_checksum = util::checksum(&message, 1);
+    message[2..4].copy_from_slice(&message_checksum.to_be_bytes());
+    message
+}
+
+/**
+ * Build an IPv4 packet holding an ICMP request for the given target. The
+ * packet is then sent in an Ethernet frame with the datalink sender, which
+ * means that no IP routing (and no ARP resolution) is done by the system.
+ */
+pub fn build_icmp_request(probe: IcmpProbe, source_ip: Ipv4Addr, target_ip: Ipv4Addr, identifier: u16, sequence: u16) -> Vec<u8> {
+
+    let message = build_icmp_message(probe, identifier, sequence);
+    let mut ipv4_buffer = vec![0u8; IPV4_HEADER_SIZE + message.len()];
+
+    if let Some(mut ipv4_packet) = MutableIpv4Packet::new(&mut ipv4_buffer) {
+
+        ipv4_packet.set_version(4);
+        ipv4_packet.set_header_length((IPV4_HEADER_SIZE / 4) as u8);
+        ipv4_packet.set_total_length((IPV4_HEADER_SIZE + message.len()) as u16);
+        ipv4_packet.set_identification(sequence);
+        ipv4_packet.set_ttl(IPV4_DEFAULT_TTL);
+        ipv4_packet.set_next_level_protocol(IpNextHeaderProtocols::Icmp);
+        ipv4_packet.set_source(source_ip);
+        ipv4_packet.set_destination(target_ip);
+        ipv4_packet.set_payload(&message);
+
+        let header_checksum = ipv4::checksum(&ipv4_packet.to_immutable());
+        ipv4_packet.set_checksum(header_checksum);
+    }
+
+    ipv4_buffer
+}
+
+/**
+ * Find the sender of an ICMP reply (echo or timestamp) matching the requests
+ * of this process. Other ICMP messages (requests, errors) are ignored.
+ */
+pub fn parse_icmp_reply(ipv4_packet: &Ipv4Packet, identifier: u16) -> Option<Ipv4Addr> {
+
+    if ipv4_packet.get_next_level_protocol() != IpNextHeaderProtocols::Icmp {
+        return None;
+    }
+
+    let message = ipv4_packet.payload();
+    let message_type = *message.first()?;
+    let reply_identifier = u16::from_be_bytes([*message.get(4)?, *message.get(5)?]);
+
+    match (message_type, reply_identifier == identifier) {
+        (ECHO_REPLY_TYPE, true) | (TIMESTAMP_REPLY_TYPE, true) => Some(ipv4_packet.get_source()),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    /**
+     * Turn a request into the reply of the target: addresses are swapped and
+     * the message type is changed, like a network stack would do.
+     */
+    fn build_reply(request: &[u8], reply_type: u8) -> Vec<u8> {
+
+        let mut reply = request.to_vec();
+        reply[12..16].copy_from_slice(&request[16..20]);
+        reply[16..20].copy_from_slice(&request[12..16]);
+        reply[IPV4_HEADER_SIZE] = reply_type;
+        reply
+    }
+
+    #[test]
+    fn should_build_valid_echo_request() {
+
+        let request = build_icmp_request(IcmpProbe::Echo, Ipv4Addr::new(10, 0, 0, 2), Ipv4Addr::new(10, 0, 0, 9), 0x1234, 7);
+        let ipv4_packet = Ipv4Packet::new(&request).unwrap();
+
+        assert_eq!(request.len(), compute_request_size(IcmpProbe::Echo));
+        assert_eq!(ipv4_packet.get_destination(), Ipv4Addr::new(10, 0, 0, 9));
+        assert_eq!(ipv4_packet.get_checksum(), ipv4::checksum(&ipv4_packet));
+        assert_eq!(&ipv4_packet.payload()[..2], &[ECHO_REQUEST_TYPE, 0]);
+        assert_eq!(util::checksum(ipv4_packet.payload(), 1), u16::from_be_bytes([ipv4_packet.payload()[2], ipv4_packet.payload()[3]]));
+    }
+
+    #[test]
+    fn should_match_replies_with_identifier() {
+
+        let echo_request = build_icmp_request(IcmpProbe::Echo, Ipv4Addr::new(10, 0, 0, 2), Ipv4Addr::new(10, 0, 0, 9), 0x1234, 1);
+        let timestamp_request = build_icmp_request(IcmpProbe::Timestamp, Ipv4Addr::new(10, 0, 0, 2), Ipv4Addr::new(10, 0, 0, 12), 0x1234, 2);
+
+        let echo_reply = build_reply(&echo_request, ECHO_REPLY_TYPE);
+        let timestamp_reply = build_reply(&timestamp_request, TIMESTAMP_REPLY_TYPE);
+
+        assert_eq!(timestamp_request.len(), compute_request_size(IcmpProbe::Timestamp));
+        assert_eq!(parse_icmp_reply(&Ipv4Packet::new(&echo_reply).unwrap(), 0x1234), Some(Ipv4Addr::new(10, 0, 0, 9)));
+        assert_eq!(parse_icmp_reply(&Ipv4Packet::new(&timestamp_reply).unwrap(), 0x1234), Some(Ipv4Addr::new(10, 0, 0, 12)));
+        assert_eq!(parse_icmp_reply(&Ipv4Packet::new(&echo_reply).unwrap(), 0x4321), None);
+    }
+
+    #[test]
+    fn should_ignore_own_requests() {
+
+        let echo_request = build_icmp_request(IcmpProbe::Echo, Ipv4Addr::new(10, 0, 0, 2), Ipv4Addr::new(10, 0, 0, 9), 0x1234, 1);
+
+        assert_eq!(parse_icmp_reply(&Ipv4Packet::new(&echo_request).unwrap(), 0x1234), None);
+    }
+
+}
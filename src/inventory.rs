@@ -0,0 +1,193 @@
+use std::collections::{HashMap, HashSet};
+use std::net::Ipv4Addr;
+
+use ipnetwork::{IpNetwork, Ipv4Network};
+use pnet_datalink::MacAddr;
+
+use crate::network::TargetDetails;
+
+/**
+ * A known inventory of static devices, read from a CSV file with one 'IPv4,
+ * MAC' pair per line. An optional header line and comments (starting with
+ * '#') are allowed. Each expected MAC address receives unicast ARP requests
+ * instead of broadcasted requests.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct Inventory {
+    expected_macs: HashMap<Ipv4Addr, MacAddr>
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InventoryStatus {
+    Present,
+    Missing,
+    Mismatch
+}
+
+impl InventoryStatus {
+
+    pub fn name(&self) -> &str {
+        match self {
+            InventoryStatus::Present => "present",
+            InventoryStatus::Missing => "missing",
+            InventoryStatus::Mismatch => "mismatch"
+        }
+    }
+}
+
+/**
+ * The verification result of a single inventory entry. The found MAC address
+ * is the sender of the ARP reply, if any.
+ */
+#[derive(Debug, PartialEq)]
+pub struct InventoryCheck {
+    pub ipv4: Ipv4Addr,
+    pub expected_mac: MacAddr,
+    pub found_mac: Option<MacAddr>,
+    pub status: InventoryStatus
+}
+
+impl Inventory {
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+
+        let mut inventory = Inventory::default();
+
+        for (line_index, line) in content.lines().enumerate() {
+
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+            let (ipv4_text, mac_text) = match fields.as_slice() {
+                [ipv4_text, mac_text, ..] => (*ipv4_text, *mac_text),
+                _ => return Err(format!("expected 'IPv4,MAC' on line {}", line_index + 1))
+            };
+
+            let ipv4 = match ipv4_text.parse::<Ipv4Addr>() {
+                Ok(ipv4) => ipv4,
+                // The first line may be a header ('ipv4,mac')
+                Err(_) if line_index == 0 => continue,
+                Err(_) => return Err(format!("invalid IPv4 '{}' on line {}", ipv4_text, line_index + 1))
+            };
+            let mac = mac_text.parse::<MacAddr>().map_err(|_| {
+                format!("invalid MAC '{}' on line {}", mac_text, line_index + 1)
+            })?;
+
+            if inventory.expected_macs.insert(ipv4, mac).is_some() {
+                return Err(format!("duplicate IPv4 {} on line {}", ipv4, line_index + 1));
+            }
+        }
+
+        match inventory.expected_macs.is_empty() {
+            true => Err("no inventory entry found".to_string()),
+            false => Ok(inventory)
+        }
+    }
+
+    pub fn find_mac(&self, ipv4: Ipv4Addr) -> Option<MacAddr> {
+        self.expected_macs.get(&ipv4).copied()
+    }
+
+    /**
+     * The scan targets of the inventory, a single-host network per entry.
+     */
+    pub fn networks(&self) -> Vec<IpNetwork> {
+
+        let mut ipv4_list: Vec<Ipv4Addr> = self.expected_macs.keys().copied().collect();
+        ipv4_list.sort();
+
+        ipv4_list.into_iter()
+            .filter_map(|ipv4| Ipv4Network::new(ipv4, 32).ok())
+            .map(IpNetwork::V4)
+            .collect()
+    }
+
+    /**
+     * Compare the scan results with the inventory. An entry is present if the
+     * expected MAC address answered for its IPv4 address, and mismatched if
+     * another MAC address answered.
+     */
+    pub fn verify(&self, target_details: &[TargetDetails]) -> Vec<InventoryCheck> {
+
+        let found_macs: HashMap<Ipv4Addr, MacAddr> = target_details.iter()
+            .map(|detail| (detail.ipv4, detail.mac))
+            .collect();
+
+        let mut checks: Vec<InventoryCheck> = self.expected_macs.iter().map(|(ipv4, expected_mac)| {
+
+            let found_mac = found_macs.get(ipv4).copied();
+            let status = match found_mac {
+                Some(found_mac) if found_mac == *expected_mac => InventoryStatus::Present,
+                Some(_) => InventoryStatus::Mismatch,
+                None => InventoryStatus::Missing
+            };
+            InventoryCheck {
+                ipv4: *ipv4,
+                expected_mac: *expected_mac,
+                found_mac,
+                status
+            }
+        }).collect();
+
+        checks.sort_by_key(|check| check.ipv4);
+        checks
+    }
+
+    /**
+     * Count the distinct MAC addresses of the inventory, a device may own
+     * several IPv4 addresses.
+     */
+    pub fn device_count(&self) -> usize {
+        self.expected_macs.values().collect::<HashSet<&MacAddr>>().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const INVENTORY_CONTENT: &str = "ipv4,mac
+# Rack A
+10.20.0.10, 00:11:22:33:44:10
+10.20.0.11, 00:11:22:33:44:11   # printer
+10.20.0.12, 00:11:22:33:44:12
+";
+
+    #[test]
+    fn should_parse_inventory() {
+
+        let inventory = Inventory::parse(INVENTORY_CONTENT).unwrap();
+
+        assert_eq!(inventory.find_mac(Ipv4Addr::new(10, 20, 0, 11)), Some(MacAddr::new(0, 0x11, 0x22, 0x33, 0x44, 0x11)));
+        assert_eq!(inventory.networks().first(), Some(&"10.20.0.10/32".parse::<IpNetwork>().unwrap()));
+        assert_eq!(inventory.device_count(), 3);
+    }
+
+    #[test]
+    fn should_reject_invalid_inventory() {
+
+        assert_eq!(Inventory::parse("10.0.0.1,00:11:22:33:44:55\nbroken,00:11:22:33:44:55").err(), Some("invalid IPv4 'broken' on line 2".to_string()));
+        assert_eq!(Inventory::parse("10.0.0.1,not-a-mac").err(), Some("invalid MAC 'not-a-mac' on line 1".to_string()));
+        assert_eq!(Inventory::parse("10.0.0.1,00:11:22:33:44:55\n10.0.0.1,00:11:22:33:44:56").err(), Some("duplicate IPv4 10.0.0.1 on line 2".to_string()));
+        assert_eq!(Inventory::parse("ipv4,mac\n").err(), Some("no inventory entry found".to_string()));
+    }
+
+    #[test]
+    fn should_verify_scan_results() {
+
+        let inventory = Inventory::parse(INVENTORY_CONTENT).unwrap();
+        let target_details = vec![
+            TargetDetails::build(Ipv4Addr::new(10, 20, 0, 10), MacAddr::new(0, 0x11, 0x22, 0x33, 0x44, 0x10)),
+            TargetDetails::build(Ipv4Addr::new(10, 20, 0, 12), MacAddr::new(0, 0x11, 0x22, 0x33, 0x44, 0x99))
+        ];
+
+        let statuses: Vec<InventoryStatus> = inventory.verify(&target_details).iter().map(|check| check.status).collect();
+
+        assert_eq!(statuses, vec![InventoryStatus::Present, InventoryStatus::Missing, InventoryStatus::Mismatch]);
+    }
+
+}
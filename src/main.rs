@@ -1,20 +1,58 @@
 mod args;
+mod baseline;
+mod checkpoint;
+mod database;
+mod dhcp;
+mod deferred;
+mod dns;
+mod export;
+mod filter;
+mod fingerprint;
+mod hostname;
+mod hosts;
+mod icmp;
+mod inventory;
+mod metrics;
+mod netbios;
 mod network;
+mod notify;
+mod permutation;
+mod ports;
+mod privilege;
+mod progress;
+mod rate;
+mod report;
+#[cfg(target_os = "linux")]
+mod seccomp;
+mod stream;
 mod time;
 mod utils;
 mod vendor;
 
+use std::fs::File;
 use std::net::IpAddr;
 use std::process;
 use std::thread;
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
 use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::args::{ScanOptions, OutputFormat};
-use crate::network::NetworkIterator;
+use crate::args::{DatabaseQuery, ScanOptions};
+use crate::checkpoint::CheckpointWriter;
+use crate::database::ScanDatabase;
+use crate::export::{OutputFiles, ScanContext};
+use crate::fingerprint::FingerprintDatabase;
+use crate::metrics::{MetricsRegistry, ScanMetrics};
+use crate::network::{CaptureChannels, NetworkIterator};
+use crate::notify::Notifier;
+use crate::progress::ProgressBar;
+use crate::rate::RateLimiter;
 use crate::vendor::Vendor;
 
+// Exit code used when scan results violate the baseline, errors exit with 1
+const BASELINE_VIOLATION_EXIT_CODE: i32 = 2;
+
 fn main() {
     
     let matches = args::build_args().get_matches();
@@ -36,24 +74,67 @@ fn main() {
     // --------------------------------------------
     // Ensure all requirements are met to perform an ARP scan on the local
     // network for the given interface. ARP scans require an active interface
-    // with an IPv4 address and root permissions (for crafting ARP packets).
+    // with an IPv4 address and raw socket permissions (for crafting ARP
+    // packets), either as root or with the CAP_NET_RAW capability.
 
-    let scan_options = ScanOptions::new(&matches);
+    // The scan options are shared once the files are loaded, since the
+    // options of a resumed scan are restored from the checkpoint file.
+    let mut scan_options = ScanOptions::new(&matches);
 
     if scan_options.request_protocol_print() {
         utils::print_ascii_packet();
         process::exit(0);
     }
     
-    if !cfg!(windows) && !utils::is_root_user() {
-        eprintln!("Should run this binary as root or use --help for options");
-        process::exit(1);
+    // Database lookups only read the hosts recorded by previous scans, no
+    // network scan is performed (and no root permissions are required).
+    if let (Some(database_file), Some(database_query)) = (&scan_options.database_file, &scan_options.database_query) {
+
+        let database = ScanDatabase::open(database_file).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(1);
+        });
+
+        let query_result = match database_query {
+            DatabaseQuery::History { since, until } => database.list_hosts(*since, *until).map(|sightings| {
+                utils::show_host_history(&sightings, *since, *until);
+            }),
+            DatabaseQuery::MacAddress(mac) => database.find_mac_period(&mac.to_string()).and_then(|period| {
+                let locations = database.find_mac_locations(&mac.to_string())?;
+                utils::show_mac_locations(*mac, period, &locations);
+                Ok(())
+            })
+        };
+        if let Err(err) = query_result {
+            eprintln!("Could not query database {} ({})", database_file, err);
+            process::exit(1);
+        }
+        process::exit(0);
     }
 
-    let (selected_interface, ip_networks) = network::compute_network_configuration(&interfaces, &scan_options);
+    let selected_interface = network::select_interface(&interfaces, &scan_options);
 
-    if scan_options.is_plain_output() {
-        utils::display_prescan_details(&ip_networks, selected_interface, scan_options.clone());
+    // The dry-run mode only prints the scan plan, no datalink channel will be
+    // opened (and no root permissions are therefore required).
+    if scan_options.dry_run {
+        scan_options.load_files().and_then(|_| scan_options.check_resumed_interface(&selected_interface.name)).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(1);
+        });
+        let target_networks = scan_options.target_networks();
+        let ip_networks = network::compute_target_networks(selected_interface, target_networks.as_deref());
+        let network_size = utils::compute_network_size(&ip_networks);
+        let estimations = network::compute_scan_estimation(network_size, &scan_options).unwrap_or_else(|err| {
+            eprintln!("Could not estimate the scan ({})", err);
+            process::exit(1);
+        });
+        utils::display_scan_plan(&ip_networks, selected_interface, &scan_options, network_size, &estimations);
+        process::exit(0);
+    }
+
+    if let Err(err) = privilege::check_scan_privileges() {
+        eprintln!("{}", err);
+        process::exit(1);
     }
 
     // Start ARP scan operation
@@ -83,25 +164,106 @@ fn main() {
     // ARP packet sending) and the response thread (which receives and stores
     // all ARP responses).
     let timed_out = Arc::new(AtomicBool::new(false));
-    let cloned_timed_out = Arc::clone(&timed_out);
 
+    // Like the vendor database, the fingerprint database is opened before
+    // dropping privileges but only parsed afterwards.
     let mut vendor_list = Vendor::new(&scan_options.oui_file);
+    let fingerprint_file = File::open(&scan_options.fingerprint_file).ok();
 
-    let cloned_options = Arc::clone(&scan_options);
-    let arp_responses = thread::spawn(move || network::receive_arp_responses(&mut rx, cloned_options, cloned_timed_out, &mut vendor_list));
+    let mut scan_database = scan_options.database_file.as_ref().map(|database_path| {
+        ScanDatabase::open(database_path).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(1);
+        })
+    });
 
-    let network_size = utils::compute_network_size(&ip_networks);
+    // ICMP requests are sent through a raw IPv4 socket, which also needs to
+    // be opened before dropping privileges.
+    let mut icmp_tx = scan_options.icmp_probe.map(|_| {
+        network::open_icmp_sender().unwrap_or_else(|err| {
+            eprintln!("Could not open the ICMP socket ({})", err);
+            process::exit(1);
+        })
+    });
+
+    // Output files are opened before dropping privileges, the unprivileged
+    // user may not be allowed to create files in the requested directories.
+    let mut output_files = OutputFiles::open(&scan_options).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(1);
+    });
+
+    // The metrics listener is opened before dropping privileges, since the
+    // listening port may be a privileged port.
+    let metrics_listener = scan_options.metrics_listen.map(|listen_address| {
+        metrics::bind_listener(listen_address).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(1);
+        })
+    });
 
-    let estimations = network::compute_scan_estimation(network_size, &scan_options);
-    let interval_ms = estimations.interval_ms;
+    // Drop privileges
+    // ---------------
+    // Once the datalink channel and the files are opened, root privileges and
+    // capabilities are not needed anymore. Vendor parsing, hostname lookups and
+    // exports are therefore run as an unprivileged user. This is done before
+    // spawning any thread, since capabilities are tracked per thread on Linux.
 
-    if scan_options.is_plain_output() {
+    if scan_options.drop_privileges {
 
-        let formatted_ms = time::format_milliseconds(estimations.duration_ms);
-        println!("Estimated scan time {} ({} bytes, {} bytes/s)", formatted_ms, estimations.request_size, estimations.bandwidth);
-        println!("Sending {} ARP requests (waiting at least {}ms, {}ms request interval)", network_size, scan_options.timeout_ms, interval_ms);
+        privilege::drop_privileges(&scan_options.unprivileged_user, scan_options.unprivileged_group.as_deref()).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            eprintln!("Use --user to select another account or --keep-privileges to skip this step");
+            process::exit(1);
+        });
+
+        #[cfg(target_os = "linux")]
+        if scan_options.seccomp_filter {
+            seccomp::install_filter().unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                process::exit(1);
+            });
+        }
     }
 
+    scan_options.load_files().and_then(|_| scan_options.check_resumed_interface(&selected_interface.name)).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(1);
+    });
+    let scan_options = Arc::new(scan_options);
+    let fingerprint_db = FingerprintDatabase::new(fingerprint_file, &scan_options.fingerprint_file);
+
+    // Scan targets are computed once the files are loaded, since inventory
+    // targets are read from the inventory file.
+    let target_networks = scan_options.target_networks();
+    let ip_networks = network::compute_target_networks(selected_interface, target_networks.as_deref());
+    let network_size = utils::compute_network_size(&ip_networks);
+    let estimations = network::compute_scan_estimation(network_size, &scan_options).unwrap_or_else(|err| {
+        eprintln!("Could not estimate the scan ({})", err);
+        process::exit(1);
+    });
+
+    if scan_options.is_verbose() {
+        utils::display_prescan_details(&ip_networks, selected_interface, scan_options.clone());
+    }
+
+    // When resuming a scan, the hosts found before the interruption are given
+    // to the response thread and will be merged in the final results.
+    let mut known_targets = match scan_options.resume_checkpoint() {
+        Some(checkpoint) => checkpoint.discovered_targets().unwrap_or_else(|err| {
+            eprintln!("Could not resume the scan");
+            eprintln!("{}", err);
+            process::exit(1);
+        }),
+        None => vec![]
+    };
+
+    let mut checkpoint_writer = scan_options.checkpoint_file.as_ref().map(|checkpoint_path| {
+        let mut writer = CheckpointWriter::new(checkpoint_path, &selected_interface.name, &ip_networks, &scan_options);
+        writer.record_targets(&known_targets);
+        writer
+    });
+
     let has_reached_timeout = Arc::new(AtomicBool::new(false));
     let cloned_reached_timeout = Arc::clone(&has_reached_timeout);
 
@@ -115,49 +277,261 @@ fn main() {
 
     let source_ip = network::find_source_ip(selected_interface, scan_options.source_ipv4);
 
-    // The retry count does right now use a 'brute-force' strategy without
-    // synchronization process with the already known hosts.
-    for _ in 0..scan_options.retry_count {
+    // A resumed scan starts at the retry round and target position saved in
+    // the checkpoint file, previous rounds are not sent again.
+    let (start_round, start_position) = match scan_options.resume_checkpoint() {
+        Some(checkpoint) => (checkpoint.retry_round, checkpoint.position),
+        None => (0, 0)
+    };
 
-        if has_reached_timeout.load(Ordering::Relaxed) {
-            break;
+    // Checkpoints are renamed over the previous checkpoint on each save, the
+    // checkpoint directory must be writable by the (unprivileged) scan user.
+    if let Some(writer) = checkpoint_writer.as_mut() {
+        writer.try_save(start_round, start_position).unwrap_or_else(|err| {
+            eprintln!("Could not write checkpoint file ({})", err);
+            if scan_options.drop_privileges {
+                eprintln!("The checkpoint directory must be writable by the user {}", scan_options.unprivileged_user);
+            }
+            process::exit(1);
+        });
+    }
+
+    // Periodic scans
+    // --------------
+    // A single scan is performed by default. In metrics mode, the scan is
+    // repeated after each scan interval and the metrics of the last completed
+    // scan are served over HTTP until a halt signal is received.
+
+    let metrics_registry = metrics_listener.map(|listener| {
+        let metrics_registry = Arc::new(Mutex::new(MetricsRegistry::default()));
+        metrics::serve_metrics(listener, Arc::clone(&metrics_registry));
+        metrics_registry
+    });
+
+    let mut notifier = Notifier::new(&scan_options);
+
+    loop {
+
+        // The progress bar is created before the response thread, discovered
+        // hosts are then sent to the main thread for the progress display.
+        let mut progress_bar = ProgressBar::new(&estimations, &scan_options);
+
+        let (discovery_tx, discovery_rx) = mpsc::channel();
+        let discovery_tx = match checkpoint_writer.is_some() || progress_bar.is_some() {
+            true => Some(discovery_tx),
+            false => None
+        };
+
+        // Sending times of ARP requests are given to the response thread, which
+        // measures the reply latency of each host.
+        let (request_tx, request_rx) = mpsc::channel();
+
+        // The datalink receiver and the vendor list are given back once the
+        // capture is over, since periodic scans use them again.
+        let receive_options = Arc::clone(&scan_options);
+        let cloned_timed_out = Arc::clone(&timed_out);
+        let scan_targets = std::mem::take(&mut known_targets);
+        let capture_channels = CaptureChannels { request_rx, discovery_tx };
+        let local_mac = selected_interface.mac;
+        let arp_responses = thread::spawn(move || {
+            let scan_results = network::receive_arp_responses(&mut rx, cloned_timed_out, &mut vendor_list, scan_targets, capture_channels, local_mac, receive_options);
+            (scan_results, rx, vendor_list)
+        });
+
+        if scan_options.is_verbose() {
+
+            let formatted_ms = time::format_milliseconds(estimations.duration_ms);
+            let rate_text = utils::format_packet_rate(estimations.packet_rate);
+            eprintln!("Estimated scan time {} ({} bytes, {} bytes/s)", formatted_ms, estimations.request_size, estimations.bandwidth);
+            let request_kind = utils::describe_request_kind(&scan_options);
+            eprintln!("Sending {} {} (waiting at least {}ms, {}, burst of {})", estimations.request_count, request_kind, scan_options.timeout_ms, rate_text, scan_options.burst_size);
         }
 
-        let ip_addresses = NetworkIterator::new(&ip_networks, scan_options.randomize_targets);
+        let mut rate_limiter = RateLimiter::new(estimations.packet_rate, scan_options.burst_size);
 
-        for ip_address in ip_addresses {
+        let mut retry_round = start_round;
+        let mut target_position: u64 = 0;
+
+        // ICMP sequence numbers only tell requests apart, the counter wraps
+        // around on purpose once 65535 requests have been sent.
+        let mut icmp_sequence: u16 = 0;
+
+        // The retry count does right now use a 'brute-force' strategy without
+        // synchronization process with the already known hosts.
+        while retry_round < scan_options.retry_count {
 
             if has_reached_timeout.load(Ordering::Relaxed) {
                 break;
             }
 
-            if let IpAddr::V4(ipv4_address) = ip_address {
-                network::send_arp_request(&mut tx, selected_interface, source_ip, ipv4_address, Arc::clone(&scan_options));
-                thread::sleep(Duration::from_millis(interval_ms));
+            let mut ip_addresses = NetworkIterator::new(&ip_networks, scan_options.target_seed());
+            if retry_round == start_round {
+                ip_addresses.skip_to_position(start_position);
+            }
+
+            // The halt signal is checked before taking the next target, this way
+            // the iterator position always matches the targets already sent.
+            while !has_reached_timeout.load(Ordering::Relaxed) {
+
+                let ip_address = match ip_addresses.next() {
+                    Some(ip_address) => ip_address,
+                    None => break
+                };
+
+                if let IpAddr::V4(ipv4_address) = ip_address {
+                    rate_limiter.acquire();
+                    network::send_arp_request(&mut tx, selected_interface, source_ip, ipv4_address, Arc::clone(&scan_options));
+                    let _ = request_tx.send((ipv4_address, Instant::now()));
+
+                    if let Some(icmp_tx) = icmp_tx.as_mut() {
+                        rate_limiter.acquire();
+                        icmp_sequence = icmp_sequence.wrapping_add(1);
+                        network::send_icmp_request(&mut tx, icmp_tx, selected_interface, source_ip, ipv4_address, icmp_sequence, Arc::clone(&scan_options));
+                    }
+                }
+
+                for (ipv4, mac) in discovery_rx.try_iter() {
+                    if let Some(writer) = checkpoint_writer.as_mut() {
+                        writer.record_host(ipv4, mac);
+                    }
+                    if let Some(progress_bar) = progress_bar.as_mut() {
+                        progress_bar.record_host(ipv4);
+                    }
+                }
+                if let Some(writer) = checkpoint_writer.as_mut() {
+                    writer.save_periodically(retry_round, ip_addresses.position());
+                }
+                if let Some(progress_bar) = progress_bar.as_mut() {
+                    progress_bar.update(&rate_limiter, retry_round);
+                }
+            }
+            target_position = ip_addresses.position();
+
+            if !has_reached_timeout.load(Ordering::Relaxed) {
+                retry_round += 1;
+                target_position = 0;
             }
         }
-    }
 
-    // Once the ARP packets are sent, the main thread will sleep for T seconds
-    // (where T is the timeout option). After the sleep phase, the response
-    // thread will receive a stop request through the 'timed_out' mutex.
-    let mut sleep_ms_mount: u64 = 0;
-    while !has_reached_timeout.load(Ordering::Relaxed) && sleep_ms_mount < scan_options.timeout_ms {
-        
-        thread::sleep(Duration::from_millis(100));
-        sleep_ms_mount += 100;
-    }
-    timed_out.store(true, Ordering::Relaxed);
+        // Once the ARP packets are sent, the main thread will sleep for T seconds
+        // (where T is the timeout option). After the sleep phase, the response
+        // thread will receive a stop request through the 'timed_out' mutex.
+        if let Some(progress_bar) = progress_bar.as_mut() {
+            progress_bar.start_waiting();
+        }
+        let mut sleep_ms_mount: u64 = 0;
+        while !has_reached_timeout.load(Ordering::Relaxed) && sleep_ms_mount < scan_options.timeout_ms {
 
-    let (response_summary, target_details) = arp_responses.join().unwrap_or_else(|error| {
-        eprintln!("Failed to close receive thread ({:?})", error);
-        process::exit(1);
-    });
+            thread::sleep(Duration::from_millis(100));
+            sleep_ms_mount += 100;
+
+            if let Some(progress_bar) = progress_bar.as_mut() {
+                discovery_rx.try_iter().for_each(|(ipv4, _)| progress_bar.record_host(ipv4));
+                progress_bar.update(&rate_limiter, retry_round);
+            }
+        }
+        timed_out.store(true, Ordering::Relaxed);
+
+        if let Some(progress_bar) = &progress_bar {
+            progress_bar.finish();
+        }
+
+        let ((mut response_summary, mut target_details), scan_rx, scan_vendor_list) = arp_responses.join().unwrap_or_else(|error| {
+            eprintln!("Failed to close receive thread ({:?})", error);
+            process::exit(1);
+        });
+        rx = scan_rx;
+        vendor_list = scan_vendor_list;
+
+        // The response thread only knows about received packets, the request
+        // details are measured by the rate limiter in the main thread.
+        response_summary.target_count = network_size;
+        response_summary.request_count = rate_limiter.request_count();
+        response_summary.request_rate = rate_limiter.achieved_rate();
+
+        // Device classes only rely on data collected during the scan, hosts are
+        // classified before hostnames are resolved.
+        fingerprint::classify_targets(&mut target_details, &fingerprint_db, &scan_options);
+
+        // Hostnames are resolved once the capture is over, with a bounded amount
+        // of concurrent lookups and a global time budget.
+        let local_ipv4 = network::find_source_ip(selected_interface, None);
+        hostname::resolve_target_hostnames(&mut target_details, &scan_options, local_ipv4);
+
+        // Requested TCP ports are probed on discovered hosts, following the same
+        // packet rate as the ARP requests.
+        ports::probe_target_ports(&mut target_details, &scan_options, estimations.packet_rate);
+
+        if let Some(baseline) = scan_options.baseline() {
+            baseline::check_targets(&mut target_details, baseline);
+        }
+
+        // The last checkpoint is saved once all responses were received, the
+        // checkpoint will then contain all hosts found in the scan.
+        if let Some(writer) = checkpoint_writer.as_mut() {
+            writer.record_targets(&target_details);
+            writer.save(retry_round, target_position);
+        }
+
+        // The inventory verification compares the expected MAC addresses with
+        // the ARP replies received for each inventory entry.
+        let inventory_checks = scan_options.inventory().map(|inventory| inventory.verify(&target_details));
+        let inventory_checks = inventory_checks.as_deref();
+
+        // Baseline violations reaching the failure severity end the scan with a
+        // dedicated exit code (the results are still displayed or exported).
+        let has_failing_violations = scan_options.baseline().is_some_and(|baseline| {
+            target_details.iter().flat_map(|detail| detail.violations.iter()).any(|violation| baseline.is_failing(violation))
+        });
+
+        if let Some(database) = scan_database.as_mut() {
+            if let Err(err) = database.record_scan(&selected_interface.name, &ip_networks, &response_summary, &target_details, &scan_options) {
+                eprintln!("[warn] Could not record the scan in the database ({})", err);
+            }
+        }
+
+        if let Some(metrics_registry) = &metrics_registry {
+            let scan_metrics = ScanMetrics::new(&selected_interface.name, &ip_networks, &response_summary, &target_details);
+            match metrics_registry.lock() {
+                Ok(mut registry) => registry.record_scan(scan_metrics),
+                Err(poisoned) => poisoned.into_inner().record_scan(scan_metrics)
+            }
+        }
 
-    match &scan_options.output {
-        OutputFormat::Plain => utils::display_scan_results(response_summary, target_details, &scan_options),
-        OutputFormat::Json => println!("{}", utils::export_to_json(response_summary, target_details)),
-        OutputFormat::Yaml => println!("{}", utils::export_to_yaml(response_summary, target_details)),
-        OutputFormat::Csv => print!("{}", utils::export_to_csv(response_summary, target_details))
+        let scan_context = ScanContext {
+            interface_name: &selected_interface.name,
+            interface_mac: selected_interface.mac,
+            source_ipv4: source_ip,
+            ip_networks: &ip_networks
+        };
+        let has_written_outputs = export::write_outputs(&response_summary, &mut target_details, inventory_checks, &scan_context, &scan_options, &mut output_files);
+
+        // Notifications are sent once the results are written, slow or
+        // unreachable sinks do not delay the outputs.
+        if let Some(notifier) = notifier.as_mut() {
+            notifier.notify_scan(&response_summary, &target_details, inventory_checks, &scan_context, &scan_options);
+        }
+
+        if metrics_registry.is_none() {
+            if has_failing_violations {
+                process::exit(BASELINE_VIOLATION_EXIT_CODE);
+            }
+            if !has_written_outputs {
+                process::exit(1);
+            }
+            break;
+        }
+
+        // The next periodic scan starts after the scan interval, unless a
+        // halt signal is received while waiting.
+        let mut interval_ms_mount: u64 = 0;
+        while !has_reached_timeout.load(Ordering::Relaxed) && interval_ms_mount < scan_options.scan_interval_ms {
+            thread::sleep(Duration::from_millis(100));
+            interval_ms_mount += 100;
+        }
+        if has_reached_timeout.load(Ordering::Relaxed) {
+            break;
+        }
+        timed_out.store(false, Ordering::Relaxed);
     }
 }
@@ -0,0 +1,326 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ipnetwork::IpNetwork;
+
+use crate::network::{ResponseSummary, TargetDetails};
+
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REQUEST_SIZE: u64 = 8192;
+
+/**
+ * Details of a single scan exposed as Prometheus metrics: hosts found per
+ * network and per vendor, and the response summary of the scan.
+ */
+pub struct ScanMetrics {
+    interface_name: String,
+    network_hosts: Vec<(String, usize)>,
+    vendor_hosts: BTreeMap<String, usize>,
+    timestamp: u64,
+    duration_ms: u128,
+    packet_count: usize,
+    arp_count: usize,
+    conflict_count: usize
+}
+
+impl ScanMetrics {
+
+    pub fn new(interface_name: &str, ip_networks: &[&IpNetwork], response_summary: &ResponseSummary, target_details: &[TargetDetails]) -> Self {
+
+        let network_hosts = ip_networks.iter().map(|ip_network| {
+            let host_count = target_details.iter().filter(|detail| ip_network.contains(detail.ipv4.into())).count();
+            (ip_network.to_string(), host_count)
+        }).collect();
+
+        let mut vendor_hosts: BTreeMap<String, usize> = BTreeMap::new();
+        for detail in target_details {
+            let vendor = detail.vendor.clone().unwrap_or_else(|| "unknown".to_string());
+            *vendor_hosts.entry(vendor).or_insert(0) += 1;
+        }
+
+        ScanMetrics {
+            interface_name: interface_name.to_string(),
+            network_hosts,
+            vendor_hosts,
+            timestamp: response_summary.start_timestamp,
+            duration_ms: response_summary.duration_ms,
+            packet_count: response_summary.packet_count,
+            arp_count: response_summary.arp_count,
+            conflict_count: response_summary.conflict_count
+        }
+    }
+}
+
+/**
+ * Metrics shared between the scan loop and the HTTP server, only the last
+ * completed scan is exposed.
+ */
+#[derive(Default)]
+pub struct MetricsRegistry {
+    last_scan: Option<ScanMetrics>,
+    scan_count: u64
+}
+
+impl MetricsRegistry {
+
+    pub fn record_scan(&mut self, scan_metrics: ScanMetrics) {
+        self.last_scan = Some(scan_metrics);
+        self.scan_count += 1;
+    }
+}
+
+/**
+ * Escape a Prometheus label value (backslashes, quotes and line feeds).
+ */
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn write_metric_header(metrics_text: &mut String, name: &str, metric_type: &str, help: &str) {
+    metrics_text.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, metric_type));
+}
+
+/**
+ * Render the registry with the Prometheus text exposition format. Metrics of
+ * the last scan are only written once a first scan is completed.
+ */
+pub fn render_metrics(registry: &MetricsRegistry) -> String {
+
+    let mut metrics_text = String::new();
+
+    write_metric_header(&mut metrics_text, "arp_scan_scans_total", "counter", "Number of completed scans");
+    metrics_text.push_str(&format!("arp_scan_scans_total {}\n", registry.scan_count));
+
+    let scan_metrics = match &registry.last_scan {
+        Some(scan_metrics) => scan_metrics,
+        None => return metrics_text
+    };
+    let interface = escape_label(&scan_metrics.interface_name);
+
+    write_metric_header(&mut metrics_text, "arp_scan_hosts", "gauge", "Hosts found by the last scan, per scanned network");
+    for (network, host_count) in &scan_metrics.network_hosts {
+        metrics_text.push_str(&format!("arp_scan_hosts{{interface=\"{}\",network=\"{}\"}} {}\n", interface, escape_label(network), host_count));
+    }
+
+    write_metric_header(&mut metrics_text, "arp_scan_vendor_hosts", "gauge", "Hosts found by the last scan, per MAC vendor");
+    for (vendor, host_count) in &scan_metrics.vendor_hosts {
+        metrics_text.push_str(&format!("arp_scan_vendor_hosts{{interface=\"{}\",vendor=\"{}\"}} {}\n", interface, escape_label(vendor), host_count));
+    }
+
+    let scan_gauges = [
+        ("arp_scan_duration_seconds", "Duration of the last scan capture", format!("{:.3}", (scan_metrics.duration_ms as f64) / 1000.0)),
+        ("arp_scan_packets_received", "Packets received during the last scan", scan_metrics.packet_count.to_string()),
+        ("arp_scan_arp_packets_received", "ARP packets received during the last scan", scan_metrics.arp_count.to_string()),
+        ("arp_scan_ip_conflicts", "IPv4 addresses announced by several MAC addresses in the last scan", scan_metrics.conflict_count.to_string()),
+        ("arp_scan_last_scan_timestamp_seconds", "Start time of the last scan (Unix timestamp)", scan_metrics.timestamp.to_string())
+    ];
+    for (name, help, value) in scan_gauges {
+        write_metric_header(&mut metrics_text, name, "gauge", help);
+        metrics_text.push_str(&format!("{}{{interface=\"{}\"}} {}\n", name, interface, value));
+    }
+
+    metrics_text
+}
+
+/**
+ * Client stream with an overall deadline: the read timeout is reduced before
+ * each read, a client sending its request byte by byte can not hold the
+ * metrics listener longer than the client timeout.
+ */
+struct DeadlineReader<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant
+}
+
+impl Read for DeadlineReader<'_> {
+
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "request deadline exceeded"));
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        let mut stream = self.stream;
+        stream.read(buffer)
+    }
+}
+
+/**
+ * Read the request line and skip the request headers. At most 8 KiB are read
+ * for the whole request head, None is returned for larger (or incomplete)
+ * requests.
+ */
+fn read_request_line(input: impl Read) -> io::Result<Option<String>> {
+
+    let mut reader = BufReader::new(input.take(MAX_REQUEST_SIZE));
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line)? == 0 || !request_line.ends_with('\n') {
+        return Ok(None);
+    }
+
+    let mut header_line = String::new();
+    loop {
+        header_line.clear();
+        if reader.read_line(&mut header_line)? == 0 || !header_line.ends_with('\n') {
+            return Ok(None);
+        }
+        if header_line.trim_end().is_empty() {
+            return Ok(Some(request_line));
+        }
+    }
+}
+
+/**
+ * Answer a single HTTP request, only 'GET /metrics' is supported. The request
+ * headers are read but ignored, connections are closed after each response.
+ */
+fn handle_client(stream: TcpStream, registry: &Mutex<MetricsRegistry>) -> io::Result<()> {
+
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+
+    let request_line = read_request_line(DeadlineReader {
+        stream: &stream,
+        deadline: Instant::now() + CLIENT_TIMEOUT
+    })?;
+
+    let request_line = match request_line {
+        Some(request_line) => request_line,
+        None => {
+            let mut writer = &stream;
+            write!(writer, "HTTP/1.1 431 Request Header Fields Too Large\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")?;
+            return writer.flush();
+        }
+    };
+
+    let mut request_parts = request_line.split_whitespace();
+    let (status, content_type, body) = match (request_parts.next(), request_parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let metrics_text = match registry.lock() {
+                Ok(registry) => render_metrics(&registry),
+                Err(poisoned) => render_metrics(&poisoned.into_inner())
+            };
+            ("200 OK", "text/plain; version=0.0.4; charset=utf-8", metrics_text)
+        },
+        (Some("GET"), Some(_)) => ("404 Not Found", "text/plain; charset=utf-8", "Metrics are served on /metrics\n".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain; charset=utf-8", "Only GET requests are supported\n".to_string())
+    };
+
+    let mut writer = &stream;
+    write!(writer, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, content_type, body.len(), body)?;
+    writer.flush()
+}
+
+/**
+ * Bind the metrics listener, this is done before privileges are dropped since
+ * the listening port may be a privileged port.
+ */
+pub fn bind_listener(listen_address: SocketAddr) -> Result<TcpListener, String> {
+
+    TcpListener::bind(listen_address).map_err(|err| {
+        format!("Could not listen on {} for metrics ({})", listen_address, err)
+    })
+}
+
+/**
+ * Serve the metrics in a background thread. Clients are handled one by one,
+ * which is enough for a few Prometheus scrapers. Each client gets at most 5
+ * seconds to send its request, a stalled client only delays the next ones.
+ */
+pub fn serve_metrics(listener: TcpListener, registry: Arc<Mutex<MetricsRegistry>>) {
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(err) = handle_client(stream, &registry) {
+                        eprintln!("[warn] Could not answer metrics request ({})", err);
+                    }
+                },
+                Err(err) => eprintln!("[warn] Could not accept metrics connection ({})", err)
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use std::net::Ipv4Addr;
+
+    fn build_target(ipv4: Ipv4Addr, vendor: Option<&str>) -> TargetDetails {
+        TargetDetails {
+            vendor: vendor.map(|vendor| vendor.to_string()),
+            ..TargetDetails::build(ipv4, "00:11:22:33:44:55".parse().unwrap())
+        }
+    }
+
+    fn build_summary() -> ResponseSummary {
+        ResponseSummary {
+            packet_count: 12,
+            arp_count: 4,
+            start_timestamp: 1_700_000_000,
+            duration_ms: 1500,
+            target_count: 512,
+            conflict_count: 1,
+            request_count: 512,
+            request_rate: None
+        }
+    }
+
+    #[test]
+    fn should_render_empty_registry() {
+
+        assert_eq!(render_metrics(&MetricsRegistry::default()), "# HELP arp_scan_scans_total Number of completed scans\n# TYPE arp_scan_scans_total counter\narp_scan_scans_total 0\n");
+    }
+
+    #[test]
+    fn should_render_scan_metrics() {
+
+        let lab_network: IpNetwork = "10.20.0.0/24".parse().unwrap();
+        let office_network: IpNetwork = "10.30.0.0/24".parse().unwrap();
+        let target_details = vec![
+            build_target(Ipv4Addr::new(10, 20, 0, 12), Some("Dell \"Inc.\"")),
+            build_target(Ipv4Addr::new(10, 20, 0, 13), None),
+            build_target(Ipv4Addr::new(10, 30, 0, 2), Some("Dell \"Inc.\""))
+        ];
+
+        let mut registry = MetricsRegistry::default();
+        registry.record_scan(ScanMetrics::new("eth0", &[&lab_network, &office_network], &build_summary(), &target_details));
+        let metrics_text = render_metrics(&registry);
+
+        assert!(metrics_text.contains("arp_scan_scans_total 1\n"));
+        assert!(metrics_text.contains("arp_scan_hosts{interface=\"eth0\",network=\"10.20.0.0/24\"} 2\n"));
+        assert!(metrics_text.contains("arp_scan_hosts{interface=\"eth0\",network=\"10.30.0.0/24\"} 1\n"));
+        assert!(metrics_text.contains("arp_scan_vendor_hosts{interface=\"eth0\",vendor=\"Dell \\\"Inc.\\\"\"} 2\n"));
+        assert!(metrics_text.contains("arp_scan_vendor_hosts{interface=\"eth0\",vendor=\"unknown\"} 1\n"));
+        assert!(metrics_text.contains("arp_scan_duration_seconds{interface=\"eth0\"} 1.500\n"));
+        assert!(metrics_text.contains("arp_scan_ip_conflicts{interface=\"eth0\"} 1\n"));
+    }
+
+    #[test]
+    fn should_read_request_line() {
+
+        let request = "GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n";
+
+        assert_eq!(read_request_line(request.as_bytes()).unwrap(), Some("GET /metrics HTTP/1.1\r\n".to_string()));
+    }
+
+    #[test]
+    fn should_reject_oversized_request() {
+
+        let long_header = format!("GET /metrics HTTP/1.1\r\nCookie: {}\r\n\r\n", "a".repeat(10000));
+        let many_headers = format!("GET /metrics HTTP/1.1\r\n{}\r\n", "X-Test: a\r\n".repeat(1000));
+
+        assert_eq!(read_request_line(long_header.as_bytes()).unwrap(), None);
+        assert_eq!(read_request_line(many_headers.as_bytes()).unwrap(), None);
+        assert_eq!(read_request_line("GET /metrics HTTP/1.1\r\n".as_bytes()).unwrap(), None);
+    }
+
+}
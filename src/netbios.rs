@@ -0,0 +1,156 @@
+use crate::dns;
+
+pub const NETBIOS_PORT: u16 = 137;
+
+// Node status query type (NBSTAT) and internet class, see RFC 1002
+const NODE_STATUS_TYPE: u16 = 0x0021;
+const IN_CLASS: u16 = 0x0001;
+
+const NAME_ENTRY_SIZE: usize = 18;
+const WORKSTATION_SUFFIX: u8 = 0x00;
+const GROUP_NAME_FLAG: u16 = 0x8000;
+
+/**
+ * Encode a NetBIOS name with the first-level encoding of RFC 1001: the name
+ * is padded to 16 bytes and each half-byte is mapped to a letter from 'A'.
+ */
+fn encode_netbios_name(name: &[u8]) -> Vec<u8> {
+
+    let mut padded_name = [0u8; 16];
+    let name_length = name.len().min(16);
+    padded_name[..name_length].copy_from_slice(&name[..name_length]);
+
+    padded_name.iter()
+        .flat_map(|byte| [b'A' + (byte >> 4), b'A' + (byte & 0x0F)])
+        .collect()
+}
+
+/**
+ * Build a node status request for the wildcard name '*'. Any NetBIOS host
+ * answers this request with the list of its registered names.
+ */
+pub fn build_node_status_request(transaction_id: u16) -> Vec<u8> {
+
+    let mut packet: Vec<u8> = Vec::with_capacity(50);
+    packet.extend_from_slice(&transaction_id.to_be_bytes());
+    packet.extend_from_slice(&[0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+
+    packet.push(32);
+    packet.extend_from_slice(&encode_netbios_name(b"*"));
+    packet.push(0);
+
+    packet.extend_from_slice(&NODE_STATUS_TYPE.to_be_bytes());
+    packet.extend_from_slice(&IN_CLASS.to_be_bytes());
+
+    packet
+}
+
+/**
+ * Find the workstation name in a node status response. The workstation name
+ * is the first unique (non-group) name with the 0x00 suffix.
+ */
+pub fn parse_node_status_response(packet: &[u8], transaction_id: u16) -> Option<String> {
+
+    let (answer_offset, answer_count) = dns::read_response_header(packet, transaction_id)?;
+    if answer_count == 0 {
+        return None;
+    }
+
+    let (_, record_type, rdata_offset, rdata_end) = dns::read_record(packet, answer_offset)?;
+    if record_type != NODE_STATUS_TYPE {
+        return None;
+    }
+
+    let name_count = *packet.get(rdata_offset)? as usize;
+    let name_table = packet.get(rdata_offset + 1..rdata_end)?;
+
+    name_table.chunks_exact(NAME_ENTRY_SIZE)
+        .take(name_count)
+        .find(|entry| {
+            let name_flags = u16::from_be_bytes([entry[16], entry[17]]);
+            entry[15] == WORKSTATION_SUFFIX && name_flags & GROUP_NAME_FLAG == 0
+        })
+        .map(|entry| String::from_utf8_lossy(&entry[..15]).trim_end_matches([' ', '\0']).to_string())
+        .filter(|name| !name.is_empty() && !name.chars().any(|character| character.is_control()))
+}
+
+/**
+ * Build a name table entry and a node status response, used by the tests of
+ * the name sources.
+ */
+#[cfg(test)]
+pub fn build_name_entry(name: &str, suffix: u8, flags: u16) -> Vec<u8> {
+
+    let mut entry = format!("{: <15}", name).into_bytes();
+    entry.push(suffix);
+    entry.extend_from_slice(&flags.to_be_bytes());
+    entry
+}
+
+
+#[cfg(test)]
+pub fn build_node_status_response(transaction_id: u16, entries: &[Vec<u8>]) -> Vec<u8> {
+
+    let request = build_node_status_request(transaction_id);
+
+    let mut packet = vec![request[0], request[1], 0x84, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00];
+    packet.extend_from_slice(&request[12..46]);
+    packet.extend_from_slice(&[0x00, 0x21, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00]);
+
+    let mut rdata = vec![entries.len() as u8];
+    entries.iter().for_each(|entry| rdata.extend_from_slice(entry));
+    rdata.extend_from_slice(&[0u8; 46]);
+
+    packet.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+    packet.extend_from_slice(&rdata);
+    packet
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn should_encode_wildcard_request() {
+
+        let packet = build_node_status_request(0x0102);
+
+        assert_eq!(packet.len(), 50);
+        assert_eq!(&packet[13..17], b"CKAA");
+        assert_eq!(&packet[46..50], &[0x00, 0x21, 0x00, 0x01]);
+    }
+
+    #[test]
+    fn should_find_workstation_name() {
+
+        let entries = vec![
+            build_name_entry("WORKGROUP", 0x00, 0x8400),
+            build_name_entry("OFFICE-PC", 0x20, 0x0400),
+            build_name_entry("OFFICE-PC", 0x00, 0x0400)
+        ];
+        let packet = build_node_status_response(0x0102, &entries);
+
+        assert_eq!(parse_node_status_response(&packet, 0x0102), Some("OFFICE-PC".to_string()));
+        assert_eq!(parse_node_status_response(&packet, 0x0103), None);
+    }
+
+    #[test]
+    fn should_ignore_group_names() {
+
+        let entries = vec![build_name_entry("WORKGROUP", 0x00, 0x8400)];
+        let packet = build_node_status_response(0x0102, &entries);
+
+        assert_eq!(parse_node_status_response(&packet, 0x0102), None);
+    }
+
+    #[test]
+    fn should_reject_control_characters() {
+
+        let entries = vec![build_name_entry("\x1B[31mPC\r\n", 0x00, 0x0400)];
+        let packet = build_node_status_response(0x0102, &entries);
+
+        assert_eq!(parse_node_status_response(&packet, 0x0102), None);
+    }
+
+}
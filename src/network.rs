@@ -1,31 +1,40 @@
+use std::io;
 use std::process;
 use std::net::{IpAddr, Ipv4Addr};
-use std::time::Instant;
-use std::collections::HashMap;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::sync::mpsc::{Receiver, Sender};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::io::ErrorKind::TimedOut;
-use std::convert::TryInto;
 
-use dns_lookup::lookup_addr;
-use ipnetwork::IpNetwork;
+use ipnetwork::{IpNetwork, Ipv4Network};
 use pnet_datalink::{MacAddr, NetworkInterface, DataLinkSender, DataLinkReceiver};
 use pnet::packet::{MutablePacket, Packet};
-use pnet::packet::ethernet::{EthernetPacket, MutableEthernetPacket, EtherTypes};
+use pnet::packet::ethernet::{EtherType, EthernetPacket, MutableEthernetPacket, EtherTypes};
 use pnet::packet::arp::{MutableArpPacket, ArpOperations, ArpHardwareTypes, ArpPacket};
 use pnet::packet::vlan::{ClassOfService, MutableVlanPacket};
-use rand::prelude::*;
-
-use crate::args::ScanOptions;
+use pnet::packet::ipv4::Ipv4Packet;
+use pnet::packet::ip::IpNextHeaderProtocols;
+use pnet::packet::udp::UdpPacket;
+use pnet::transport::{transport_channel, TransportChannelType, TransportSender};
+
+use crate::args::{NameSource, ScanOptions};
+use crate::baseline::BaselineViolation;
+use crate::dhcp::{self, DhcpFingerprint};
+use crate::fingerprint::{ArpBehaviour, PaddingKind};
+use crate::icmp;
+use crate::permutation::IndexPermutation;
+use crate::rate;
+use crate::stream::StreamWriter;
 use crate::utils;
 use crate::vendor::Vendor;
-use crate::args::ScanTiming;
 
 pub const DATALINK_RCV_TIMEOUT: u64 = 500;
 
 const VLAN_QOS_DEFAULT: u8 = 1;
 const ARP_PACKET_SIZE: usize = 28;
-const VLAN_PACKET_SIZE: usize = 32;
+const ICMP_SOCKET_BUFFER_SIZE: usize = 4096;
 
 const ETHERNET_STD_PACKET_SIZE: usize = 42;
 const ETHERNET_VLAN_PACKET_SIZE: usize = 46;
@@ -35,7 +44,8 @@ const ETHERNET_VLAN_PACKET_SIZE: usize = 46;
  * starts and should give insights about the scan.
  */
 pub struct ScanEstimation {
-    pub interval_ms: u64,
+    pub request_count: u128,
+    pub packet_rate: Option<f64>,
     pub duration_ms: u128,
     pub request_size: u128,
     pub bandwidth: u128
@@ -48,7 +58,12 @@ pub struct ScanEstimation {
 pub struct ResponseSummary {
     pub packet_count: usize,
     pub arp_count: usize,
-    pub duration_ms: u128
+    pub start_timestamp: u64,
+    pub duration_ms: u128,
+    pub target_count: u128,
+    pub conflict_count: usize,
+    pub request_count: u64,
+    pub request_rate: Option<f64>
 }
 
 /**
@@ -56,19 +71,74 @@ pub struct ResponseSummary {
  * address and a linked MAC address. Hostnames are optional since some hosts
  * does not respond to the resolve call (or the numeric mode may be enabled).
  */
+#[derive(Clone)]
 pub struct TargetDetails {
     pub ipv4: Ipv4Addr,
     pub mac: MacAddr,
     pub hostname: Option<String>,
-    pub vendor: Option<String>
+    pub hostname_source: Option<NameSource>,
+    pub hostname_confirmed: Option<bool>,
+    pub dhcp: Option<DhcpFingerprint>,
+    pub vendor: Option<String>,
+    pub device_class: Option<String>,
+    pub open_ports: Vec<u16>,
+    pub behaviour: ArpBehaviour,
+    pub found_by: DiscoveryMethod,
+    pub violations: Vec<BaselineViolation>
+}
+
+/**
+ * Build a host found by ARP requests without any other detail, tests then set
+ * the fields they need with the struct update syntax.
+ */
+#[cfg(test)]
+impl TargetDetails {
+
+    pub fn build(ipv4: Ipv4Addr, mac: MacAddr) -> Self {
+        TargetDetails {
+            ipv4,
+            mac,
+            hostname: None,
+            hostname_source: None,
+            hostname_confirmed: None,
+            dhcp: None,
+            vendor: None,
+            device_class: None,
+            open_ports: vec![],
+            behaviour: ArpBehaviour::default(),
+            found_by: DiscoveryMethod::Arp,
+            violations: vec![]
+        }
+    }
+}
+
+/**
+ * Requests answered by a discovered host. Hosts only answering ICMP requests
+ * have no matching ARP entry (proxy ARP, hardened hosts, ...).
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiscoveryMethod {
+    Arp,
+    Icmp,
+    Both
+}
+
+impl DiscoveryMethod {
+
+    pub fn name(&self) -> &str {
+        match self {
+            DiscoveryMethod::Arp => "arp",
+            DiscoveryMethod::Icmp => "icmp",
+            DiscoveryMethod::Both => "both"
+        }
+    }
 }
 
 /**
- * Compute a network configuration based on the scan options and available
- * interfaces. This configuration will be used in the scan process to target a
- * specific network on a network interfaces.
+ * Select the scan interface based on the scan options and available
+ * interfaces, the default interface is used when none is requested.
  */
-pub fn compute_network_configuration<'a>(interfaces: &'a [NetworkInterface], scan_options: &'a Arc<ScanOptions>) -> (&'a NetworkInterface, Vec<&'a IpNetwork>) {
+pub fn select_interface<'a>(interfaces: &'a [NetworkInterface], scan_options: &ScanOptions) -> &'a NetworkInterface {
     
     let mut interface_name = scan_options.interface_name.clone();
     if scan_options.interface_name.is_none() && scan_options.interface_index.is_none() {
@@ -90,18 +160,23 @@ pub fn compute_network_configuration<'a>(interfaces: &'a [NetworkInterface], sca
         }
     };
 
-    let selected_interface = selected_interface.unwrap_or_else(|| {
+    selected_interface.unwrap_or_else(|| {
         eprintln!("Could not find the specified interface");
         eprintln!("Make sure the interface is up, not loopback, and has a valid IPv4");
-            process::exit(1);
-        });
+        process::exit(1);
+    })
+}
 
-    let ip_networks: Vec<&ipnetwork::IpNetwork> = match &scan_options.network_range {
-        Some(network_range) => network_range.iter().collect(),
-        None => selected_interface.ips.iter().filter(|ip_network| ip_network.is_ipv4()).collect()
-    };
+/**
+ * Compute the networks targeted by the scan: the requested networks, or the
+ * IPv4 networks of the selected interface.
+ */
+pub fn compute_target_networks<'a>(selected_interface: &'a NetworkInterface, target_networks: Option<&'a [IpNetwork]>) -> Vec<&'a IpNetwork> {
 
-    (selected_interface, ip_networks)
+    match target_networks {
+        Some(target_networks) => target_networks.iter().collect(),
+        None => selected_interface.ips.iter().filter(|ip_network| ip_network.is_ipv4()).collect()
+    }
 }
 
 fn find_interface_by_name<'a>(interfaces: &'a [NetworkInterface], interface_name: &String) -> Option<&'a NetworkInterface> {
@@ -119,58 +194,104 @@ fn find_interface_by_index(interfaces: &[NetworkInterface], interface_index: u32
  * Based on the network size and given scan options, this function performs an
  * estimation of the scan impact (timing, bandwidth, ...). Keep in mind that
  * this is only an estimation, real results may vary based on the network.
+ * An error is returned if the estimation can not be computed (overflows).
  */
-pub fn compute_scan_estimation(host_count: u128, options: &Arc<ScanOptions>) -> ScanEstimation {
+pub fn compute_scan_estimation(host_count: u128, options: &ScanOptions) -> Result<ScanEstimation, String> {
+
+    let overflow_error = || "Scan is too large to be estimated".to_string();
 
     let timeout: u128 = options.timeout_ms.into();
-    let packet_size: u128 = match options.has_vlan() {
-        true => ETHERNET_VLAN_PACKET_SIZE.try_into().expect("Internal number conversion failed for VLAN packet size"),
-        false => ETHERNET_STD_PACKET_SIZE.try_into().expect("Internal number conversion failed for Ethernet packet size")
-    };
-    let retry_count: u128 = options.retry_count.try_into().unwrap_or_else(|err| {
-        eprintln!("[warn] Could not cast retry count, defaults to 1 - {}", err);
-        1
-    });
+    let packet_size: u128 = compute_average_packet_size(options) as u128;
+    let retry_count: u128 = options.retry_count as u128;
 
     // The values below are averages based on an amount of performed network
     // scans. This may of course vary based on network configurations.
-    let avg_arp_request_ms: u128 = 3;
-    let avg_resolve_ms = 500;
-
-    let request_size: u128 = host_count * packet_size;
-
-    // Either the user provides an interval (expressed in milliseconds), either
-    // he provides a bandwidth (in bits per second) or either we are using the
-    // default interval. The goal of the code below is to compute the interval
-    // & bandwidth, based on the given inputs. Note that the computations in
-    // each match arm are therefore linked (but rewritten, based on the inputs).
-    let (interval_ms, bandwidth, request_phase_ms): (u64, u128, u128) = match options.scan_timing {
-        ScanTiming::Bandwidth(bandwidth) => {
+    let avg_unlimited_request_us: u128 = 20;
+    let avg_resolve_ms: u128 = match options.resolve_hostname {
+        true => 500,
+        false => 0
+    };
 
-            let bandwidth_lg: u128 = bandwidth.into();
-            let request_phase_ms: u128 = (request_size * 1000) / bandwidth_lg;
-            let interval_ms: u128 = (request_phase_ms/retry_count/host_count) - avg_arp_request_ms;
-            
-            (interval_ms.try_into().unwrap(), bandwidth_lg, request_phase_ms)
+    // A resumed scan will only send the requests that were not sent before the
+    // interruption (previous retry rounds and the current round position).
+    // An ICMP request is sent after each ARP request when requested.
+    let target_requests: u128 = match options.icmp_probe {
+        Some(_) => 2,
+        None => 1
+    };
+    let total_count = host_count.checked_mul(retry_count)
+        .and_then(|target_count| target_count.checked_mul(target_requests))
+        .ok_or_else(overflow_error)?;
+    let sent_count: u128 = match options.resume_checkpoint() {
+        Some(checkpoint) => host_count.saturating_mul(checkpoint.retry_round as u128).saturating_add(checkpoint.position.into()).saturating_mul(target_requests),
+        None => 0
+    };
+    let request_count = total_count.saturating_sub(sent_count);
+    let request_size = request_count.checked_mul(packet_size).ok_or_else(overflow_error)?;
+
+    // The requests are paced by a token bucket: the bucket starts full, which
+    // means that the first burst is sent without waiting. The next requests
+    // are then sent at the packet rate. Without any rate limit, the requests
+    // are sent as fast as the network interface allows.
+    let packet_rate = rate::compute_packet_rate(&options.scan_timing, packet_size as usize);
+    let (request_phase_ms, bandwidth): (u128, u128) = match packet_rate {
+        Some(packet_rate) => {
+
+            let paced_count = request_count.saturating_sub(options.burst_size.into());
+            let request_phase_ms = (paced_count as f64) * 1000.0 / packet_rate;
+            if !request_phase_ms.is_finite() || request_phase_ms >= (u64::MAX as f64) {
+                return Err(overflow_error());
+            }
 
+            let bandwidth = (packet_rate * (packet_size as f64)).round() as u128;
+            (request_phase_ms.ceil() as u128, bandwidth)
         },
-        ScanTiming::Interval(interval) => {
-
-            let interval_ms_lg: u128 = interval.into();
-            let request_phase_ms: u128 = (host_count * (avg_arp_request_ms + interval_ms_lg)) * retry_count;
-            let bandwidth = (request_size * 1000) / request_phase_ms;
-
-            (interval, bandwidth, request_phase_ms)
+        None => {
+
+            let request_phase_us = request_count.checked_mul(avg_unlimited_request_us).ok_or_else(overflow_error)?;
+            let bandwidth = match request_phase_us {
+                0 => 0,
+                _ => request_size.saturating_mul(1_000_000) / request_phase_us
+            };
+            (request_phase_us.div_ceil(1000), bandwidth)
         }
     };
-    
-    let duration_ms = request_phase_ms + timeout + avg_resolve_ms;
 
-    ScanEstimation {
-        interval_ms,
+    let duration_ms = request_phase_ms
+        .checked_add(timeout)
+        .and_then(|duration_ms| duration_ms.checked_add(avg_resolve_ms))
+        .ok_or_else(overflow_error)?;
+
+    Ok(ScanEstimation {
+        request_count,
+        packet_rate,
         duration_ms,
         request_size,
         bandwidth
+    })
+}
+
+/**
+ * Average size of the requests sent to each target: the ARP request, and the
+ * ICMP request when requested (in the same Ethernet and 802.1Q headers).
+ */
+fn compute_average_packet_size(options: &ScanOptions) -> usize {
+
+    let arp_size = compute_packet_size(options);
+    match options.icmp_probe {
+        Some(icmp_probe) => (2 * arp_size - ARP_PACKET_SIZE + icmp::compute_request_size(icmp_probe)).div_ceil(2),
+        None => arp_size
+    }
+}
+
+/**
+ * Size of a single ARP request on the network, including the Ethernet header
+ * (and the 802.1Q header when a VLAN is used).
+ */
+pub fn compute_packet_size(options: &ScanOptions) -> usize {
+    match options.has_vlan() {
+        true => ETHERNET_VLAN_PACKET_SIZE,
+        false => ETHERNET_STD_PACKET_SIZE
     }
 }
 
@@ -181,35 +302,8 @@ pub fn compute_scan_estimation(host_count: u128, options: &Arc<ScanOptions>) ->
  */
 pub fn send_arp_request(tx: &mut Box<dyn DataLinkSender>, interface: &NetworkInterface, source_ip: Ipv4Addr, target_ip: Ipv4Addr, options: Arc<ScanOptions>) {
 
-    let mut ethernet_buffer = match options.has_vlan() {
-        true => vec![0u8; ETHERNET_VLAN_PACKET_SIZE],
-        false => vec![0u8; ETHERNET_STD_PACKET_SIZE]
-    };
-    let mut ethernet_packet = MutableEthernetPacket::new(&mut ethernet_buffer).unwrap_or_else(|| {
-        eprintln!("Could not build Ethernet packet");
-        process::exit(1);
-    });
-
-    let target_mac = match options.destination_mac {
-        Some(forced_mac) => forced_mac,
-        None => MacAddr::broadcast()
-    };
-    let source_mac = match options.source_mac {
-        Some(forced_source_mac) => forced_source_mac,
-        None => interface.mac.unwrap_or_else(|| {
-            eprintln!("Interface should have a MAC address");
-            process::exit(1);
-        })
-    };
-
-    ethernet_packet.set_destination(target_mac);
-    ethernet_packet.set_source(source_mac);
-
-    let selected_ethertype = match options.vlan_id {
-        Some(_) => EtherTypes::Vlan,
-        None => EtherTypes::Arp
-    };
-    ethernet_packet.set_ethertype(selected_ethertype);
+    let target_mac = find_destination_mac(target_ip, &options);
+    let source_mac = find_source_mac(interface, &options);
 
     let mut arp_buffer = [0u8; ARP_PACKET_SIZE];
     let mut arp_packet = MutableArpPacket::new(&mut arp_buffer).unwrap_or_else(|| {
@@ -227,9 +321,100 @@ pub fn send_arp_request(tx: &mut Box<dyn DataLinkSender>, interface: &NetworkInt
     arp_packet.set_target_hw_addr(target_mac);
     arp_packet.set_target_proto_addr(target_ip);
 
+    send_ethernet_frame(tx, interface, target_mac, EtherTypes::Arp, arp_packet.packet_mut(), &options);
+}
+
+/**
+ * Open the raw IPv4 socket used for ICMP requests, this needs the same
+ * privileges as the datalink channel.
+ */
+pub fn open_icmp_sender() -> io::Result<TransportSender> {
+
+    // The receiving side is not used, replies are captured by the datalink
+    // channel with the ARP replies.
+    let (icmp_tx, _) = transport_channel(ICMP_SOCKET_BUFFER_SIZE, TransportChannelType::Layer3(IpNextHeaderProtocols::Icmp))?;
+    Ok(icmp_tx)
+}
+
+/**
+ * Send a single ICMP request to a target IPv4 address. Hosts drop unicast IP
+ * datagrams received in broadcast frames (RFC 1122), the request is therefore
+ * sent to the MAC address of the target when it is known (inventory entry or
+ * forced destination MAC). Otherwise the IPv4 packet is given to the kernel,
+ * which resolves the MAC address of the target with its own broadcast ARP
+ * requests: hosts ignoring broadcast ARP requests are not reached this way.
+ * VLAN scans always know the destination MAC (checked with the options).
+ */
+pub fn send_icmp_request(tx: &mut Box<dyn DataLinkSender>, icmp_tx: &mut TransportSender, interface: &NetworkInterface, source_ip: Ipv4Addr, target_ip: Ipv4Addr, sequence: u16, options: Arc<ScanOptions>) {
+
+    let icmp_probe = match options.icmp_probe {
+        Some(icmp_probe) => icmp_probe,
+        None => return
+    };
+
+    let mut ipv4_buffer = icmp::build_icmp_request(icmp_probe, source_ip, target_ip, icmp::compute_identifier(), sequence);
+    match find_known_destination_mac(target_ip, &options) {
+        Some(target_mac) => send_ethernet_frame(tx, interface, target_mac, EtherTypes::Ipv4, &mut ipv4_buffer, &options),
+        None => {
+            if let Some(ipv4_packet) = Ipv4Packet::new(&ipv4_buffer) {
+                let _ = icmp_tx.send_to(ipv4_packet, IpAddr::V4(target_ip));
+            }
+        }
+    };
+}
+
+/**
+ * The MAC address of a target, when known before the scan: the expected MAC
+ * of an inventory entry or the forced destination MAC.
+ */
+fn find_known_destination_mac(target_ip: Ipv4Addr, options: &ScanOptions) -> Option<MacAddr> {
+
+    let inventory_mac = options.inventory().and_then(|inventory| inventory.find_mac(target_ip));
+    inventory_mac.or(options.destination_mac)
+}
+
+/**
+ * Requests are broadcasted by default. When verifying an inventory, each
+ * request is sent to the expected MAC address of the target (unicast).
+ */
+fn find_destination_mac(target_ip: Ipv4Addr, options: &ScanOptions) -> MacAddr {
+    find_known_destination_mac(target_ip, options).unwrap_or_else(MacAddr::broadcast)
+}
+
+fn find_source_mac(interface: &NetworkInterface, options: &ScanOptions) -> MacAddr {
+    match options.source_mac {
+        Some(forced_source_mac) => forced_source_mac,
+        None => interface.mac.unwrap_or_else(|| {
+            eprintln!("Interface should have a MAC address");
+            process::exit(1);
+        })
+    }
+}
+
+/**
+ * Wrap a payload (ARP or IPv4 packet) in an Ethernet frame and send it. When
+ * a VLAN is requested, the payload is wrapped in a 802.1Q header first.
+ */
+fn send_ethernet_frame(tx: &mut Box<dyn DataLinkSender>, interface: &NetworkInterface, destination_mac: MacAddr, payload_ethertype: EtherType, payload: &mut [u8], options: &ScanOptions) {
+
+    let header_size = match options.has_vlan() {
+        true => MutableEthernetPacket::minimum_packet_size() + MutableVlanPacket::minimum_packet_size(),
+        false => MutableEthernetPacket::minimum_packet_size()
+    };
+    let mut ethernet_buffer = vec![0u8; header_size + payload.len()];
+    let mut ethernet_packet = MutableEthernetPacket::new(&mut ethernet_buffer).unwrap_or_else(|| {
+        eprintln!("Could not build Ethernet packet");
+        process::exit(1);
+    });
+
+    ethernet_packet.set_destination(destination_mac);
+    ethernet_packet.set_source(find_source_mac(interface, options));
+
     if let Some(vlan_id) = options.vlan_id {
 
-        let mut vlan_buffer = [0u8; VLAN_PACKET_SIZE];
+        ethernet_packet.set_ethertype(EtherTypes::Vlan);
+
+        let mut vlan_buffer = vec![0u8; MutableVlanPacket::minimum_packet_size() + payload.len()];
         let mut vlan_packet = MutableVlanPacket::new(&mut vlan_buffer).unwrap_or_else(|| {
             eprintln!("Could not build VLAN packet");
             process::exit(1);
@@ -237,14 +422,15 @@ pub fn send_arp_request(tx: &mut Box<dyn DataLinkSender>, interface: &NetworkInt
         vlan_packet.set_vlan_identifier(vlan_id);
         vlan_packet.set_priority_code_point(ClassOfService::new(VLAN_QOS_DEFAULT));
         vlan_packet.set_drop_eligible_indicator(0);
-        vlan_packet.set_ethertype(EtherTypes::Arp);
+        vlan_packet.set_ethertype(payload_ethertype);
 
-        vlan_packet.set_payload(arp_packet.packet_mut());
+        vlan_packet.set_payload(payload);
 
         ethernet_packet.set_payload(vlan_packet.packet_mut());
     }
     else {
-        ethernet_packet.set_payload(arp_packet.packet_mut());
+        ethernet_packet.set_ethertype(payload_ethertype);
+        ethernet_packet.set_payload(payload);
     }
 
     tx.send_to(ethernet_packet.to_immutable().packet(), Some(interface.clone()));
@@ -253,69 +439,70 @@ pub fn send_arp_request(tx: &mut Box<dyn DataLinkSender>, interface: &NetworkInt
 /**
  * A network iterator for iterating over multiple network ranges in with a
  * low-memory approach. This iterator was crafted to allow iteration over huge
- * network ranges (192.168.0.0/16) without consuming excessive memory.
+ * network ranges (192.168.0.0/16) without consuming excessive memory. Each
+ * target is identified by an index over all networks, which allows a full
+ * permutation of the targets when a random seed is given.
  */
 pub struct NetworkIterator {
-    current_iterator: Option<ipnetwork::IpNetworkIterator>,
-    networks: Vec<IpNetwork>,
-    is_random: bool,
-    random_pool: Vec<IpAddr>
+    networks: Vec<Ipv4Network>,
+    network_offsets: Vec<u64>,
+    target_count: u64,
+    position: u64,
+    permutation: Option<IndexPermutation>
 }
 
 impl NetworkIterator {
 
-    pub fn new(networks_ref: &[&IpNetwork], is_random: bool) -> NetworkIterator {
+    pub fn new(networks_ref: &[&IpNetwork], random_seed: Option<u64>) -> NetworkIterator {
 
-        // The IpNetwork struct implements the Clone trait, which means that a simple
-        // dereference will clone the struct in the new vector
-        let mut networks: Vec<IpNetwork> = networks_ref.iter().map(|network| *(*network)).collect();
+        // IPv6 networks are not supported by the ARP protocol and are therefore
+        // ignored by the iterator (the network size computation rejects them).
+        let networks: Vec<Ipv4Network> = networks_ref.iter().filter_map(|network| match network {
+            IpNetwork::V4(ipv4_network) => Some(*ipv4_network),
+            IpNetwork::V6(_) => None
+        }).collect();
 
-        if is_random {
-            let mut rng = rand::thread_rng();
-            networks.shuffle(&mut rng);
+        let mut network_offsets: Vec<u64> = Vec::with_capacity(networks.len());
+        let mut target_count: u64 = 0;
+        for network in networks.iter() {
+            network_offsets.push(target_count);
+            target_count += 1u64 << (32 - u32::from(network.prefix()));
         }
 
+        let permutation = random_seed.map(|seed| IndexPermutation::new(target_count, seed));
+
         NetworkIterator {
-            current_iterator: None,
             networks,
-            is_random,
-            random_pool: vec![]
+            network_offsets,
+            target_count,
+            position: 0,
+            permutation
         }
     }
 
     /**
-     * The functions below are not public and only used by the Iterator trait
-     * to help keep the next() code clean.
+     * Number of targets already returned by the iterator. This position can
+     * be saved and restored to continue an interrupted scan.
      */
-
-    fn has_no_items_left(&self) -> bool {
-        self.current_iterator.is_none() && self.networks.is_empty() && self.random_pool.is_empty()
+    pub fn position(&self) -> u64 {
+        self.position
     }
 
-    fn fill_random_pool(&mut self) {
-
-        for _ in 0..1000 {
-
-            let next_ip = self.current_iterator.as_mut().unwrap().next();
-            if next_ip.is_none() {
-                break;
-            }
-
-            self.random_pool.push(next_ip.unwrap());
-        }
-
-        let mut rng = rand::thread_rng();
-        self.random_pool.shuffle(&mut rng);
+    pub fn skip_to_position(&mut self, position: u64) {
+        self.position = position.min(self.target_count);
     }
 
-    fn select_new_iterator(&mut self) {
-
-        self.current_iterator = Some(self.networks.remove(0).iter());
-    }
+    /**
+     * Find the IPv4 address for a given target index, the networks are laid
+     * out one after the other (in the requested order).
+     */
+    fn find_target_address(&self, target_index: u64) -> Ipv4Addr {
 
-    fn pop_next_iterator_address(&mut self) -> Option<IpAddr> {
+        let network_index = self.network_offsets.partition_point(|offset| *offset <= target_index) - 1;
+        let network = self.networks[network_index];
+        let host_offset = target_index - self.network_offsets[network_index];
 
-        self.current_iterator.as_mut().map(|iterator| iterator.next()).unwrap_or(None)
+        Ipv4Addr::from(u32::from(network.network()) + host_offset as u32)
     }
 
 }
@@ -326,29 +513,17 @@ impl Iterator for NetworkIterator {
 
     fn next(&mut self) -> Option<Self::Item> {
 
-        if self.has_no_items_left() {
+        if self.position >= self.target_count {
             return None;
         }
 
-        if self.current_iterator.is_none() && !self.networks.is_empty() {
-            self.select_new_iterator();
-        }
-
-        if self.is_random && self.random_pool.is_empty() {
-            self.fill_random_pool();
-        }
-
-        let next_ip = match self.is_random {
-            true => self.random_pool.pop(),
-            false => self.pop_next_iterator_address()
+        let target_index = match &self.permutation {
+            Some(permutation) => permutation.permute(self.position),
+            None => self.position
         };
+        self.position += 1;
 
-        if next_ip.is_none() && !self.networks.is_empty() {
-            self.select_new_iterator();
-            return self.pop_next_iterator_address();
-        }
-
-        next_ip
+        Some(IpAddr::V4(self.find_target_address(target_index)))
     }
 }
 
@@ -373,20 +548,90 @@ pub fn find_source_ip(network_interface: &NetworkInterface, forced_source_ipv4:
     }
 }
 
+/**
+ * Find a DHCP client message (DISCOVER, REQUEST, INFORM) in an IPv4 frame.
+ * These messages are broadcasted by devices joining the network.
+ */
+fn find_dhcp_fingerprint(ethernet_packet: &EthernetPacket) -> Option<DhcpFingerprint> {
+
+    let ipv4_packet = Ipv4Packet::new(ethernet_packet.payload())?;
+    if ipv4_packet.get_next_level_protocol() != IpNextHeaderProtocols::Udp {
+        return None;
+    }
+
+    let udp_packet = UdpPacket::new(ipv4_packet.payload())?;
+    if udp_packet.get_source() != dhcp::DHCP_CLIENT_PORT || udp_packet.get_destination() != dhcp::DHCP_SERVER_PORT {
+        return None;
+    }
+
+    dhcp::parse_client_message(udp_packet.payload())
+}
+
+/**
+ * Channels between the sending thread and the response thread: the sending
+ * time of each request, and the discovered hosts (only needed for checkpoints
+ * and the progress display).
+ */
+pub struct CaptureChannels {
+    pub request_rx: Receiver<(Ipv4Addr, Instant)>,
+    pub discovery_tx: Option<Sender<(Ipv4Addr, MacAddr)>>
+}
+
 /**
  * Wait at least N seconds and receive ARP network responses. The main
  * downside of this function is the blocking nature of the datalink receiver:
  * when the N seconds are elapsed, the receiver loop will therefore only stop
  * on the next received frame. Therefore, the receiver should have been
- * configured to stop at certain intervals (500ms for example).
+ * configured to stop at certain intervals (500ms for example). When requested,
+ * DHCP client messages and ICMP replies seen on the same channel are also
+ * collected. The request channel gives the sending time of each ARP request,
+ * used to record the ARP behaviour of each host. Frames sent by the local
+ * interface (ARP requests of the kernel, ...) are ignored.
  */
-pub fn receive_arp_responses(rx: &mut Box<dyn DataLinkReceiver>, options: Arc<ScanOptions>, timed_out: Arc<AtomicBool>, vendor_list: &mut Vendor) -> (ResponseSummary, Vec<TargetDetails>) {
+pub fn receive_arp_responses(rx: &mut Box<dyn DataLinkReceiver>, timed_out: Arc<AtomicBool>, vendor_list: &mut Vendor, known_targets: Vec<TargetDetails>, channels: CaptureChannels, local_mac: Option<MacAddr>, options: Arc<ScanOptions>) -> (ResponseSummary, Vec<TargetDetails>) {
+
+    let CaptureChannels { request_rx, discovery_tx } = channels;
 
-    let mut discover_map: HashMap<Ipv4Addr, TargetDetails> = HashMap::new();
+    // Targets found in a previous run (when resuming a scan) are merged in the
+    // discover map, newer ARP responses will override them.
+    let mut discover_map: HashMap<Ipv4Addr, TargetDetails> = known_targets.into_iter()
+        .map(|target_detail| (target_detail.ipv4, target_detail))
+        .collect();
     let start_recording = Instant::now();
+    let start_timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0);
 
     let mut packet_count = 0;
     let mut arp_count = 0;
+    let mut dhcp_fingerprints: HashMap<MacAddr, DhcpFingerprint> = HashMap::new();
+
+    let mut request_times: HashMap<Ipv4Addr, Instant> = HashMap::new();
+    let mut request_counts: HashMap<Ipv4Addr, u32> = HashMap::new();
+
+    // An IPv4 address announced by several MAC addresses is an address
+    // conflict (or a spoofing attempt), conflicts are counted in the summary.
+    let mut sender_macs: HashMap<Ipv4Addr, HashSet<MacAddr>> = HashMap::new();
+
+    // In streaming mode, hosts are written as soon as they are discovered
+    // (hosts known from a resumed scan are written first).
+    let mut stream_writer = match (options.stream_output, options.stdout_format()) {
+        (true, Some(stdout_format)) => Some(StreamWriter::new(&stdout_format)),
+        _ => None
+    };
+    if let Some(stream_writer) = stream_writer.as_mut() {
+        for target_detail in discover_map.values() {
+            let vendor = vendor_list.search_by_mac(&target_detail.mac);
+            stream_writer.write_host(target_detail.ipv4, target_detail.mac, vendor.as_deref(), target_detail.found_by);
+        }
+    }
+
+    let icmp_identifier = options.icmp_probe.map(|_| icmp::compute_identifier());
+    let mut icmp_replies: HashMap<Ipv4Addr, MacAddr> = HashMap::new();
+
+    // ICMP requests given to the kernel trigger its own ARP requests (the
+    // datalink channel does not capture frames it sent itself). The replies
+    // to these kernel requests are not counted as ARP discoveries, otherwise
+    // hosts answering ICMP only would be reported as answering ARP.
+    let mut kernel_arp_requests: HashMap<Ipv4Addr, u32> = HashMap::new();
 
     loop {
 
@@ -410,12 +655,57 @@ pub fn receive_arp_responses(rx: &mut Box<dyn DataLinkReceiver>, options: Arc<Sc
             }
         };
         packet_count += 1;
+
+        request_rx.try_iter().for_each(|(target_ipv4, sent_at)| {
+            request_times.insert(target_ipv4, sent_at);
+            *request_counts.entry(target_ipv4).or_insert(0) += 1;
+        });
         
         let ethernet_packet = match EthernetPacket::new(arp_buffer) {
             Some(packet) => packet,
             None => continue
         };
 
+        if local_mac.is_some_and(|local_mac| ethernet_packet.get_source() == local_mac) {
+            let kernel_arp_request = match (icmp_identifier, ethernet_packet.get_ethertype()) {
+                (Some(_), EtherTypes::Arp) => ArpPacket::new(ethernet_packet.payload())
+                    .filter(|arp| arp.get_operation() == ArpOperations::Request),
+                _ => None
+            };
+            if let Some(arp) = kernel_arp_request {
+                *kernel_arp_requests.entry(arp.get_target_proto_addr()).or_insert(0) += 1;
+            }
+            continue;
+        }
+
+        if ethernet_packet.get_ethertype() == EtherTypes::Ipv4 {
+
+            let icmp_sender = icmp_identifier.and_then(|identifier| {
+                Ipv4Packet::new(ethernet_packet.payload()).and_then(|ipv4_packet| icmp::parse_icmp_reply(&ipv4_packet, identifier))
+            });
+            // Replies from addresses that were not scanned are ignored, they
+            // may answer requests sent by another process.
+            if let Some(sender_ipv4) = icmp_sender.filter(|sender_ipv4| request_times.contains_key(sender_ipv4)) {
+                icmp_replies.insert(sender_ipv4, ethernet_packet.get_source());
+            }
+
+            // Devices may send several messages (DISCOVER then REQUEST), the
+            // details of all messages are merged for each client.
+            let dhcp_fingerprint = match options.listen_dhcp {
+                true => find_dhcp_fingerprint(&ethernet_packet),
+                false => None
+            };
+            if let Some(fingerprint) = dhcp_fingerprint {
+                match dhcp_fingerprints.get_mut(&fingerprint.mac) {
+                    Some(known_fingerprint) => known_fingerprint.merge(fingerprint),
+                    None => {
+                        dhcp_fingerprints.insert(fingerprint.mac, fingerprint);
+                    }
+                };
+            }
+            continue;
+        }
+
         let is_arp_type = matches!(ethernet_packet.get_ethertype(), EtherTypes::Arp);
         if !is_arp_type {
             continue;
@@ -432,28 +722,113 @@ pub fn receive_arp_responses(rx: &mut Box<dyn DataLinkReceiver>, options: Arc<Sc
 
             let sender_ipv4 = arp.get_sender_proto_addr();
             let sender_mac = arp.get_sender_hw_addr();
+
+            if arp.get_operation() == ArpOperations::Reply {
+                if let Some(pending_count) = kernel_arp_requests.get_mut(&sender_ipv4).filter(|count| **count > 0) {
+                    *pending_count -= 1;
+                    continue;
+                }
+            }
+
+            // The discovery channel is only used to follow the scan progress,
+            // a closed channel should not stop the ARP response capture.
+            if let Some(discovery_tx) = &discovery_tx {
+                let _ = discovery_tx.send((sender_ipv4, sender_mac));
+            }
+
+            // ARP probes are sent with an unspecified sender address, they
+            // can not conflict with other hosts.
+            if !sender_ipv4.is_unspecified() {
+                sender_macs.entry(sender_ipv4).or_default().insert(sender_mac);
+            }
+
+            if let Some(stream_writer) = stream_writer.as_mut() {
+                if !discover_map.contains_key(&sender_ipv4) {
+                    let vendor = vendor_list.search_by_mac(&sender_mac);
+                    stream_writer.write_host(sender_ipv4, sender_mac, vendor.as_deref(), DiscoveryMethod::Arp);
+                }
+            }
+
+            // The ARP behaviour is kept across responses of the same host, the
+            // latency is measured from the last request sent to this host.
+            let mut behaviour = discover_map.remove(&sender_ipv4)
+                .map(|target_detail| target_detail.behaviour)
+                .unwrap_or_default();
+
+            if arp.get_operation() == ArpOperations::Reply {
+                behaviour.reply_count += 1;
+                if let Some(sent_at) = request_times.get(&sender_ipv4) {
+                    behaviour.record_latency(sent_at.elapsed().as_micros() as u64);
+                }
+            }
+            if sender_ipv4 == arp.get_target_proto_addr() {
+                behaviour.gratuitous_count += 1;
+            }
+            let padding_offset = MutableEthernetPacket::minimum_packet_size() + ARP_PACKET_SIZE;
+            behaviour.padding = Some(PaddingKind::from_padding(arp_buffer.get(padding_offset..).unwrap_or_default()));
     
             discover_map.insert(sender_ipv4, TargetDetails {
                 ipv4: sender_ipv4,
                 mac: sender_mac,
                 hostname: None,
-                vendor: None
+                hostname_source: None,
+                hostname_confirmed: None,
+                dhcp: None,
+                vendor: None,
+                device_class: None,
+                open_ports: vec![],
+                behaviour,
+                found_by: DiscoveryMethod::Arp,
+                violations: vec![]
             });
         }
     }
 
-    // For each target found, enhance each item with additional results
-    // results such as the hostname & MAC vendor.
-    let target_details = discover_map.into_values().map(|mut target_detail| {
+    request_rx.try_iter().for_each(|(target_ipv4, _)| {
+        *request_counts.entry(target_ipv4).or_insert(0) += 1;
+    });
 
-        if options.resolve_hostname {
-            target_detail.hostname = find_hostname(target_detail.ipv4);
-        }
+    // Hosts answering ICMP requests are marked in the discover map, hosts
+    // that did not answer ARP requests are added with the MAC address of the
+    // ICMP reply frame.
+    for (sender_ipv4, sender_mac) in icmp_replies {
+        match discover_map.get_mut(&sender_ipv4) {
+            Some(target_detail) => target_detail.found_by = DiscoveryMethod::Both,
+            None => {
+                if let Some(stream_writer) = stream_writer.as_mut() {
+                    let vendor = vendor_list.search_by_mac(&sender_mac);
+                    stream_writer.write_host(sender_ipv4, sender_mac, vendor.as_deref(), DiscoveryMethod::Icmp);
+                }
+                discover_map.insert(sender_ipv4, TargetDetails {
+                    ipv4: sender_ipv4,
+                    mac: sender_mac,
+                    hostname: None,
+                    hostname_source: None,
+                    hostname_confirmed: None,
+                    dhcp: None,
+                    vendor: None,
+                    device_class: None,
+                    open_ports: vec![],
+                    behaviour: ArpBehaviour::default(),
+                    found_by: DiscoveryMethod::Icmp,
+                    violations: vec![]
+                });
+            }
+        };
+    }
+
+    // For each target found, enhance each item with additional results such
+    // as the MAC vendor or DHCP details. Hostnames are resolved later, once
+    // the capture ends.
+    let target_details = discover_map.into_values().map(|mut target_detail| {
 
         if vendor_list.has_vendor_db() {
             target_detail.vendor = vendor_list.search_by_mac(&target_detail.mac);
         }
 
+        target_detail.dhcp = dhcp_fingerprints.get(&target_detail.mac).cloned();
+        target_detail.behaviour.request_count = request_counts.get(&target_detail.ipv4).copied().unwrap_or(0);
+
         target_detail
 
     }).collect();
@@ -463,75 +838,124 @@ pub fn receive_arp_responses(rx: &mut Box<dyn DataLinkReceiver>, options: Arc<Sc
     let response_summary = ResponseSummary {
         packet_count,
         arp_count,
-        duration_ms: start_recording.elapsed().as_millis()
+        start_timestamp,
+        duration_ms: start_recording.elapsed().as_millis(),
+        target_count: 0,
+        conflict_count: sender_macs.values().filter(|macs| macs.len() > 1).count(),
+        request_count: 0,
+        request_rate: None
     };
     (response_summary, target_details)
 }
 
-/**
- * Find the local hostname linked to an IPv4 address. This will perform a
- * reverse DNS request in the local network to find the IPv4 hostname.
- */
-fn find_hostname(ipv4: Ipv4Addr) -> Option<String> {
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+
+    use super::*;
 
-    let ip: IpAddr = ipv4.into();
-    match lookup_addr(&ip) {
-        Ok(hostname) => {
+    use crate::args;
 
-            // The 'lookup_addr' function returns an IP address if no hostname
-            // was found. If this is the case, we prefer switching to None.
-            if hostname.parse::<IpAddr>().is_ok() {
-                return None; 
-            }
+    fn build_options(arguments: &[&str]) -> Arc<ScanOptions> {
 
-            Some(hostname)
-        },
-        Err(_) => None
+        let mut command_line = vec!["arp-scan"];
+        command_line.extend_from_slice(arguments);
+
+        Arc::new(ScanOptions::new(&args::build_args().get_matches_from(command_line)))
     }
-}
 
-#[cfg(test)]
-mod tests {
+    #[test]
+    fn should_estimate_default_scan() {
 
-    use super::*;
+        let options = build_options(&[]);
+        let estimation = compute_scan_estimation(256, &options).unwrap();
 
-    use ipnetwork::Ipv4Network;
-    use std::env;
+        // 255 paced requests at 100 packets/s, the first one is sent directly
+        assert_eq!(estimation.request_count, 256);
+        assert_eq!(estimation.request_size, 256 * 42);
+        assert_eq!(estimation.packet_rate, Some(100.0));
+        assert_eq!(estimation.bandwidth, 4200);
+        assert_eq!(estimation.duration_ms, 2550 + 2000 + 500);
+    }
 
     #[test]
-    fn should_resolve_public_ip() {
+    fn should_estimate_retries_and_vlan() {
 
-        // Sometimes, we do not have access to public networks in the test
-        // environment and can pass the OFFLINE environment variable.
-        if env::var("OFFLINE").is_ok() {
-            assert_eq!(true, true);
-        }
-        else {
-            let ipv4 = Ipv4Addr::new(1,1,1,1);
-            assert_eq!(find_hostname(ipv4), Some("one.one.one.one".to_string()));
+        let options = build_options(&["-r", "3", "-Q", "42", "--numeric", "-I", "1ms"]);
+        let estimation = compute_scan_estimation(100, &options).unwrap();
+
+        assert_eq!(estimation.request_count, 300);
+        assert_eq!(estimation.request_size, 300 * 46);
+        assert_eq!(estimation.duration_ms, 299 + 2000);
+    }
+
+    #[test]
+    fn should_estimate_icmp_requests() {
+
+        let options = build_options(&["--icmp", "timestamp", "--numeric", "-I", "1ms"]);
+        let estimation = compute_scan_estimation(100, &options).unwrap();
+
+        assert_eq!(estimation.request_count, 200);
+        assert_eq!(estimation.request_size, 200 * 48);
+        assert_eq!(estimation.duration_ms, 199 + 2000);
+    }
+
+    #[test]
+    fn should_estimate_bursts() {
+
+        let options = build_options(&["--rate", "10", "--burst", "5", "--numeric"]);
+        let estimation = compute_scan_estimation(25, &options).unwrap();
+
+        assert_eq!(estimation.duration_ms, 2000 + 2000);
+    }
+
+    #[test]
+    fn should_not_panic_on_high_bandwidth() {
+
+        let options = build_options(&["-B", "100000000000000"]);
+        let estimation = compute_scan_estimation(65536, &options).unwrap();
+
+        assert_eq!(estimation.request_count, 65536);
+        assert_eq!(estimation.bandwidth, 12_500_000_000_000);
+    }
+
+    #[test]
+    fn should_not_panic_on_empty_scan() {
+
+        for arguments in [vec!["-I", "0"], vec!["-B", "1000"], vec![]] {
+
+            let options = build_options(&arguments);
+            let estimation = compute_scan_estimation(0, &options).unwrap();
+
+            assert_eq!(estimation.request_count, 0);
+            assert_eq!(estimation.request_size, 0);
         }
     }
 
     #[test]
-    fn should_resolve_localhost() {
+    fn should_estimate_unlimited_scan() {
 
-        let ipv4 = Ipv4Addr::new(127,0,0,1);
+        let options = build_options(&["--profile", "fast"]);
+        let estimation = compute_scan_estimation(256, &options).unwrap();
 
-        assert_eq!(find_hostname(ipv4), Some("localhost".to_string()));
+        assert_eq!(estimation.packet_rate, None);
+        assert!(estimation.bandwidth > 0);
+        assert!(estimation.duration_ms >= 800);
     }
 
     #[test]
-    fn should_not_resolve_unknown_ip() {
+    fn should_fail_on_overflowing_scan() {
 
-        let ipv4 = Ipv4Addr::new(10,254,254,254);
+        let options = build_options(&["-r", "1000"]);
+        let estimation = compute_scan_estimation(u128::MAX / 10, &options);
 
-        assert_eq!(find_hostname(ipv4), None);
+        assert!(estimation.is_err());
     }
 
     #[test]
     fn should_iterate_over_empty_networks() {
 
-        let mut iterator = NetworkIterator::new(&vec![], false);
+        let mut iterator = NetworkIterator::new(&[], None);
 
         assert_eq!(iterator.next(), None);
     }
@@ -546,7 +970,7 @@ mod tests {
             &network_a
         ];
 
-        let mut iterator = NetworkIterator::new(&target_network, false);
+        let mut iterator = NetworkIterator::new(&target_network, None);
 
         assert_eq!(iterator.next(), Some(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1))));
         assert_eq!(iterator.next(), None);
@@ -562,7 +986,7 @@ mod tests {
             &network_a
         ];
 
-        let mut iterator = NetworkIterator::new(&target_network, false);
+        let mut iterator = NetworkIterator::new(&target_network, None);
 
         assert_eq!(iterator.next(), Some(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 0))));
         assert_eq!(iterator.next(), Some(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1))));
@@ -583,7 +1007,7 @@ mod tests {
             &network_b
         ];
 
-        let mut iterator = NetworkIterator::new(&target_network, false);
+        let mut iterator = NetworkIterator::new(&target_network, None);
 
         assert_eq!(iterator.next(), Some(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1))));
         assert_eq!(iterator.next(), Some(IpAddr::V4(Ipv4Addr::new(10, 10, 20, 20))));
@@ -604,11 +1028,53 @@ mod tests {
             &network_b
         ];
 
-        let mut iterator = NetworkIterator::new(&target_network, true);
+        let mut iterator = NetworkIterator::new(&target_network, Some(1234));
 
         assert_eq!(iterator.next().is_some(), true);
         assert_eq!(iterator.next().is_some(), true);
         assert_eq!(iterator.next(), None);
     }
 
+    #[test]
+    fn should_cover_all_networks_with_random() {
+
+        let network_a = IpNetwork::V4(
+            Ipv4Network::new(Ipv4Addr::new(192, 168, 1, 0), 24).unwrap()
+        );
+        let network_b = IpNetwork::V4(
+            Ipv4Network::new(Ipv4Addr::new(10, 10, 0, 0), 22).unwrap()
+        );
+        let target_network: Vec<&IpNetwork> = vec![
+            &network_a,
+            &network_b
+        ];
+
+        let mut random_addresses: Vec<IpAddr> = NetworkIterator::new(&target_network, Some(42)).collect();
+        let sequential_addresses: Vec<IpAddr> = NetworkIterator::new(&target_network, None).collect();
+
+        assert_eq!(random_addresses.len(), 256 + 1024);
+        assert_ne!(random_addresses, sequential_addresses);
+
+        random_addresses.sort();
+        let mut sorted_addresses = sequential_addresses.clone();
+        sorted_addresses.sort();
+        assert_eq!(random_addresses, sorted_addresses);
+    }
+
+    #[test]
+    fn should_reproduce_random_order_with_seed() {
+
+        let network_a = IpNetwork::V4(
+            Ipv4Network::new(Ipv4Addr::new(172, 16, 0, 0), 16).unwrap()
+        );
+        let target_network: Vec<&IpNetwork> = vec![
+            &network_a
+        ];
+
+        let first_run: Vec<IpAddr> = NetworkIterator::new(&target_network, Some(7)).take(500).collect();
+        let second_run: Vec<IpAddr> = NetworkIterator::new(&target_network, Some(7)).take(500).collect();
+
+        assert_eq!(first_run, second_run);
+    }
+
 }
@@ -0,0 +1,560 @@
+use std::collections::HashMap;
+#[cfg(unix)]
+use std::ffi::CStr;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpStream, ToSocketAddrs, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+use std::process::{self, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use pnet_datalink::MacAddr;
+
+use crate::args::ScanOptions;
+use crate::export::{self, ScanContext};
+use crate::inventory::InventoryCheck;
+use crate::network::{ResponseSummary, TargetDetails};
+use crate::time;
+
+const SYSLOG_PORT: u16 = 514;
+const HTTP_PORT: u16 = 80;
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+// Syslog messages use the 'user' facility, the severity depends on the event
+const SYSLOG_FACILITY: u8 = 1;
+const SEVERITY_WARNING: u8 = 4;
+const SEVERITY_NOTICE: u8 = 5;
+const SEVERITY_INFO: u8 = 6;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SyslogTransport {
+    Udp(String, u16),
+    Tcp(String, u16),
+    Unix(String)
+}
+
+/**
+ * A destination for scan notifications, configured with '--notify KIND:TARGET'.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum NotificationSink {
+    Webhook { host: String, port: u16, path: String },
+    Syslog(SyslogTransport),
+    Command(String)
+}
+
+impl NotificationSink {
+
+    pub fn describe(&self) -> String {
+        match self {
+            NotificationSink::Webhook { host, port, path } => format!("webhook http://{}:{}{}", host, port, path),
+            NotificationSink::Syslog(SyslogTransport::Udp(host, port)) => format!("syslog udp://{}:{}", host, port),
+            NotificationSink::Syslog(SyslogTransport::Tcp(host, port)) => format!("syslog tcp://{}:{}", host, port),
+            NotificationSink::Syslog(SyslogTransport::Unix(path)) => format!("syslog unix://{}", path),
+            NotificationSink::Command(program) => format!("command {}", program)
+        }
+    }
+}
+
+/**
+ * Split an address such as 'logs.lab:1514' into a host and a port, the
+ * default port is used when no port is given.
+ */
+fn parse_host_port(address: &str, default_port: u16) -> Result<(String, u16), String> {
+
+    let (host, port) = match address.rsplit_once(':') {
+        Some((host, port_text)) => {
+            let port = port_text.parse::<u16>().map_err(|_| format!("invalid port in '{}'", address))?;
+            (host, port)
+        },
+        None => (address, default_port)
+    };
+
+    match host.is_empty() {
+        true => Err(format!("missing host in '{}'", address)),
+        false => Ok((host.to_string(), port))
+    }
+}
+
+/**
+ * Parse a notification sink (e.g. 'webhook:http://10.0.0.5/scans',
+ * 'syslog:udp://logs.lab:514' or 'exec:./on-scan.sh').
+ */
+pub fn parse_sink(sink_text: &str) -> Result<NotificationSink, String> {
+
+    let (kind, target) = match sink_text.split_once(':') {
+        Some((_, "")) | None => return Err(format!("missing target in '{}'", sink_text)),
+        Some(parts) => parts
+    };
+
+    match kind {
+        "webhook" => {
+            let location = target.strip_prefix("http://").ok_or_else(|| format!("only http:// webhooks are supported, got '{}'", target))?;
+            let (address, path) = match location.find('/') {
+                Some(path_start) => (&location[..path_start], &location[path_start..]),
+                None => (location, "/")
+            };
+            let (host, port) = parse_host_port(address, HTTP_PORT)?;
+            Ok(NotificationSink::Webhook { host, port, path: path.to_string() })
+        },
+        "syslog" => {
+            if let Some(address) = target.strip_prefix("udp://") {
+                let (host, port) = parse_host_port(address, SYSLOG_PORT)?;
+                return Ok(NotificationSink::Syslog(SyslogTransport::Udp(host, port)));
+            }
+            if let Some(address) = target.strip_prefix("tcp://") {
+                let (host, port) = parse_host_port(address, SYSLOG_PORT)?;
+                return Ok(NotificationSink::Syslog(SyslogTransport::Tcp(host, port)));
+            }
+            match target.strip_prefix("unix://") {
+                Some(path) if !path.is_empty() => Ok(NotificationSink::Syslog(SyslogTransport::Unix(path.to_string()))),
+                _ => Err(format!("expected udp://, tcp:// or unix:// syslog address, got '{}'", target))
+            }
+        },
+        "exec" => Ok(NotificationSink::Command(target.to_string())),
+        _ => Err(format!("unknown sink '{}'", kind))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChangeKind {
+    NewHost,
+    MissingHost,
+    BaselineViolation
+}
+
+impl ChangeKind {
+
+    pub fn name(&self) -> &str {
+        match self {
+            ChangeKind::NewHost => "new_host",
+            ChangeKind::MissingHost => "missing_host",
+            ChangeKind::BaselineViolation => "baseline_violation"
+        }
+    }
+}
+
+/**
+ * A change found by a scan: hosts appearing or disappearing since the
+ * previous periodic scan, and hosts violating the baseline.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChangeEvent {
+    pub kind: ChangeKind,
+    pub ipv4: Ipv4Addr,
+    pub mac: MacAddr,
+    pub details: String
+}
+
+impl ChangeEvent {
+
+    fn describe(&self) -> String {
+        match self.details.is_empty() {
+            true => format!("{} {} {}", self.kind.name(), self.ipv4, self.mac),
+            false => format!("{} {} {} {}", self.kind.name(), self.ipv4, self.mac, self.details)
+        }
+    }
+}
+
+/**
+ * List the changes of a scan. New & missing hosts are only reported when a
+ * previous scan is known, hosts are identified by their MAC address.
+ */
+pub fn detect_changes(previous_hosts: Option<&HashMap<MacAddr, Ipv4Addr>>, target_details: &[TargetDetails]) -> Vec<ChangeEvent> {
+
+    let mut changes: Vec<ChangeEvent> = vec![];
+
+    if let Some(previous_hosts) = previous_hosts {
+
+        for detail in target_details.iter().filter(|detail| !previous_hosts.contains_key(&detail.mac)) {
+            changes.push(ChangeEvent { kind: ChangeKind::NewHost, ipv4: detail.ipv4, mac: detail.mac, details: String::new() });
+        }
+
+        let mut missing_hosts: Vec<(&MacAddr, &Ipv4Addr)> = previous_hosts.iter()
+            .filter(|(mac, _)| !target_details.iter().any(|detail| detail.mac == **mac))
+            .collect();
+        missing_hosts.sort_by_key(|(_, ipv4)| **ipv4);
+        for (mac, ipv4) in missing_hosts {
+            changes.push(ChangeEvent { kind: ChangeKind::MissingHost, ipv4: *ipv4, mac: *mac, details: String::new() });
+        }
+    }
+
+    for detail in target_details.iter().filter(|detail| !detail.violations.is_empty()) {
+        let details = detail.violations.iter()
+            .map(|violation| format!("{}/{}", violation.kind.name(), violation.severity.name()))
+            .collect::<Vec<String>>()
+            .join(",");
+        changes.push(ChangeEvent { kind: ChangeKind::BaselineViolation, ipv4: detail.ipv4, mac: detail.mac, details });
+    }
+
+    changes
+}
+
+#[cfg(unix)]
+fn local_hostname() -> String {
+
+    let mut buffer = [0 as libc::c_char; 256];
+    let result = unsafe { libc::gethostname(buffer.as_mut_ptr(), buffer.len()) };
+    if result != 0 {
+        return "-".to_string();
+    }
+
+    // Syslog hostnames are printable ASCII characters without spaces
+    let hostname = unsafe { CStr::from_ptr(buffer.as_ptr()) }.to_string_lossy().into_owned();
+    match hostname.is_empty() || !hostname.chars().all(|character| character.is_ascii_graphic()) {
+        true => "-".to_string(),
+        false => hostname
+    }
+}
+
+#[cfg(not(unix))]
+fn local_hostname() -> String {
+    "-".to_string()
+}
+
+/**
+ * Build a RFC 5424 syslog message, without structured data.
+ */
+fn format_syslog_message(severity: u8, timestamp: u64, hostname: &str, message_id: &str, message: &str) -> String {
+
+    format!(
+        "<{}>1 {} {} arp-scan {} {} - {}",
+        SYSLOG_FACILITY * 8 + severity,
+        time::format_rfc3339(timestamp),
+        hostname,
+        process::id(),
+        message_id,
+        message
+    )
+}
+
+/**
+ * Syslog messages of a scan: a summary message, followed by a message for
+ * each change event.
+ */
+fn build_syslog_messages(response_summary: &ResponseSummary, target_details: &[TargetDetails], changes: &[ChangeEvent]) -> Vec<String> {
+
+    let hostname = local_hostname();
+    let timestamp = response_summary.start_timestamp;
+
+    let summary_text = format!(
+        "scan finished, {} host(s) found in {}ms, {} packet(s) received, {} change(s)",
+        target_details.len(),
+        response_summary.duration_ms,
+        response_summary.packet_count,
+        changes.len()
+    );
+    let mut messages = vec![format_syslog_message(SEVERITY_INFO, timestamp, &hostname, "scan_finished", &summary_text)];
+
+    for change in changes {
+        let severity = match change.kind {
+            ChangeKind::BaselineViolation => SEVERITY_WARNING,
+            ChangeKind::NewHost | ChangeKind::MissingHost => SEVERITY_NOTICE
+        };
+        messages.push(format_syslog_message(severity, timestamp, &hostname, change.kind.name(), &change.describe()));
+    }
+    messages
+}
+
+fn resolve_address(host: &str, port: u16) -> io::Result<std::net::SocketAddr> {
+
+    (host, port).to_socket_addrs()?.next().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("no address found for {}", host))
+    })
+}
+
+fn send_syslog(transport: &SyslogTransport, messages: &[String], timeout: Duration) -> io::Result<()> {
+
+    match transport {
+        SyslogTransport::Udp(host, port) => {
+            let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+            socket.set_write_timeout(Some(timeout))?;
+            socket.connect(resolve_address(host, *port)?)?;
+            for message in messages {
+                socket.send(message.as_bytes())?;
+            }
+        },
+        SyslogTransport::Tcp(host, port) => {
+            // Messages are framed with their length (RFC 6587 octet counting)
+            let mut stream = TcpStream::connect_timeout(&resolve_address(host, *port)?, timeout)?;
+            stream.set_write_timeout(Some(timeout))?;
+            let framed_messages: String = messages.iter().map(|message| format!("{} {}", message.len(), message)).collect();
+            stream.write_all(framed_messages.as_bytes())?;
+            stream.flush()?;
+        },
+        #[cfg(unix)]
+        SyslogTransport::Unix(path) => {
+            let socket = UnixDatagram::unbound()?;
+            socket.set_write_timeout(Some(timeout))?;
+            socket.connect(path)?;
+            for message in messages {
+                socket.send(message.as_bytes())?;
+            }
+        },
+        #[cfg(not(unix))]
+        SyslogTransport::Unix(_) => {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "unix sockets are not available on this platform"));
+        }
+    }
+    Ok(())
+}
+
+/**
+ * POST the JSON results to the webhook, any 2xx status is a success.
+ */
+fn send_webhook(host: &str, port: u16, path: &str, json_body: &str, timeout: Duration) -> io::Result<()> {
+
+    let mut stream = TcpStream::connect_timeout(&resolve_address(host, port)?, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}:{}\r\nUser-Agent: arp-scan/{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path,
+        host,
+        port,
+        env!("CARGO_PKG_VERSION"),
+        json_body.len(),
+        json_body
+    );
+    stream.write_all(request.as_bytes())?;
+    stream.flush()?;
+
+    let mut status_line = String::new();
+    BufReader::new(&stream).read_line(&mut status_line)?;
+    let status_code = status_line.split_whitespace().nth(1).unwrap_or("");
+    match status_code.starts_with('2') && status_code.len() == 3 {
+        true => Ok(()),
+        false => Err(io::Error::other(format!("unexpected HTTP status '{}'", status_line.trim_end())))
+    }
+}
+
+/**
+ * Run the command hook, with the JSON results on stdin and the change events
+ * in environment variables. The command is killed once the timeout is over.
+ */
+fn run_command(program: &str, json_body: &str, host_count: usize, changes: &[ChangeEvent], timeout: Duration) -> io::Result<()> {
+
+    let change_lines = changes.iter().map(|change| change.describe()).collect::<Vec<String>>().join("\n");
+
+    let mut child = Command::new(program)
+        .env("ARP_SCAN_HOST_COUNT", host_count.to_string())
+        .env("ARP_SCAN_CHANGE_COUNT", changes.len().to_string())
+        .env("ARP_SCAN_CHANGES", change_lines)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+
+    // The results are written from another thread, a command ignoring its
+    // standard input must not block the timeout.
+    if let Some(mut stdin) = child.stdin.take() {
+        let json_input = json_body.to_string();
+        thread::spawn(move || {
+            let _ = stdin.write_all(json_input.as_bytes());
+        });
+    }
+
+    let start_time = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return match status.success() {
+                true => Ok(()),
+                false => Err(io::Error::other(format!("command ended with {}", status)))
+            };
+        }
+        if start_time.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "command timed out"));
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/**
+ * Sends scan results and change events to the notification sinks. Hosts of
+ * the previous scan are kept to detect changes between periodic scans.
+ */
+pub struct Notifier {
+    sinks: Vec<NotificationSink>,
+    retry_count: u32,
+    timeout: Duration,
+    previous_hosts: Option<HashMap<MacAddr, Ipv4Addr>>
+}
+
+impl Notifier {
+
+    pub fn new(options: &ScanOptions) -> Option<Self> {
+
+        if options.notify_sinks.is_empty() {
+            return None;
+        }
+
+        Some(Notifier {
+            sinks: options.notify_sinks.clone(),
+            retry_count: options.notify_retries,
+            timeout: Duration::from_millis(options.notify_timeout_ms),
+            previous_hosts: None
+        })
+    }
+
+    /**
+     * Notify all sinks about a completed scan. A failing sink is retried and
+     * then reported as a warning, the other sinks are still notified.
+     */
+    pub fn notify_scan(&mut self, response_summary: &ResponseSummary, target_details: &[TargetDetails], inventory_checks: Option<&[InventoryCheck]>, context: &ScanContext, options: &ScanOptions) {
+
+        let changes = detect_changes(self.previous_hosts.as_ref(), target_details);
+        self.previous_hosts = Some(target_details.iter().map(|detail| (detail.mac, detail.ipv4)).collect());
+
+        let json_body = export::export_to_json(response_summary, target_details, inventory_checks, context, options);
+        let syslog_messages = build_syslog_messages(response_summary, target_details, &changes);
+
+        for sink in &self.sinks {
+
+            let mut attempt = 0;
+            loop {
+                let send_result = match sink {
+                    NotificationSink::Webhook { host, port, path } => send_webhook(host, *port, path, &json_body, self.timeout),
+                    NotificationSink::Syslog(transport) => send_syslog(transport, &syslog_messages, self.timeout),
+                    NotificationSink::Command(program) => run_command(program, &json_body, target_details.len(), &changes, self.timeout)
+                };
+
+                match send_result {
+                    Ok(()) => break,
+                    Err(err) if attempt < self.retry_count => {
+                        eprintln!("[warn] Could not notify {} ({}), retrying", sink.describe(), err);
+                        attempt += 1;
+                        thread::sleep(RETRY_DELAY);
+                    },
+                    Err(err) => {
+                        eprintln!("[warn] Could not notify {} after {} attempt(s) ({})", sink.describe(), attempt + 1, err);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    use crate::baseline::{BaselineViolation, Severity, ViolationKind};
+
+    fn build_target(ipv4: Ipv4Addr, mac: &str) -> TargetDetails {
+        TargetDetails::build(ipv4, mac.parse().unwrap())
+    }
+
+    fn build_summary() -> ResponseSummary {
+        ResponseSummary {
+            packet_count: 6,
+            arp_count: 2,
+            start_timestamp: 1_700_000_000,
+            duration_ms: 1500,
+            target_count: 256,
+            conflict_count: 0,
+            request_count: 256,
+            request_rate: None
+        }
+    }
+
+    #[test]
+    fn should_parse_notification_sinks() {
+
+        assert_eq!(parse_sink("webhook:http://10.0.0.5:8080/hooks/scan"), Ok(NotificationSink::Webhook { host: "10.0.0.5".to_string(), port: 8080, path: "/hooks/scan".to_string() }));
+        assert_eq!(parse_sink("webhook:http://hooks.lab"), Ok(NotificationSink::Webhook { host: "hooks.lab".to_string(), port: 80, path: "/".to_string() }));
+        assert_eq!(parse_sink("syslog:udp://logs.lab"), Ok(NotificationSink::Syslog(SyslogTransport::Udp("logs.lab".to_string(), 514))));
+        assert_eq!(parse_sink("syslog:tcp://logs.lab:1514"), Ok(NotificationSink::Syslog(SyslogTransport::Tcp("logs.lab".to_string(), 1514))));
+        assert_eq!(parse_sink("syslog:unix:///dev/log"), Ok(NotificationSink::Syslog(SyslogTransport::Unix("/dev/log".to_string()))));
+        assert_eq!(parse_sink("exec:./on-scan.sh"), Ok(NotificationSink::Command("./on-scan.sh".to_string())));
+
+        assert!(parse_sink("webhook:https://hooks.lab").is_err());
+        assert!(parse_sink("syslog:logs.lab").is_err());
+        assert!(parse_sink("syslog:udp://logs.lab:port").is_err());
+        assert!(parse_sink("email:admin@lab").is_err());
+        assert!(parse_sink("exec:").is_err());
+    }
+
+    #[test]
+    fn should_detect_host_changes() {
+
+        let previous_hosts: HashMap<MacAddr, Ipv4Addr> = [
+            ("00:11:22:33:44:55".parse().unwrap(), Ipv4Addr::new(10, 20, 0, 2)),
+            ("b8:27:eb:01:02:03".parse().unwrap(), Ipv4Addr::new(10, 20, 0, 12))
+        ].into_iter().collect();
+
+        let mut new_target = build_target(Ipv4Addr::new(10, 20, 0, 30), "aa:bb:cc:dd:ee:ff");
+        new_target.violations = vec![BaselineViolation { kind: ViolationKind::UnknownMac, severity: Severity::High }];
+        let target_details = vec![build_target(Ipv4Addr::new(10, 20, 0, 2), "00:11:22:33:44:55"), new_target];
+
+        assert_eq!(detect_changes(None, &target_details).len(), 1);
+
+        let changes = detect_changes(Some(&previous_hosts), &target_details);
+        let descriptions: Vec<String> = changes.iter().map(|change| change.describe()).collect();
+        assert_eq!(descriptions, vec![
+            "new_host 10.20.0.30 aa:bb:cc:dd:ee:ff",
+            "missing_host 10.20.0.12 b8:27:eb:01:02:03",
+            "baseline_violation 10.20.0.30 aa:bb:cc:dd:ee:ff unknown_mac/high"
+        ]);
+    }
+
+    #[test]
+    fn should_format_syslog_messages() {
+
+        let message = format_syslog_message(SEVERITY_NOTICE, 1_700_000_000, "scanner", "new_host", "new_host 10.20.0.30 aa:bb:cc:dd:ee:ff");
+        assert_eq!(message, format!("<13>1 2023-11-14T22:13:20Z scanner arp-scan {} new_host - new_host 10.20.0.30 aa:bb:cc:dd:ee:ff", process::id()));
+    }
+
+    #[test]
+    fn should_post_results_to_webhook() {
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let json_body = r#"{"schema_version":2,"results":[{"ipv4":"10.20.0.2","mac":"00:11:22:33:44:55"}]}"#.to_string();
+
+        let body_size = json_body.len();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request: Vec<u8> = vec![];
+            let mut buffer = [0; 1024];
+            while !String::from_utf8_lossy(&request).split_once("\r\n\r\n").is_some_and(|(_, body)| body.len() >= body_size) {
+                let read_size = stream.read(&mut buffer).unwrap();
+                assert!(read_size > 0);
+                request.extend_from_slice(&buffer[..read_size]);
+            }
+            stream.write_all(b"HTTP/1.1 204 No Content\r\n\r\n").unwrap();
+            String::from_utf8_lossy(&request).into_owned()
+        });
+
+        send_webhook("127.0.0.1", port, "/scans", &json_body, Duration::from_secs(2)).unwrap();
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /scans HTTP/1.1\r\n"));
+        assert!(request.ends_with(&json_body));
+    }
+
+    #[test]
+    fn should_send_syslog_over_udp() {
+
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let port = server.local_addr().unwrap().port();
+
+        let messages = build_syslog_messages(&build_summary(), &[], &[]);
+        send_syslog(&SyslogTransport::Udp("127.0.0.1".to_string(), port), &messages, Duration::from_secs(2)).unwrap();
+
+        let mut datagram = [0; 1024];
+        let datagram_size = server.recv(&mut datagram).unwrap();
+        let message = String::from_utf8_lossy(&datagram[..datagram_size]);
+        assert!(message.starts_with("<14>1 2023-11-14T22:13:20Z "));
+        assert!(message.ends_with("scan_finished - scan finished, 0 host(s) found in 1500ms, 6 packet(s) received, 0 change(s)"));
+    }
+
+}
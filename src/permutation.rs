@@ -0,0 +1,137 @@
+// Number of Feistel rounds applied on each index. Four rounds are enough to
+// get a well-mixed order, the goal here is to spread ARP requests across the
+// whole range (not to build a cryptographic primitive).
+const FEISTEL_ROUNDS: u64 = 4;
+
+/**
+ * A memory-bounded permutation over the [0, size) integer range. Each index is
+ * mapped to a unique position with a balanced Feistel network, which means
+ * that huge ranges (a /8 network for example) can be shuffled without storing
+ * any address. The same seed will always produce the same order.
+ */
+pub struct IndexPermutation {
+    size: u64,
+    half_bits: u32,
+    half_mask: u64,
+    seed: u64
+}
+
+impl IndexPermutation {
+
+    pub fn new(size: u64, seed: u64) -> IndexPermutation {
+
+        // The Feistel network works on an even number of bits, large enough to
+        // hold all indexes. Values outside the range will be skipped with a
+        // 'cycle walking' approach (see the 'permute' method).
+        let required_bits = match size {
+            0 | 1 => 2,
+            _ => 64 - (size - 1).leading_zeros()
+        };
+        let half_bits = required_bits.div_ceil(2);
+        let half_mask = match half_bits {
+            32 => u64::from(u32::MAX),
+            _ => (1u64 << half_bits) - 1
+        };
+
+        IndexPermutation {
+            size,
+            half_bits,
+            half_mask,
+            seed
+        }
+    }
+
+    /**
+     * Find the permuted position of a given index. The index must be lower
+     * than the permutation size, otherwise the returned value is meaningless.
+     */
+    pub fn permute(&self, index: u64) -> u64 {
+
+        // Since the Feistel domain is at most 4 times bigger than the range,
+        // cycle walking will only take a few iterations on average. Each value
+        // of the range is still reached exactly once.
+        let mut value = self.encrypt(index);
+        while value >= self.size {
+            value = self.encrypt(value);
+        }
+        value
+    }
+
+    fn encrypt(&self, value: u64) -> u64 {
+
+        let mut left = (value >> self.half_bits) & self.half_mask;
+        let mut right = value & self.half_mask;
+
+        for round in 0..FEISTEL_ROUNDS {
+            let next_right = left ^ (self.round_function(round, right) & self.half_mask);
+            left = right;
+            right = next_right;
+        }
+
+        (left << self.half_bits) | right
+    }
+
+    fn round_function(&self, round: u64, value: u64) -> u64 {
+
+        // SplitMix64 finalizer, fed with the seed, the round and the half block
+        let mut hash = self.seed ^ round.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ value;
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        hash ^ (hash >> 31)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn collect_permutation(size: u64, seed: u64) -> Vec<u64> {
+
+        let permutation = IndexPermutation::new(size, seed);
+        (0..size).map(|index| permutation.permute(index)).collect()
+    }
+
+    #[test]
+    fn should_cover_every_index_once() {
+
+        for size in [1, 2, 3, 7, 256, 1000, 4099] {
+
+            let mut values = collect_permutation(size, 42);
+            values.sort_unstable();
+
+            assert_eq!(values, (0..size).collect::<Vec<u64>>());
+        }
+    }
+
+    #[test]
+    fn should_be_reproducible_with_seed() {
+
+        assert_eq!(collect_permutation(1024, 7), collect_permutation(1024, 7));
+    }
+
+    #[test]
+    fn should_change_order_with_seed() {
+
+        assert_ne!(collect_permutation(1024, 7), collect_permutation(1024, 8));
+    }
+
+    #[test]
+    fn should_not_keep_sequential_order() {
+
+        let values = collect_permutation(65536, 1);
+        let sequential_steps = values.windows(2).filter(|pair| pair[1] == pair[0] + 1).count();
+
+        assert!(sequential_steps < 100);
+    }
+
+    #[test]
+    fn should_handle_full_ipv4_range() {
+
+        let permutation = IndexPermutation::new(1 << 32, 99);
+
+        assert!((0..1000).all(|index| permutation.permute(index) < (1 << 32)));
+    }
+
+}
@@ -0,0 +1,156 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpStream};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::args::ScanOptions;
+use crate::network::TargetDetails;
+use crate::progress::ProgressCounter;
+use crate::rate::RateLimiter;
+
+// Connection attempts mostly wait for answers, many can run at the same time
+// while the rate limiter keeps the pace of outgoing SYN packets.
+const PORT_WORKERS: usize = 64;
+
+/**
+ * Check if a TCP port accepts connections. Refused, unreachable or silent
+ * ports (filtered by a firewall) are all considered as closed.
+ */
+fn is_port_open(socket_addr: SocketAddrV4, timeout: Duration) -> bool {
+    TcpStream::connect_timeout(&SocketAddr::V4(socket_addr), timeout).is_ok()
+}
+
+/**
+ * Probe a list of TCP sockets with a pool of workers. The rate limiter is
+ * shared by all workers, each connection attempt takes one token. The open
+ * sockets are returned.
+ */
+fn probe_sockets<P>(sockets: Vec<SocketAddrV4>, timeout: Duration, rate_limiter: RateLimiter, mut on_progress: P) -> Vec<SocketAddrV4>
+where
+    P: FnMut(usize, usize)
+{
+    let total_count = sockets.len();
+    let worker_count = PORT_WORKERS.clamp(1, total_count.max(1));
+    let pending_queue = Arc::new(Mutex::new(VecDeque::from(sockets)));
+    let rate_limiter = Arc::new(Mutex::new(rate_limiter));
+    let (result_tx, result_rx) = mpsc::channel::<(SocketAddrV4, bool)>();
+
+    for _ in 0..worker_count {
+
+        let pending_queue = Arc::clone(&pending_queue);
+        let rate_limiter = Arc::clone(&rate_limiter);
+        let result_tx = result_tx.clone();
+
+        thread::spawn(move || loop {
+
+            let next_socket = pending_queue.lock().ok().and_then(|mut queue| queue.pop_front());
+            let socket_addr = match next_socket {
+                Some(socket_addr) => socket_addr,
+                None => break
+            };
+
+            if let Ok(mut rate_limiter) = rate_limiter.lock() {
+                rate_limiter.acquire();
+            }
+
+            if result_tx.send((socket_addr, is_port_open(socket_addr, timeout))).is_err() {
+                break;
+            }
+        });
+    }
+    drop(result_tx);
+
+    let mut probed_count = 0;
+    let mut open_sockets: Vec<SocketAddrV4> = vec![];
+    on_progress(probed_count, total_count);
+
+    for (socket_addr, is_open) in result_rx.iter() {
+
+        if is_open {
+            open_sockets.push(socket_addr);
+        }
+        probed_count += 1;
+        on_progress(probed_count, total_count);
+    }
+
+    open_sockets
+}
+
+/**
+ * Probe the requested TCP ports on all discovered hosts, with a full TCP
+ * connection (no raw socket is needed, privileges may have been dropped).
+ * Connection attempts follow the packet rate of the ARP scan.
+ */
+pub fn probe_target_ports(target_details: &mut [TargetDetails], options: &ScanOptions, packet_rate: Option<f64>) {
+
+    if options.probe_ports.is_empty() || target_details.is_empty() {
+        return;
+    }
+
+    let sockets: Vec<SocketAddrV4> = target_details.iter()
+        .flat_map(|detail| options.probe_ports.iter().map(|port| SocketAddrV4::new(detail.ipv4, *port)))
+        .collect();
+
+    let timeout = Duration::from_millis(options.port_timeout_ms);
+    let rate_limiter = RateLimiter::new(packet_rate, options.burst_size);
+    let progress = ProgressCounter::new("Probing TCP ports", options);
+
+    let open_sockets = probe_sockets(sockets, timeout, rate_limiter, |probed_count, total_count| {
+        progress.update(probed_count, total_count);
+    });
+    progress.finish();
+
+    let mut open_ports: HashMap<Ipv4Addr, Vec<u16>> = HashMap::new();
+    for socket_addr in open_sockets {
+        open_ports.entry(*socket_addr.ip()).or_default().push(socket_addr.port());
+    }
+
+    for detail in target_details.iter_mut() {
+        detail.open_ports = open_ports.remove(&detail.ipv4).unwrap_or_default();
+        detail.open_ports.sort_unstable();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use std::net::TcpListener;
+
+    fn find_closed_port() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    }
+
+    #[test]
+    fn should_find_open_ports() {
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let open_port = listener.local_addr().unwrap().port();
+        let closed_port = find_closed_port();
+
+        let open_socket = SocketAddrV4::new(Ipv4Addr::LOCALHOST, open_port);
+        let closed_socket = SocketAddrV4::new(Ipv4Addr::LOCALHOST, closed_port);
+
+        let open_sockets = probe_sockets(vec![open_socket, closed_socket], Duration::from_millis(500), RateLimiter::new(None, 1), |_, _| {});
+
+        assert_eq!(open_sockets, vec![open_socket]);
+    }
+
+    #[test]
+    fn should_report_probe_progress() {
+
+        let sockets: Vec<SocketAddrV4> = (0..5).map(|_| SocketAddrV4::new(Ipv4Addr::LOCALHOST, find_closed_port())).collect();
+
+        let mut progress_updates: Vec<(usize, usize)> = vec![];
+        probe_sockets(sockets, Duration::from_millis(500), RateLimiter::new(Some(1000.0), 1), |probed_count, total_count| {
+            progress_updates.push((probed_count, total_count));
+        });
+
+        assert_eq!(progress_updates.first(), Some(&(0, 5)));
+        assert_eq!(progress_updates.last(), Some(&(5, 5)));
+    }
+
+}
@@ -0,0 +1,312 @@
+#[cfg(unix)]
+use std::ffi::CString;
+#[cfg(target_os = "linux")]
+use std::env;
+#[cfg(target_os = "linux")]
+use std::fs;
+#[cfg(unix)]
+use std::io;
+
+// Linux capability numbers, as defined in 'linux/capability.h'
+pub const CAP_NET_ADMIN: u32 = 12;
+pub const CAP_NET_RAW: u32 = 13;
+
+/**
+ * Find a capability set (CapEff, CapPrm, ...) in the content of a process
+ * status file. Each capability set is an hexadecimal bit mask.
+ */
+pub fn parse_capability_mask(process_status: &str, set_name: &str) -> Option<u64> {
+
+    process_status.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim() == set_name)
+        .and_then(|(_, mask_text)| u64::from_str_radix(mask_text.trim(), 16).ok())
+}
+
+pub fn has_capability(capability_mask: u64, capability: u32) -> bool {
+    capability_mask & (1u64 << capability) != 0
+}
+
+/**
+ * Ensure the current process can open a raw datalink channel. On Linux, raw
+ * packet sockets require the CAP_NET_RAW capability in the effective set,
+ * which is granted to root by default or can be granted to the binary itself.
+ * The promiscuous mode (packet socket membership) does not need CAP_NET_ADMIN.
+ */
+#[cfg(target_os = "linux")]
+pub fn check_scan_privileges() -> Result<(), String> {
+
+    let process_status = fs::read_to_string("/proc/self/status").map_err(|err| {
+        format!("Could not read process capabilities ({})", err)
+    })?;
+    let effective_mask = parse_capability_mask(&process_status, "CapEff").ok_or_else(|| {
+        "Could not find effective capabilities of the process".to_string()
+    })?;
+
+    if has_capability(effective_mask, CAP_NET_RAW) {
+        return Ok(());
+    }
+
+    let binary_path = env::current_exe()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|_| "arp-scan".to_string());
+
+    // Running as root without CAP_NET_RAW usually means that the capability
+    // has been removed from the bounding set (containers, systemd units, ...)
+    let is_root = unsafe { libc::geteuid() } == 0;
+    match is_root {
+        true => Err([
+            "Missing CAP_NET_RAW capability to send ARP packets, even if running as root".to_string(),
+            "The capability may have been dropped by a container or a service manager".to_string(),
+            "Grant it back, for example with 'docker run --cap-add=NET_RAW ...'".to_string()
+        ].join("\n")),
+        false => Err([
+            "Missing CAP_NET_RAW capability to send ARP packets".to_string(),
+            "Run this binary as root, or grant the capability to the binary with:".to_string(),
+            format!("    sudo setcap cap_net_raw+ep {}", binary_path)
+        ].join("\n"))
+    }
+}
+
+/**
+ * Describe the network capabilities of the current process, this is used to
+ * check the privileges without starting a scan (dry-run mode).
+ */
+#[cfg(target_os = "linux")]
+pub fn describe_privileges() -> String {
+
+    let effective_mask = fs::read_to_string("/proc/self/status").ok()
+        .and_then(|process_status| parse_capability_mask(&process_status, "CapEff"))
+        .unwrap_or(0);
+
+    let capabilities = [(CAP_NET_RAW, "CAP_NET_RAW"), (CAP_NET_ADMIN, "CAP_NET_ADMIN")];
+    let capability_list = capabilities.iter()
+        .map(|(capability, name)| match has_capability(effective_mask, *capability) {
+            true => format!("{} granted", name),
+            false => format!("{} missing", name)
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    match has_capability(effective_mask, CAP_NET_RAW) {
+        true => format!("{} (scan allowed)", capability_list),
+        false => format!("{} (scan not allowed)", capability_list)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn describe_privileges() -> String {
+    match check_scan_privileges() {
+        Ok(_) => "scan allowed".to_string(),
+        Err(_) => "root user required".to_string()
+    }
+}
+
+/**
+ * Other UNIX systems (BSD, macOS, ...) do not expose capabilities, the process
+ * should run with an effective root user to open BPF devices.
+ */
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn check_scan_privileges() -> Result<(), String> {
+
+    match unsafe { libc::geteuid() } {
+        0 => Ok(()),
+        _ => Err("Should run this binary as root or use --help for options".to_string())
+    }
+}
+
+#[cfg(not(unix))]
+pub fn check_scan_privileges() -> Result<(), String> {
+    Ok(())
+}
+
+/**
+ * Find the user and group identifiers of the unprivileged account used once
+ * the datalink channel is opened. The group defaults to the user primary group.
+ */
+#[cfg(unix)]
+fn find_account_ids(user_name: &str, group_name: Option<&str>) -> Result<(libc::uid_t, libc::gid_t), String> {
+
+    let user_text = CString::new(user_name).map_err(|_| format!("Invalid user name {}", user_name))?;
+    // SAFETY: the name is a valid C string, the returned entry is only read
+    // before any other account lookup (no thread is running yet).
+    let user_entry = unsafe { libc::getpwnam(user_text.as_ptr()) };
+    if user_entry.is_null() {
+        return Err(format!("Could not find user {} to drop privileges", user_name));
+    }
+    let (user_id, primary_group_id) = unsafe { ((*user_entry).pw_uid, (*user_entry).pw_gid) };
+
+    let group_id = match group_name {
+        Some(group_name) => {
+
+            let group_text = CString::new(group_name).map_err(|_| format!("Invalid group name {}", group_name))?;
+            // SAFETY: same as getpwnam, the entry is read right away.
+            let group_entry = unsafe { libc::getgrnam(group_text.as_ptr()) };
+            if group_entry.is_null() {
+                return Err(format!("Could not find group {} to drop privileges", group_name));
+            }
+            unsafe { (*group_entry).gr_gid }
+        },
+        None => primary_group_id
+    };
+
+    Ok((user_id, group_id))
+}
+
+/**
+ * Drop all privileges of the current process once the datalink channel is
+ * opened. A root process switches to the given unprivileged user and group,
+ * then all capabilities are removed (this also applies to binaries granted
+ * CAP_NET_RAW with setcap). This must be called before spawning threads,
+ * since capabilities are tracked per thread on Linux.
+ */
+#[cfg(unix)]
+pub fn drop_privileges(user_name: &str, group_name: Option<&str>) -> Result<(), String> {
+
+    let os_error = |action: &str| format!("Could not {} ({})", action, io::Error::last_os_error());
+
+    // Executed programs should never get new privileges (setuid binaries or
+    // file capabilities), this is also required to install a seccomp filter.
+    // SAFETY: PR_SET_NO_NEW_PRIVS only reads its integer arguments.
+    #[cfg(target_os = "linux")]
+    if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+        return Err(os_error("forbid new privileges"));
+    }
+
+    if unsafe { libc::geteuid() } == 0 {
+
+        let (user_id, group_id) = find_account_ids(user_name, group_name)?;
+
+        // The bounding set can only be reduced with CAP_SETPCAP (granted to
+        // root), unknown capabilities are simply ignored by the kernel.
+        // SAFETY: PR_CAPBSET_DROP only reads its integer arguments.
+        #[cfg(target_os = "linux")]
+        for capability in 0..64 {
+            unsafe { libc::prctl(libc::PR_CAPBSET_DROP, capability, 0, 0, 0) };
+        }
+
+        // SAFETY: an empty group list is given, the null pointer is never read.
+        if unsafe { libc::setgroups(0, std::ptr::null()) } != 0 {
+            return Err(os_error("clear supplementary groups"));
+        }
+        // SAFETY: setgid and setuid only take an identifier and do not touch
+        // memory. No thread has been spawned yet, the whole process is changed.
+        if unsafe { libc::setgid(group_id) } != 0 {
+            return Err(os_error("change group"));
+        }
+        if unsafe { libc::setuid(user_id) } != 0 {
+            return Err(os_error("change user"));
+        }
+
+        // Going back to root must fail once the user has been changed
+        // SAFETY: same as above, setuid only takes an identifier.
+        if user_id != 0 && unsafe { libc::setuid(0) } == 0 {
+            return Err("Could not drop root privileges permanently".to_string());
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    clear_capabilities()?;
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn drop_privileges(_user_name: &str, _group_name: Option<&str>) -> Result<(), String> {
+    Ok(())
+}
+
+/**
+ * Clear the effective, permitted, inheritable and ambient capability sets of
+ * the current thread, and verify that no capability is left.
+ */
+#[cfg(target_os = "linux")]
+fn clear_capabilities() -> Result<(), String> {
+
+    #[repr(C)]
+    struct CapabilityHeader {
+        version: u32,
+        pid: libc::c_int
+    }
+
+    #[repr(C)]
+    struct CapabilityData {
+        effective: u32,
+        permitted: u32,
+        inheritable: u32
+    }
+
+    // Version 3 of the capability API uses two data structs (64 bits)
+    const LINUX_CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+
+    let mut header = CapabilityHeader {
+        version: LINUX_CAPABILITY_VERSION_3,
+        pid: 0
+    };
+    let empty_data = [
+        CapabilityData { effective: 0, permitted: 0, inheritable: 0 },
+        CapabilityData { effective: 0, permitted: 0, inheritable: 0 }
+    ];
+
+    // SAFETY: PR_CAP_AMBIENT_CLEAR_ALL only reads its integer arguments.
+    unsafe { libc::prctl(libc::PR_CAP_AMBIENT, libc::PR_CAP_AMBIENT_CLEAR_ALL, 0, 0, 0) };
+
+    // SAFETY: the header and the two data structs match the layout expected by
+    // capset for version 3, both live until the end of the call and capset
+    // only reads the data structs.
+    if unsafe { libc::syscall(libc::SYS_capset, &mut header as *mut CapabilityHeader, empty_data.as_ptr()) } != 0 {
+        return Err(format!("Could not clear capabilities ({})", io::Error::last_os_error()));
+    }
+
+    let process_status = fs::read_to_string("/proc/thread-self/status").map_err(|err| {
+        format!("Could not verify process capabilities ({})", err)
+    })?;
+    let remaining_mask = ["CapEff", "CapPrm", "CapInh", "CapAmb"].iter()
+        .map(|set_name| parse_capability_mask(&process_status, set_name).unwrap_or(u64::MAX))
+        .fold(0, |mask, set_mask| mask | set_mask);
+
+    match remaining_mask {
+        0 => Ok(()),
+        _ => Err("Could not clear all capabilities".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const PROCESS_STATUS: &str = "Name:\tarp-scan
+Uid:\t1000\t1000\t1000\t1000
+CapInh:\t0000000000000000
+CapPrm:\t0000000000003000
+CapEff:\t0000000000002000
+CapBnd:\t000001ffffffffff
+CapAmb:\t0000000000000000
+";
+
+    #[test]
+    fn should_parse_capability_masks() {
+
+        assert_eq!(parse_capability_mask(PROCESS_STATUS, "CapEff"), Some(0x2000));
+        assert_eq!(parse_capability_mask(PROCESS_STATUS, "CapPrm"), Some(0x3000));
+        assert_eq!(parse_capability_mask(PROCESS_STATUS, "CapBnd"), Some(0x01ff_ffff_ffff));
+    }
+
+    #[test]
+    fn should_handle_missing_capability_mask() {
+
+        assert_eq!(parse_capability_mask(PROCESS_STATUS, "CapUnknown"), None);
+        assert_eq!(parse_capability_mask("CapEff:\tnot-hex", "CapEff"), None);
+    }
+
+    #[test]
+    fn should_detect_capabilities() {
+
+        let effective_mask = parse_capability_mask(PROCESS_STATUS, "CapEff").unwrap();
+
+        assert!(has_capability(effective_mask, CAP_NET_RAW));
+        assert!(!has_capability(effective_mask, CAP_NET_ADMIN));
+    }
+
+}
@@ -0,0 +1,234 @@
+use std::collections::HashSet;
+use std::io::{self, IsTerminal, Write};
+use std::net::Ipv4Addr;
+use std::time::{Duration, Instant};
+
+use crate::args::ScanOptions;
+use crate::network::ScanEstimation;
+use crate::rate::RateLimiter;
+use crate::time;
+use crate::utils;
+
+const BAR_WIDTH: usize = 24;
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/**
+ * A progress line on stderr, updated while the ARP requests are sent and
+ * while the scan waits for the last replies. The progress is only shown on
+ * a terminal, since the line is redrawn in place.
+ */
+pub struct ProgressBar {
+    total_count: u64,
+    round_count: usize,
+    request_phase_ms: u128,
+    timeout_ms: u128,
+    target_rate: Option<f64>,
+    start_time: Instant,
+    wait_start: Option<Instant>,
+    last_draw: Option<Instant>,
+    found_hosts: HashSet<Ipv4Addr>
+}
+
+impl ProgressBar {
+
+    /**
+     * Create the progress bar if it can be displayed (see 'can_show_progress').
+     */
+    pub fn new(estimations: &ScanEstimation, options: &ScanOptions) -> Option<Self> {
+
+        if !can_show_progress(options) {
+            return None;
+        }
+
+        // The scan estimation includes the final timeout, only the request
+        // phase is corrected with the observed progress.
+        let timeout_ms = u128::from(options.timeout_ms);
+
+        Some(ProgressBar {
+            total_count: u64::try_from(estimations.request_count).unwrap_or(u64::MAX),
+            round_count: options.retry_count,
+            request_phase_ms: estimations.duration_ms.saturating_sub(timeout_ms),
+            timeout_ms,
+            target_rate: estimations.packet_rate,
+            start_time: Instant::now(),
+            wait_start: None,
+            last_draw: None,
+            found_hosts: HashSet::new()
+        })
+    }
+
+    pub fn record_host(&mut self, ipv4: Ipv4Addr) {
+        self.found_hosts.insert(ipv4);
+    }
+
+    /**
+     * Redraw the progress line, at most every 100ms to keep the terminal
+     * output cheap compared to the request sending.
+     */
+    pub fn update(&mut self, rate_limiter: &RateLimiter, retry_round: usize) {
+
+        if self.last_draw.is_some_and(|last_draw| last_draw.elapsed() < REDRAW_INTERVAL) {
+            return;
+        }
+        self.last_draw = Some(Instant::now());
+
+        let sent_count = rate_limiter.request_count().min(self.total_count);
+        let eta_ms = match self.wait_start {
+            Some(wait_start) => self.timeout_ms.saturating_sub(wait_start.elapsed().as_millis()),
+            None => {
+                let elapsed_ms = self.start_time.elapsed().as_millis();
+                compute_eta_ms(self.request_phase_ms, elapsed_ms, sent_count, self.total_count) + self.timeout_ms
+            }
+        };
+
+        let achieved_text = match rate_limiter.achieved_rate() {
+            Some(achieved_rate) => format!("{:.0}", achieved_rate),
+            None => "-".to_string()
+        };
+
+        eprint!(
+            "\r\x1B[K{} {}/{} packets, round {}/{}, {} {}, {} of {}, ETA {}",
+            format_bar(sent_count, self.total_count),
+            sent_count,
+            self.total_count,
+            (retry_round + 1).min(self.round_count),
+            self.round_count,
+            self.found_hosts.len(),
+            match self.found_hosts.len() {
+                1 => "host",
+                _ => "hosts"
+            },
+            achieved_text,
+            utils::format_packet_rate(self.target_rate),
+            time::format_milliseconds(eta_ms)
+        );
+        let _ = io::stderr().flush();
+    }
+
+    /**
+     * All requests were sent, the remaining time is now the final timeout.
+     */
+    pub fn start_waiting(&mut self) {
+        self.wait_start = Some(Instant::now());
+        self.last_draw = None;
+    }
+
+    /**
+     * Clear the progress line, before the results are printed.
+     */
+    pub fn finish(&self) {
+        eprint!("\r\x1B[K");
+        let _ = io::stderr().flush();
+    }
+}
+
+/**
+ * A progress counter on stderr ('Resolving hostnames 3/12'), used by the scan
+ * phases following the capture. Like the progress bar, the counter is only
+ * displayed when the progress can be shown.
+ */
+pub struct ProgressCounter {
+    label: &'static str,
+    is_shown: bool
+}
+
+impl ProgressCounter {
+
+    pub fn new(label: &'static str, options: &ScanOptions) -> Self {
+        ProgressCounter {
+            label,
+            is_shown: can_show_progress(options)
+        }
+    }
+
+    pub fn update(&self, done_count: usize, total_count: usize) {
+
+        if self.is_shown {
+            eprint!("\r{} {}/{}", self.label, done_count, total_count);
+            let _ = io::stderr().flush();
+        }
+    }
+
+    /**
+     * Clear the progress line, before the results are printed.
+     */
+    pub fn finish(&self) {
+
+        if self.is_shown {
+            eprint!("\r\x1B[K");
+            let _ = io::stderr().flush();
+        }
+    }
+}
+
+/**
+ * Progress lines are only shown in verbose mode with stderr on a terminal,
+ * since they are redrawn in place. Streamed rows would be mixed with the
+ * progress line, the progress is therefore disabled in streaming mode.
+ */
+fn can_show_progress(options: &ScanOptions) -> bool {
+    options.is_verbose() && !options.stream_output && io::stderr().is_terminal()
+}
+
+fn format_bar(sent_count: u64, total_count: u64) -> String {
+
+    let filled_width = match total_count {
+        0 => BAR_WIDTH,
+        _ => ((sent_count as u128 * BAR_WIDTH as u128) / total_count as u128) as usize
+    };
+    format!("[{}{}]", "#".repeat(filled_width), "-".repeat(BAR_WIDTH - filled_width))
+}
+
+/**
+ * Compute the remaining time of the request phase. The initial estimation is
+ * corrected by the observed progress: if the first half of the requests took
+ * twice the planned time, the second half is expected to be twice as long.
+ */
+fn compute_eta_ms(estimated_ms: u128, elapsed_ms: u128, sent_count: u64, total_count: u64) -> u128 {
+
+    if sent_count == 0 || total_count == 0 || elapsed_ms == 0 {
+        return estimated_ms.saturating_sub(elapsed_ms);
+    }
+
+    let progress_ratio = sent_count as f64 / total_count as f64;
+    let planned_elapsed_ms = estimated_ms as f64 * progress_ratio;
+    let correction = match planned_elapsed_ms > 0.0 {
+        true => elapsed_ms as f64 / planned_elapsed_ms,
+        false => 1.0
+    };
+
+    ((estimated_ms as f64 - planned_elapsed_ms) * correction).max(0.0).round() as u128
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn should_use_estimation_before_first_request() {
+
+        assert_eq!(compute_eta_ms(10_000, 0, 0, 500), 10_000);
+        assert_eq!(compute_eta_ms(10_000, 12_000, 0, 500), 0);
+        assert_eq!(compute_eta_ms(10_000, 0, 1, 500), 10_000);
+    }
+
+    #[test]
+    fn should_correct_eta_with_observed_progress() {
+
+        // Half of the requests were sent in the planned time
+        assert_eq!(compute_eta_ms(10_000, 5_000, 250, 500), 5_000);
+        // Half of the requests took twice the planned time
+        assert_eq!(compute_eta_ms(10_000, 10_000, 250, 500), 10_000);
+        assert_eq!(compute_eta_ms(10_000, 10_000, 500, 500), 0);
+    }
+
+    #[test]
+    fn should_fill_progress_bar() {
+
+        assert_eq!(format_bar(0, 100), format!("[{}]", "-".repeat(BAR_WIDTH)));
+        assert_eq!(format_bar(50, 100), format!("[{}{}]", "#".repeat(BAR_WIDTH / 2), "-".repeat(BAR_WIDTH / 2)));
+        assert_eq!(format_bar(0, 0), format!("[{}]", "#".repeat(BAR_WIDTH)));
+    }
+
+}
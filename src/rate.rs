@@ -0,0 +1,250 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::args::ScanTiming;
+
+// The operating system sleep is not precise enough for sub-millisecond
+// pacing. The last part of each wait is therefore done with a busy loop.
+const SPIN_THRESHOLD: Duration = Duration::from_micros(200);
+
+/**
+ * Convert the scan timing requested by the user into a packet rate (packets
+ * per second). No rate is returned if the requests should be sent as fast as
+ * possible (zero interval).
+ */
+pub fn compute_packet_rate(scan_timing: &ScanTiming, packet_size: usize) -> Option<f64> {
+
+    match scan_timing {
+        ScanTiming::Interval(0) => None,
+        ScanTiming::Interval(interval_us) => Some(1_000_000.0 / (*interval_us as f64)),
+        ScanTiming::PacketRate(0) | ScanTiming::Bandwidth(0) => None,
+        ScanTiming::PacketRate(packets_second) => Some(*packets_second as f64),
+        ScanTiming::Bandwidth(bits_second) => Some((*bits_second as f64) / ((packet_size * 8) as f64))
+    }
+}
+
+/**
+ * A token bucket rate limiter for outgoing requests. Tokens are refilled at
+ * the requested packet rate and the bucket can hold up to 'burst_size' tokens,
+ * which allows short bursts of requests while keeping the average rate.
+ */
+pub struct RateLimiter {
+    packet_rate: Option<f64>,
+    burst_size: f64,
+    tokens: f64,
+    last_refill: Instant,
+    first_request: Option<Instant>,
+    last_request: Option<Instant>,
+    request_count: u64
+}
+
+impl RateLimiter {
+
+    pub fn new(packet_rate: Option<f64>, burst_size: u32) -> Self {
+
+        let burst_size = f64::from(burst_size.max(1));
+
+        RateLimiter {
+            packet_rate,
+            burst_size,
+            tokens: burst_size,
+            last_refill: Instant::now(),
+            first_request: None,
+            last_request: None,
+            request_count: 0
+        }
+    }
+
+    /**
+     * Block the current thread until a new request can be sent. The method
+     * consumes a token from the bucket and records the request.
+     */
+    pub fn acquire(&mut self) {
+
+        let wait_duration = self.take_token(Instant::now());
+        if !wait_duration.is_zero() {
+            wait_precisely(wait_duration);
+        }
+        self.record_request(Instant::now());
+    }
+
+    /**
+     * Consume a token at the given time and return how long the caller should
+     * wait before sending the request. A missing token is borrowed from the
+     * bucket, the refill time is then moved to the end of the wait.
+     */
+    fn take_token(&mut self, now: Instant) -> Duration {
+
+        let packet_rate = match self.packet_rate {
+            Some(packet_rate) => packet_rate,
+            None => return Duration::ZERO
+        };
+
+        let elapsed_seconds = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed_seconds * packet_rate).min(self.burst_size);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Duration::ZERO;
+        }
+
+        let wait_duration = Duration::from_secs_f64((1.0 - self.tokens) / packet_rate);
+        self.tokens = 0.0;
+        self.last_refill = now + wait_duration;
+        wait_duration
+    }
+
+    fn record_request(&mut self, now: Instant) {
+
+        self.first_request.get_or_insert(now);
+        self.last_request = Some(now);
+        self.request_count += 1;
+    }
+
+    pub fn request_count(&self) -> u64 {
+        self.request_count
+    }
+
+    /**
+     * Measure the packet rate achieved so far, based on the time elapsed
+     * between the first and the last request.
+     */
+    pub fn achieved_rate(&self) -> Option<f64> {
+
+        match (self.first_request, self.last_request) {
+            (Some(first_request), Some(last_request)) if self.request_count > 1 => {
+                let elapsed_seconds = last_request.duration_since(first_request).as_secs_f64();
+                match elapsed_seconds > 0.0 {
+                    true => Some(((self.request_count - 1) as f64) / elapsed_seconds),
+                    false => None
+                }
+            },
+            _ => None
+        }
+    }
+
+}
+
+/**
+ * Wait for the given duration with a microsecond precision. The thread will
+ * sleep for most of the duration and spin for the remaining time.
+ */
+fn wait_precisely(duration: Duration) {
+
+    let deadline = Instant::now() + duration;
+
+    if duration > SPIN_THRESHOLD {
+        thread::sleep(duration - SPIN_THRESHOLD);
+    }
+
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn should_convert_interval_to_rate() {
+
+        assert_eq!(compute_packet_rate(&ScanTiming::Interval(10_000), 42), Some(100.0));
+        assert_eq!(compute_packet_rate(&ScanTiming::Interval(250), 42), Some(4000.0));
+    }
+
+    #[test]
+    fn should_convert_bandwidth_to_rate() {
+
+        assert_eq!(compute_packet_rate(&ScanTiming::Bandwidth(3360), 42), Some(10.0));
+    }
+
+    #[test]
+    fn should_not_limit_zero_values() {
+
+        assert_eq!(compute_packet_rate(&ScanTiming::Interval(0), 42), None);
+        assert_eq!(compute_packet_rate(&ScanTiming::PacketRate(0), 42), None);
+        assert_eq!(compute_packet_rate(&ScanTiming::Bandwidth(0), 42), None);
+    }
+
+    /**
+     * Take tokens on a simulated clock, requests are sent as soon as allowed.
+     * The simulated time of each request is returned.
+     */
+    fn simulate_requests(limiter: &mut RateLimiter, start: Instant, request_count: usize) -> Vec<Instant> {
+
+        let mut now = start;
+        (0..request_count).map(|_| {
+            now += limiter.take_token(now);
+            limiter.record_request(now);
+            now
+        }).collect()
+    }
+
+    #[test]
+    fn should_pace_sub_millisecond_requests() {
+
+        let mut limiter = RateLimiter::new(Some(20_000.0), 1);
+        let start = limiter.last_refill;
+        let request_times = simulate_requests(&mut limiter, start, 1000);
+
+        // The first request uses the initial token, the next ones are sent
+        // every 50µs: 999 intervals in total
+        assert_eq!(request_times[0], start);
+        assert_eq!(request_times[1] - request_times[0], Duration::from_micros(50));
+        assert_eq!(request_times[999] - start, Duration::from_micros(49_950));
+        assert_eq!(limiter.request_count(), 1000);
+    }
+
+    #[test]
+    fn should_allow_initial_burst() {
+
+        let mut limiter = RateLimiter::new(Some(1.0), 10);
+        let start = limiter.last_refill;
+        let request_times = simulate_requests(&mut limiter, start, 11);
+
+        assert!(request_times[..10].iter().all(|request_time| *request_time == start));
+        assert_eq!(request_times[10] - start, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn should_refill_tokens_while_idle() {
+
+        let mut limiter = RateLimiter::new(Some(100.0), 5);
+        let start = limiter.last_refill;
+        simulate_requests(&mut limiter, start, 5);
+
+        // 30ms of inactivity refill 3 tokens, the 4th request has to wait
+        let request_times = simulate_requests(&mut limiter, start + Duration::from_millis(30), 4);
+
+        assert_eq!(request_times[2], start + Duration::from_millis(30));
+        assert_eq!(request_times[3], start + Duration::from_millis(40));
+    }
+
+    #[test]
+    fn should_not_wait_without_rate() {
+
+        let mut limiter = RateLimiter::new(None, 1);
+        let start = limiter.last_refill;
+        let request_times = simulate_requests(&mut limiter, start, 100);
+
+        assert_eq!(request_times[99], start);
+    }
+
+    #[test]
+    fn should_measure_achieved_rate() {
+
+        let mut limiter = RateLimiter::new(Some(2000.0), 1);
+        assert_eq!(limiter.achieved_rate(), None);
+
+        let start = limiter.last_refill;
+        simulate_requests(&mut limiter, start, 201);
+
+        // 200 intervals of 500µs
+        let achieved_rate = limiter.achieved_rate().unwrap();
+        assert!((achieved_rate - 2000.0).abs() < 0.001);
+    }
+
+}
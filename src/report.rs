@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+
+use ipnetwork::IpNetwork;
+
+use crate::args::{ScanOptions, ScanTiming};
+use crate::export::escape_xml;
+use crate::network::{ResponseSummary, TargetDetails};
+use crate::time;
+use crate::utils;
+
+const REPORT_STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1.5em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.7em; text-align: left; }
+th { background: #f0f0f0; }
+table.sortable th { cursor: pointer; }
+table.sortable th[aria-sort=ascending]::after { content: ' \\25B2'; }
+table.sortable th[aria-sort=descending]::after { content: ' \\25BC'; }
+td.mono { font-family: monospace; }
+";
+
+// Rows are sorted with a numeric collation, IPv4 addresses are then sorted
+// by their numeric parts (10.0.0.2 before 10.0.0.12).
+const REPORT_SCRIPT: &str = "
+document.querySelectorAll('table.sortable').forEach(function (table) {
+  table.querySelectorAll('th').forEach(function (header, column) {
+    header.addEventListener('click', function () {
+      var ascending = header.getAttribute('aria-sort') !== 'ascending';
+      table.querySelectorAll('th').forEach(function (other) { other.removeAttribute('aria-sort'); });
+      header.setAttribute('aria-sort', ascending ? 'ascending' : 'descending');
+      var body = table.tBodies[0];
+      var rows = Array.prototype.slice.call(body.rows);
+      rows.sort(function (first, second) {
+        var comparison = first.cells[column].textContent.localeCompare(second.cells[column].textContent, undefined, { numeric: true });
+        return ascending ? comparison : -comparison;
+      });
+      rows.forEach(function (row) { body.appendChild(row); });
+    });
+  });
+});
+";
+
+/**
+ * Content shared by the Markdown and HTML reports: scan parameters, summary
+ * statistics, the hosts table and the vendor breakdown.
+ */
+struct ScanReport {
+    started_at: String,
+    parameters: Vec<(&'static str, String)>,
+    statistics: Vec<(&'static str, String)>,
+    host_titles: Vec<&'static str>,
+    host_rows: Vec<Vec<String>>,
+    vendor_counts: Vec<(String, usize)>
+}
+
+fn describe_scan_timing(options: &ScanOptions) -> String {
+    match options.scan_timing {
+        ScanTiming::Interval(interval_us) => format!("{}us between requests", interval_us),
+        ScanTiming::Bandwidth(bits_second) => format!("{} bits/s", bits_second),
+        ScanTiming::PacketRate(packet_rate) => format!("{} packets/s", packet_rate)
+    }
+}
+
+/**
+ * Count discovered hosts per vendor, the most common vendors first. Hosts
+ * without a known vendor are grouped as 'Unknown'.
+ */
+fn count_vendors(target_details: &[TargetDetails]) -> Vec<(String, usize)> {
+
+    let mut vendor_counts: HashMap<String, usize> = HashMap::new();
+    for detail in target_details {
+        let vendor = detail.vendor.clone().unwrap_or_else(|| "Unknown".to_string());
+        *vendor_counts.entry(vendor).or_insert(0) += 1;
+    }
+
+    let mut vendor_counts: Vec<(String, usize)> = vendor_counts.into_iter().collect();
+    vendor_counts.sort_by(|first, second| second.1.cmp(&first.1).then_with(|| first.0.cmp(&second.0)));
+    vendor_counts
+}
+
+fn build_report(response_summary: &ResponseSummary, target_details: &[TargetDetails], interface_name: &str, ip_networks: &[&IpNetwork], options: &ScanOptions) -> ScanReport {
+
+    let network_list = ip_networks.iter().map(|network| network.to_string()).collect::<Vec<String>>().join(", ");
+    let parameters = vec![
+        ("Interface", interface_name.to_string()),
+        ("Networks", network_list),
+        ("Profile", options.profile.name().to_string()),
+        ("Targets", format!("{} hosts", response_summary.target_count)),
+        ("Requests", format!("{} {}", response_summary.request_count, utils::describe_request_kind(options))),
+        ("Timing", describe_scan_timing(options)),
+        ("Achieved rate", utils::format_packet_rate(response_summary.request_rate)),
+        ("Retry count", options.retry_count.to_string()),
+        ("Timeout", format!("{}ms", options.timeout_ms))
+    ];
+
+    let statistics = vec![
+        ("Hosts found", target_details.len().to_string()),
+        ("Duration", format!("{:.3} seconds", (response_summary.duration_ms as f64) / 1000.0)),
+        ("Packets received", response_summary.packet_count.to_string()),
+        ("ARP packets", response_summary.arp_count.to_string()),
+        ("IPv4 conflicts", response_summary.conflict_count.to_string())
+    ];
+
+    let columns = utils::list_table_columns(target_details, options);
+    let host_titles = columns.iter().map(|column| column.title()).collect();
+    let host_rows = target_details.iter()
+        .map(|detail| columns.iter().map(|column| utils::format_column_value(detail, *column, options)).collect())
+        .collect();
+
+    ScanReport {
+        started_at: time::format_timestamp(response_summary.start_timestamp),
+        parameters,
+        statistics,
+        host_titles,
+        host_rows,
+        vendor_counts: count_vendors(target_details)
+    }
+}
+
+/**
+ * Escape a table cell, HTML tags are not interpreted and line breaks would
+ * end the table row.
+ */
+fn escape_markdown(text: &str) -> String {
+    text.chars()
+        .filter(|character| !character.is_control())
+        .fold(String::with_capacity(text.len()), |mut escaped, character| {
+            if matches!(character, '\\' | '|' | '<' | '>') {
+                escaped.push('\\');
+            }
+            escaped.push(character);
+            escaped
+        })
+}
+
+fn push_markdown_table(report_text: &mut String, titles: &[&str], rows: &[Vec<String>]) {
+
+    report_text.push_str(&format!("| {} |\n", titles.join(" | ")));
+    report_text.push_str(&format!("|{}\n", "---|".repeat(titles.len())));
+    for row in rows {
+        let cells: Vec<String> = row.iter().map(|cell| escape_markdown(cell)).collect();
+        report_text.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    report_text.push('\n');
+}
+
+fn to_rows(pairs: &[(&str, String)]) -> Vec<Vec<String>> {
+    pairs.iter().map(|(name, value)| vec![name.to_string(), value.clone()]).collect()
+}
+
+/**
+ * Export the scan results as a Markdown report, ready to be pasted in wiki
+ * pages or tickets.
+ */
+pub fn export_to_markdown(response_summary: &ResponseSummary, target_details: &[TargetDetails], interface_name: &str, ip_networks: &[&IpNetwork], options: &ScanOptions) -> String {
+
+    let report = build_report(response_summary, target_details, interface_name, ip_networks, options);
+    let mut report_text = String::new();
+
+    report_text.push_str("# ARP scan report\n\n");
+    report_text.push_str(&format!("Scan started {} (UTC) on {}.\n\n", report.started_at, escape_markdown(interface_name)));
+
+    report_text.push_str("## Scan parameters\n\n");
+    push_markdown_table(&mut report_text, &["Parameter", "Value"], &to_rows(&report.parameters));
+
+    report_text.push_str("## Summary\n\n");
+    push_markdown_table(&mut report_text, &["Statistic", "Value"], &to_rows(&report.statistics));
+
+    report_text.push_str("## Hosts\n\n");
+    match report.host_rows.is_empty() {
+        true => report_text.push_str("No hosts found.\n\n"),
+        false => push_markdown_table(&mut report_text, &report.host_titles, &report.host_rows)
+    };
+
+    if !report.vendor_counts.is_empty() {
+        report_text.push_str("## Vendors\n\n");
+        let vendor_rows: Vec<Vec<String>> = report.vendor_counts.iter().map(|(vendor, count)| vec![vendor.clone(), count.to_string()]).collect();
+        push_markdown_table(&mut report_text, &["Vendor", "Hosts"], &vendor_rows);
+    }
+
+    report_text
+}
+
+fn push_html_table(report_text: &mut String, titles: &[&str], rows: &[Vec<String>], sortable: bool, mono_columns: usize) {
+
+    report_text.push_str(match sortable {
+        true => "<table class=\"sortable\">\n<thead><tr>",
+        false => "<table>\n<thead><tr>"
+    });
+    for title in titles {
+        report_text.push_str(&format!("<th>{}</th>", escape_xml(title)));
+    }
+    report_text.push_str("</tr></thead>\n<tbody>\n");
+    for row in rows {
+        report_text.push_str("<tr>");
+        for (index, cell) in row.iter().enumerate() {
+            match index < mono_columns {
+                true => report_text.push_str(&format!("<td class=\"mono\">{}</td>", escape_xml(cell))),
+                false => report_text.push_str(&format!("<td>{}</td>", escape_xml(cell)))
+            };
+        }
+        report_text.push_str("</tr>\n");
+    }
+    report_text.push_str("</tbody>\n</table>\n");
+}
+
+/**
+ * Export the scan results as a self-contained HTML report (inline style and
+ * script), the hosts and vendors tables can be sorted by clicking a header.
+ */
+pub fn export_to_html(response_summary: &ResponseSummary, target_details: &[TargetDetails], interface_name: &str, ip_networks: &[&IpNetwork], options: &ScanOptions) -> String {
+
+    let report = build_report(response_summary, target_details, interface_name, ip_networks, options);
+    let mut report_text = String::new();
+
+    report_text.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    report_text.push_str(&format!("<title>ARP scan report - {}</title>\n", escape_xml(&report.started_at)));
+    report_text.push_str(&format!("<style>{}</style>\n</head>\n<body>\n", REPORT_STYLE));
+
+    report_text.push_str("<h1>ARP scan report</h1>\n");
+    report_text.push_str(&format!("<p>Scan started {} (UTC) on {}.</p>\n", escape_xml(&report.started_at), escape_xml(interface_name)));
+
+    report_text.push_str("<h2>Scan parameters</h2>\n");
+    push_html_table(&mut report_text, &["Parameter", "Value"], &to_rows(&report.parameters), false, 0);
+
+    report_text.push_str("<h2>Summary</h2>\n");
+    push_html_table(&mut report_text, &["Statistic", "Value"], &to_rows(&report.statistics), false, 0);
+
+    report_text.push_str("<h2>Hosts</h2>\n");
+    match report.host_rows.is_empty() {
+        true => report_text.push_str("<p>No hosts found.</p>\n"),
+        false => push_html_table(&mut report_text, &report.host_titles, &report.host_rows, true, 2)
+    };
+
+    if !report.vendor_counts.is_empty() {
+        report_text.push_str("<h2>Vendors</h2>\n");
+        let vendor_rows: Vec<Vec<String>> = report.vendor_counts.iter().map(|(vendor, count)| vec![vendor.clone(), count.to_string()]).collect();
+        push_html_table(&mut report_text, &["Vendor", "Hosts"], &vendor_rows, true, 0);
+    }
+
+    report_text.push_str(&format!("<script>{}</script>\n</body>\n</html>\n", REPORT_SCRIPT));
+    report_text
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    use crate::args;
+
+    fn build_target(ipv4: Ipv4Addr, vendor: Option<&str>) -> TargetDetails {
+        TargetDetails {
+            hostname: Some("nas|backup".to_string()),
+            vendor: vendor.map(|vendor| vendor.to_string()),
+            ..TargetDetails::build(ipv4, "00:11:22:33:44:55".parse().unwrap())
+        }
+    }
+
+    fn build_summary() -> ResponseSummary {
+        ResponseSummary {
+            packet_count: 9,
+            arp_count: 3,
+            start_timestamp: 1_700_000_000,
+            duration_ms: 1500,
+            target_count: 256,
+            conflict_count: 0,
+            request_count: 256,
+            request_rate: Some(99.6)
+        }
+    }
+
+    fn build_options(arguments: &[&str]) -> Arc<ScanOptions> {
+
+        let mut command_line = vec!["arp-scan"];
+        command_line.extend_from_slice(arguments);
+
+        Arc::new(ScanOptions::new(&args::build_args().get_matches_from(command_line)))
+    }
+
+    #[test]
+    fn should_escape_markdown_cells() {
+
+        assert_eq!(escape_markdown("nas|backup\\"), "nas\\|backup\\\\");
+        assert_eq!(escape_markdown("<img src=x>\nrow"), "\\<img src=x\\>row");
+    }
+
+    #[test]
+    fn should_count_vendors() {
+
+        let target_details = vec![
+            build_target(Ipv4Addr::new(10, 20, 0, 2), Some("Cisco Systems")),
+            build_target(Ipv4Addr::new(10, 20, 0, 3), None),
+            build_target(Ipv4Addr::new(10, 20, 0, 4), Some("Raspberry Pi")),
+            build_target(Ipv4Addr::new(10, 20, 0, 5), Some("Raspberry Pi"))
+        ];
+
+        assert_eq!(count_vendors(&target_details), vec![
+            ("Raspberry Pi".to_string(), 2),
+            ("Cisco Systems".to_string(), 1),
+            ("Unknown".to_string(), 1)
+        ]);
+    }
+
+    #[test]
+    fn should_export_markdown_report() {
+
+        let options = build_options(&["-o", "markdown"]);
+        let network: IpNetwork = "10.20.0.0/24".parse().unwrap();
+        let target_details = vec![build_target(Ipv4Addr::new(10, 20, 0, 2), Some("Synology"))];
+
+        let report_text = export_to_markdown(&build_summary(), &target_details, "eth0", &[&network], &options);
+
+        assert!(report_text.starts_with("# ARP scan report\n\nScan started Tue Nov 14 22:13:20 2023 (UTC) on eth0.\n"));
+        assert!(report_text.contains("| Networks | 10.20.0.0/24 |\n"));
+        assert!(report_text.contains("| Hosts found | 1 |\n"));
+        assert!(report_text.contains("| IPv4 | MAC | Hostname | Vendor |\n|---|---|---|---|\n| 10.20.0.2 | 00:11:22:33:44:55 | nas\\|backup | Synology |\n"));
+        assert!(report_text.contains("| Vendor | Hosts |\n|---|---|\n| Synology | 1 |\n"));
+    }
+
+    #[test]
+    fn should_export_html_report() {
+
+        let options = build_options(&["-o", "html"]);
+        let network: IpNetwork = "10.20.0.0/24".parse().unwrap();
+        let target_details = vec![build_target(Ipv4Addr::new(10, 20, 0, 2), Some("Smith & Sons"))];
+
+        let report_text = export_to_html(&build_summary(), &target_details, "eth0", &[&network], &options);
+
+        assert!(report_text.starts_with("<!DOCTYPE html>\n"));
+        assert!(report_text.contains("<table class=\"sortable\">\n<thead><tr><th>IPv4</th><th>MAC</th><th>Hostname</th><th>Vendor</th></tr></thead>"));
+        assert!(report_text.contains("<td class=\"mono\">10.20.0.2</td><td class=\"mono\">00:11:22:33:44:55</td><td>nas|backup</td><td>Smith &amp; Sons</td>"));
+        assert!(report_text.ends_with("</script>\n</body>\n</html>\n"));
+    }
+
+}
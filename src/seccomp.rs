@@ -0,0 +1,165 @@
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+use std::io;
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+use libc::{sock_filter, sock_fprog};
+
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH_CURRENT: u32 = 0xC000_003E;
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH_CURRENT: u32 = 0xC000_00B7;
+
+// Offsets in the 'seccomp_data' structure given to the filter program
+const SECCOMP_DATA_NR_OFFSET: u32 = 0;
+const SECCOMP_DATA_ARCH_OFFSET: u32 = 4;
+
+/**
+ * System calls needed once the datalink channel is opened: packet sending &
+ * receiving, threads & timers, memory management, file reads & writes (vendor
+ * database, checkpoints, exports), UDP/TCP sockets for hostname lookups and
+ * the metrics listener.
+ */
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+fn list_allowed_syscalls() -> Vec<libc::c_long> {
+
+    let mut syscalls = vec![
+        // Datalink channel & other sockets
+        libc::SYS_pselect6, libc::SYS_ppoll, libc::SYS_sendto, libc::SYS_recvfrom,
+        libc::SYS_sendmsg, libc::SYS_recvmsg, libc::SYS_sendmmsg, libc::SYS_socket,
+        libc::SYS_connect, libc::SYS_bind, libc::SYS_getsockname, libc::SYS_getpeername,
+        libc::SYS_setsockopt, libc::SYS_getsockopt, libc::SYS_shutdown, libc::SYS_accept4,
+        // Files & descriptors
+        libc::SYS_read, libc::SYS_write, libc::SYS_readv, libc::SYS_writev,
+        libc::SYS_pread64, libc::SYS_pwrite64, libc::SYS_openat, libc::SYS_close,
+        libc::SYS_lseek, libc::SYS_fstat, libc::SYS_newfstatat, libc::SYS_statx,
+        libc::SYS_fcntl, libc::SYS_ioctl, libc::SYS_ftruncate, libc::SYS_fsync,
+        libc::SYS_fdatasync, libc::SYS_getdents64, libc::SYS_readlinkat, libc::SYS_faccessat,
+        libc::SYS_pipe2, libc::SYS_dup, libc::SYS_dup3, libc::SYS_renameat,
+        libc::SYS_renameat2,
+        // Memory management
+        libc::SYS_mmap, libc::SYS_munmap, libc::SYS_mremap, libc::SYS_mprotect,
+        libc::SYS_madvise, libc::SYS_brk,
+        // Threads, signals & timers
+        libc::SYS_clone, libc::SYS_clone3, libc::SYS_futex, libc::SYS_set_robust_list,
+        libc::SYS_rseq, libc::SYS_set_tid_address, libc::SYS_sched_yield, libc::SYS_sched_getaffinity,
+        libc::SYS_nanosleep, libc::SYS_clock_nanosleep, libc::SYS_clock_gettime, libc::SYS_gettimeofday,
+        libc::SYS_rt_sigaction, libc::SYS_rt_sigprocmask, libc::SYS_rt_sigreturn, libc::SYS_sigaltstack,
+        libc::SYS_restart_syscall, libc::SYS_tgkill, libc::SYS_exit, libc::SYS_exit_group,
+        // Process details
+        libc::SYS_getpid, libc::SYS_gettid, libc::SYS_getuid, libc::SYS_geteuid,
+        libc::SYS_getgid, libc::SYS_getegid, libc::SYS_getrandom, libc::SYS_uname,
+        libc::SYS_sysinfo, libc::SYS_prctl
+    ];
+
+    // Legacy system calls, still used by the C library on x86_64
+    #[cfg(target_arch = "x86_64")]
+    syscalls.extend_from_slice(&[
+        libc::SYS_open, libc::SYS_stat, libc::SYS_lstat, libc::SYS_access,
+        libc::SYS_readlink, libc::SYS_poll, libc::SYS_select, libc::SYS_pipe,
+        libc::SYS_dup2, libc::SYS_getdents, libc::SYS_arch_prctl, libc::SYS_rename
+    ]);
+
+    syscalls
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+fn build_statement(code: u32, k: u32) -> sock_filter {
+    sock_filter { code: code as u16, jt: 0, jf: 0, k }
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+fn build_jump(code: u32, k: u32, jt: u8, jf: u8) -> sock_filter {
+    sock_filter { code: code as u16, jt, jf, k }
+}
+
+/**
+ * Build the BPF program of the seccomp filter. A system call that is not in
+ * the allowed list fails with EPERM (the scan can then report an error),
+ * while a system call from another architecture kills the process.
+ */
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+fn build_filter_program() -> Vec<sock_filter> {
+
+    let mut program = vec![
+        build_statement(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, SECCOMP_DATA_ARCH_OFFSET),
+        build_jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, AUDIT_ARCH_CURRENT, 1, 0),
+        build_statement(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_KILL_PROCESS),
+        build_statement(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, SECCOMP_DATA_NR_OFFSET)
+    ];
+
+    for syscall in list_allowed_syscalls() {
+        program.push(build_jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, syscall as u32, 0, 1));
+        program.push(build_statement(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ALLOW));
+    }
+
+    program.push(build_statement(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ERRNO | (libc::EPERM as u32)));
+    program
+}
+
+/**
+ * Install the seccomp filter on the current thread, threads spawned after
+ * this call will inherit the filter. The 'no new privileges' flag must be set
+ * beforehand (this is done when dropping privileges).
+ */
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+pub fn install_filter() -> Result<(), String> {
+
+    let mut program = build_filter_program();
+    let filter = sock_fprog {
+        len: program.len() as u16,
+        filter: program.as_mut_ptr()
+    };
+
+    let result = unsafe { libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_FILTER, &filter as *const sock_fprog, 0, 0) };
+    match result {
+        0 => Ok(()),
+        _ => Err(format!("Could not install seccomp filter ({})", io::Error::last_os_error()))
+    }
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+pub fn install_filter() -> Result<(), String> {
+    Err("Seccomp filters are not supported on this architecture".to_string())
+}
+
+#[cfg(all(test, any(target_arch = "x86_64", target_arch = "aarch64")))]
+mod tests {
+
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn should_end_with_default_action() {
+
+        let program = build_filter_program();
+        let last_statement = program.last().unwrap();
+
+        assert_eq!(last_statement.k, libc::SECCOMP_RET_ERRNO | (libc::EPERM as u32));
+        assert_eq!(program.len(), 5 + 2 * list_allowed_syscalls().len());
+    }
+
+    #[test]
+    fn should_deny_unlisted_syscalls() {
+
+        // The filter only applies to the thread installing it (and its future
+        // children), the test process itself is not impacted.
+        let filter_thread = thread::spawn(|| {
+
+            unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) };
+            install_filter().unwrap();
+
+            let allowed_result = unsafe { libc::getpid() };
+            let denied_result = unsafe { libc::syscall(libc::SYS_unshare, 0) };
+            let denied_error = io::Error::last_os_error().raw_os_error();
+
+            (allowed_result, denied_result, denied_error)
+        });
+
+        let (allowed_result, denied_result, denied_error) = filter_thread.join().unwrap();
+
+        assert!(allowed_result > 0);
+        assert_eq!(denied_result, -1);
+        assert_eq!(denied_error, Some(libc::EPERM));
+    }
+
+}
@@ -0,0 +1,129 @@
+use std::io::{self, Write};
+use std::net::Ipv4Addr;
+use std::process;
+use std::time::Instant;
+
+use pnet_datalink::MacAddr;
+use serde::Serialize;
+
+use crate::args::OutputFormat;
+use crate::network::DiscoveryMethod;
+
+/**
+ * Host records are written while the scan is running, as soon as a new host
+ * is discovered. Only the details known during the capture are available
+ * (hostnames and ports are found later, in the final summary).
+ */
+#[derive(Serialize)]
+struct StreamHostRecord {
+    #[serde(rename = "type")]
+    record_type: &'static str,
+    ipv4: String,
+    mac: String,
+    vendor: Option<String>,
+    found_by: String,
+    elapsed_ms: u128
+}
+
+/**
+ * Write discovered hosts on stdout during the scan: a table row in plain
+ * mode, or a JSON record per line (NDJSON) for machine outputs.
+ */
+pub struct StreamWriter {
+    is_plain: bool,
+    start_time: Instant,
+    host_count: usize
+}
+
+impl StreamWriter {
+
+    pub fn new(output: &OutputFormat) -> Self {
+        StreamWriter {
+            is_plain: matches!(output, OutputFormat::Plain),
+            start_time: Instant::now(),
+            host_count: 0
+        }
+    }
+
+    pub fn write_host(&mut self, ipv4: Ipv4Addr, mac: MacAddr, vendor: Option<&str>, found_by: DiscoveryMethod) {
+
+        let host_record = StreamHostRecord {
+            record_type: "host",
+            ipv4: ipv4.to_string(),
+            mac: mac.to_string(),
+            vendor: vendor.map(|vendor| vendor.to_string()),
+            found_by: found_by.name().to_string(),
+            elapsed_ms: self.start_time.elapsed().as_millis()
+        };
+        let line = match self.is_plain {
+            true => self.format_plain_row(&host_record),
+            false => format_host_record(&host_record)
+        };
+        self.host_count += 1;
+
+        // Each line is flushed right away, stdout may be piped to another
+        // process that should see hosts as soon as they are discovered.
+        let mut stdout = io::stdout().lock();
+        let _ = writeln!(stdout, "{}", line).and_then(|_| stdout.flush());
+    }
+
+    fn format_plain_row(&self, host_record: &StreamHostRecord) -> String {
+
+        let row = format!("| {: <15} | {: <17} | {: <8} | {}", host_record.ipv4, host_record.mac, host_record.found_by, host_record.vendor.as_deref().unwrap_or_default());
+
+        match self.host_count {
+            0 => format!("\n| IPv4            | MAC               | Found by | Vendor\n{}", row),
+            _ => row
+        }
+    }
+}
+
+fn format_host_record(host_record: &StreamHostRecord) -> String {
+
+    serde_json::to_string(host_record).unwrap_or_else(|err| {
+        eprintln!("Could not stream host record ({})", err);
+        process::exit(1);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn should_format_ndjson_host_record() {
+
+        let host_record = StreamHostRecord {
+            record_type: "host",
+            ipv4: "10.20.0.12".to_string(),
+            mac: "00:11:22:33:44:55".to_string(),
+            vendor: Some("Dell Inc.".to_string()),
+            found_by: DiscoveryMethod::Arp.name().to_string(),
+            elapsed_ms: 1250
+        };
+
+        let record = format_host_record(&host_record);
+
+        assert_eq!(record, r#"{"type":"host","ipv4":"10.20.0.12","mac":"00:11:22:33:44:55","vendor":"Dell Inc.","found_by":"arp","elapsed_ms":1250}"#);
+    }
+
+    #[test]
+    fn should_print_plain_header_once() {
+
+        let mut stream_writer = StreamWriter::new(&OutputFormat::Plain);
+        let host_record = StreamHostRecord {
+            record_type: "host",
+            ipv4: "10.20.0.12".to_string(),
+            mac: "00:11:22:33:44:55".to_string(),
+            vendor: None,
+            found_by: DiscoveryMethod::Icmp.name().to_string(),
+            elapsed_ms: 0
+        };
+
+        assert!(stream_writer.format_plain_row(&host_record).contains("| IPv4 "));
+        stream_writer.host_count += 1;
+        assert_eq!(stream_writer.format_plain_row(&host_record), "| 10.20.0.12      | 00:11:22:33:44:55 | icmp     | ");
+    }
+
+}
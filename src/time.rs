@@ -1,6 +1,6 @@
 /**
  * Parse a given time string into milliseconds. This can be used to convert a
- * string such as '20ms', '10s' or '1h' into adequate milliseconds. Without
+ * string such as '20ms', '10s', '1h' or '7d' into adequate milliseconds. Without
  * suffix, the default behavior is to parse into milliseconds.
  */
 pub fn parse_to_milliseconds(time_arg: &str) -> Result<u64, &str> {
@@ -39,12 +39,40 @@ pub fn parse_to_milliseconds(time_arg: &str) -> Result<u64, &str> {
         };
     }
 
+    if time_arg.ends_with('d') {
+        let day_text = &time_arg[0..len-1];
+        return match day_text.parse::<u64>().ok().and_then(|value| value.checked_mul(1000 * 60 * 60 * 24)) {
+            Some(ms_value) => Ok(ms_value),
+            None => Err("invalid days")
+        };
+    }
+
     match time_arg.parse::<u64>() {
         Ok(ms_value) => Ok(ms_value),
         Err(_) => Err("invalid milliseconds")
     }
 }
 
+/**
+ * Parse a given time string into microseconds. This supports the 'us' (or
+ * 'µs') suffix for sub-millisecond durations, other suffixes are handled like
+ * the milliseconds parser (a value without suffix is still in milliseconds).
+ */
+pub fn parse_to_microseconds(time_arg: &str) -> Result<u64, &str> {
+
+    let microseconds_text = time_arg.strip_suffix("us").or_else(|| time_arg.strip_suffix("µs"));
+    if let Some(microseconds_text) = microseconds_text {
+        return match microseconds_text.parse::<u64>() {
+            Ok(us_value) => Ok(us_value),
+            Err(_) => Err("invalid microseconds")
+        };
+    }
+
+    parse_to_milliseconds(time_arg).and_then(|ms_value| {
+        ms_value.checked_mul(1000).ok_or("duration too long")
+    })
+}
+
 /**
  * Format milliseconds to a human-readable string. This will of course give an
  * approximation, but will be readable.
@@ -69,6 +97,74 @@ pub fn format_milliseconds(milliseconds: u128) -> String {
     format!("{}h", hours)
 }
 
+/**
+ * Convert a day count since the Unix epoch to a civil date (year, month and
+ * day) of the proleptic Gregorian calendar.
+ */
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+
+    // Years start in March to handle leap days at the end of the year.
+    let shifted_days = days + 719_468;
+    let era = shifted_days / 146_097;
+    let era_day = shifted_days - era * 146_097;
+    let era_year = (era_day - era_day / 1460 + era_day / 36_524 - era_day / 146_096) / 365;
+    let year_day = era_day - (365 * era_year + era_year / 4 - era_year / 100);
+    let shifted_month = (5 * year_day + 2) / 153;
+    let month_day = year_day - (153 * shifted_month + 2) / 5 + 1;
+    let month = match shifted_month < 10 {
+        true => shifted_month + 3,
+        false => shifted_month - 9
+    };
+    let year = era_year + era * 400 + u64::from(month <= 2);
+
+    (year, month, month_day)
+}
+
+/**
+ * Format a Unix timestamp (in seconds) as an UTC date, with the layout used
+ * by nmap reports (e.g. 'Tue Nov 14 22:13:20 2023').
+ */
+pub fn format_timestamp(timestamp: u64) -> String {
+
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+    let days = timestamp / 86_400;
+    let day_seconds = timestamp % 86_400;
+    let (year, month, month_day) = civil_from_days(days);
+
+    format!(
+        "{} {} {:02} {:02}:{:02}:{:02} {}",
+        WEEKDAYS[(days % 7) as usize],
+        MONTHS[(month - 1) as usize],
+        month_day,
+        day_seconds / 3600,
+        (day_seconds % 3600) / 60,
+        day_seconds % 60,
+        year
+    )
+}
+
+/**
+ * Format a Unix timestamp (in seconds) as an UTC RFC 3339 date, as expected
+ * by syslog messages (e.g. '2023-11-14T22:13:20Z').
+ */
+pub fn format_rfc3339(timestamp: u64) -> String {
+
+    let day_seconds = timestamp % 86_400;
+    let (year, month, month_day) = civil_from_days(timestamp / 86_400);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        month_day,
+        day_seconds / 3600,
+        (day_seconds % 3600) / 60,
+        day_seconds % 60
+    )
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -98,6 +194,18 @@ mod tests {
         assert_eq!(parse_to_milliseconds("2h"), Ok(7_200_000));
     }
 
+    #[test]
+    fn should_parse_days() {
+
+        assert_eq!(parse_to_milliseconds("7d"), Ok(604_800_000));
+    }
+
+    #[test]
+    fn should_deny_overflowing_days() {
+
+        assert_eq!(parse_to_milliseconds("300000000000d"), Err("invalid days"));
+    }
+
     #[test]
     fn should_deny_negative() {
         
@@ -116,6 +224,28 @@ mod tests {
         assert_eq!(parse_to_milliseconds("3z"), Err("invalid milliseconds"));
     }
 
+    #[test]
+    fn should_parse_microseconds() {
+
+        assert_eq!(parse_to_microseconds("250us"), Ok(250));
+        assert_eq!(parse_to_microseconds("80µs"), Ok(80));
+    }
+
+    #[test]
+    fn should_parse_microseconds_from_other_units() {
+
+        assert_eq!(parse_to_microseconds("10"), Ok(10_000));
+        assert_eq!(parse_to_microseconds("3ms"), Ok(3000));
+        assert_eq!(parse_to_microseconds("2s"), Ok(2_000_000));
+    }
+
+    #[test]
+    fn should_deny_invalid_microseconds() {
+
+        assert_eq!(parse_to_microseconds("1.5us"), Err("invalid microseconds"));
+        assert_eq!(parse_to_microseconds("18446744073709552ms"), Err("duration too long"));
+    }
+
     // ---
 
     #[test]
@@ -142,4 +272,19 @@ mod tests {
         assert_eq!(format_milliseconds(4_200_000), "1h".to_string());
     }
 
+    #[test]
+    fn should_format_timestamps() {
+
+        assert_eq!(format_timestamp(0), "Thu Jan 01 00:00:00 1970");
+        assert_eq!(format_timestamp(951_782_400), "Tue Feb 29 00:00:00 2000");
+        assert_eq!(format_timestamp(1_700_000_000), "Tue Nov 14 22:13:20 2023");
+    }
+
+    #[test]
+    fn should_format_rfc3339_timestamps() {
+
+        assert_eq!(format_rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_rfc3339(1_700_000_000), "2023-11-14T22:13:20Z");
+    }
+
 }
@@ -1,22 +1,18 @@
-use std::env;
+use std::io::{self, Write};
 use std::process;
 use std::sync::Arc;
 
-use pnet_datalink::NetworkInterface;
+use pnet_datalink::{MacAddr, NetworkInterface};
 use ipnetwork::{IpNetwork, NetworkSize};
-use serde::Serialize;
-use ansi_term::Color::{Green, Red};
+use ansi_term::Color::{self, Green, Red};
 
-use crate::network::{ResponseSummary, TargetDetails};
-use crate::args::ScanOptions;
-
-/**
- * Based on the current UNIX environment, find if the process is run as root
- * user. This approach only supports Linux-like systems (Ubuntu, Fedore, ...).
- */
-pub fn is_root_user() -> bool {
-    env::var("USER").unwrap_or_else(|_| String::from("")) == *"root"
-}
+use crate::network::{DiscoveryMethod, ResponseSummary, ScanEstimation, TargetDetails};
+use crate::privilege;
+use crate::time;
+use crate::args::{NameSource, ResultColumn, ScanOptions};
+use crate::baseline::{BaselineViolation, Severity};
+use crate::database::{HostSighting, MacLocation};
+use crate::inventory::{InventoryCheck, InventoryStatus};
 
 /**
  * Prints on stdout a list of all available network interfaces with some
@@ -60,6 +56,91 @@ pub fn show_interfaces(interfaces: &[NetworkInterface]) {
     println!();
 }
 
+fn column_width<'a>(values: impl Iterator<Item = &'a str>, title: &str) -> usize {
+    values.map(|value| value.len()).max().unwrap_or(0).max(title.len())
+}
+
+/**
+ * Prints on stdout the hosts recorded in the scan database for a given time
+ * window, with the first & last sighting of each MAC address.
+ */
+pub fn show_host_history(sightings: &[HostSighting], since: u64, until: u64) {
+
+    println!();
+    println!("Hosts seen between {} and {} (UTC)", time::format_timestamp(since), time::format_timestamp(until));
+
+    let hostname_len = column_width(sightings.iter().map(|sighting| sighting.hostname.as_deref().unwrap_or("")), "Hostname");
+    let vendor_len = column_width(sightings.iter().map(|sighting| sighting.vendor.as_deref().unwrap_or("")), "Vendor");
+
+    if !sightings.is_empty() {
+        println!();
+        println!("| IPv4            | MAC               | {: <h_max$} | {: <v_max$} | First seen               | Last seen                | Scans |", "Hostname", "Vendor", h_max=hostname_len, v_max=vendor_len);
+        println!("|-----------------|-------------------|-{:-<h_max$}-|-{:-<v_max$}-|--------------------------|--------------------------|-------|", "", "", h_max=hostname_len, v_max=vendor_len);
+    }
+
+    for sighting in sightings {
+        println!(
+            "| {: <15} | {: <17} | {: <h_max$} | {: <v_max$} | {: <24} | {: <24} | {: <5} |",
+            sighting.ipv4,
+            sighting.mac,
+            sighting.hostname.as_deref().unwrap_or(""),
+            sighting.vendor.as_deref().unwrap_or(""),
+            time::format_timestamp(sighting.first_seen),
+            time::format_timestamp(sighting.last_seen),
+            sighting.scan_count,
+            h_max=hostname_len,
+            v_max=vendor_len
+        );
+    }
+
+    println!();
+    match sightings.len() {
+        0 => println!("No hosts seen in this time window"),
+        1 => println!("1 host seen in this time window"),
+        sighting_count => println!("{} hosts seen in this time window", sighting_count)
+    };
+    println!();
+}
+
+/**
+ * Prints on stdout where a MAC address has been seen in the scan database:
+ * the IPv4 addresses and interfaces used, the most recent ones first.
+ */
+pub fn show_mac_locations(mac: MacAddr, period: Option<(u64, u64)>, locations: &[MacLocation]) {
+
+    println!();
+    let (first_seen, last_seen) = match period {
+        Some(period) => period,
+        None => {
+            println!("MAC address {} was never seen in the database", mac);
+            println!();
+            return;
+        }
+    };
+    println!("MAC address {} first seen {}, last seen {} (UTC)", mac, time::format_timestamp(first_seen), time::format_timestamp(last_seen));
+
+    let interface_len = column_width(locations.iter().map(|location| location.interface.as_str()), "Interface");
+    let hostname_len = column_width(locations.iter().map(|location| location.hostname.as_deref().unwrap_or("")), "Hostname");
+
+    println!();
+    println!("| IPv4            | {: <i_max$} | {: <h_max$} | First seen               | Last seen                | Scans |", "Interface", "Hostname", i_max=interface_len, h_max=hostname_len);
+    println!("|-----------------|-{:-<i_max$}-|-{:-<h_max$}-|--------------------------|--------------------------|-------|", "", "", i_max=interface_len, h_max=hostname_len);
+    for location in locations {
+        println!(
+            "| {: <15} | {: <i_max$} | {: <h_max$} | {: <24} | {: <24} | {: <5} |",
+            location.ipv4,
+            location.interface,
+            location.hostname.as_deref().unwrap_or(""),
+            time::format_timestamp(location.first_seen),
+            time::format_timestamp(location.last_seen),
+            location.scan_count,
+            i_max=interface_len,
+            h_max=hostname_len
+        );
+    }
+    println!();
+}
+
 pub fn print_ascii_packet() {
 
     println!();
@@ -113,8 +194,8 @@ pub fn select_default_interface(interfaces: &[NetworkInterface]) -> Option<Netwo
 }
 
 /**
- * Display scan settings before launching an ARP scan. This includes network
- * details (IP range, interface, ...) and timing informations.
+ * Display scan settings on stderr before launching an ARP scan. This includes
+ * network details (IP range, interface, ...) and timing informations.
  */
 pub fn display_prescan_details(ip_networks: &[&IpNetwork], selected_interface: &NetworkInterface, scan_options: Arc<ScanOptions>) {
     
@@ -124,13 +205,127 @@ pub fn display_prescan_details(ip_networks: &[&IpNetwork], selected_interface: &
         network_list.push_str(&more_text);
     }
 
-    println!();
-    println!("Selected interface {} with IP {}", selected_interface.name, network_list);
+    eprintln!();
+    eprintln!("Selected interface {} with IP {}", selected_interface.name, network_list);
     if let Some(forced_source_ipv4) = scan_options.source_ipv4 {
-        println!("The ARP source IPv4 will be forced to {}", forced_source_ipv4);
+        eprintln!("The ARP source IPv4 will be forced to {}", forced_source_ipv4);
     }
     if let Some(forced_destination_mac) = scan_options.destination_mac {
-        println!("The ARP destination MAC will be forced to {}", forced_destination_mac);
+        eprintln!("The ARP destination MAC will be forced to {}", forced_destination_mac);
+    }
+    if let Some(inventory) = scan_options.inventory() {
+        eprintln!("Unicast ARP requests will be sent to the {} inventory devices", inventory.device_count());
+    }
+    if let Some(random_seed) = scan_options.target_seed() {
+        eprintln!("Targets will be scanned in random order (seed {})", random_seed);
+    }
+    if let Some(checkpoint) = scan_options.resume_checkpoint() {
+        eprintln!("Resuming scan at retry round {}, target {} ({} hosts already found)", checkpoint.retry_round + 1, checkpoint.position, checkpoint.hosts.len());
+    }
+}
+
+/**
+ * Display the full scan plan without performing the scan (dry-run mode). The
+ * plan gives all details about the targets, the requests and the timings.
+ */
+pub fn display_scan_plan(ip_networks: &[&IpNetwork], selected_interface: &NetworkInterface, scan_options: &ScanOptions, network_size: u128, estimations: &ScanEstimation) {
+
+    let network_list = ip_networks.iter().map(|network| network.to_string()).collect::<Vec<String>>().join(", ");
+    let source_ip = match scan_options.source_ipv4 {
+        Some(forced_source_ipv4) => forced_source_ipv4.to_string(),
+        None => selected_interface.ips.iter().find(|ip| ip.is_ipv4()).map(|ip| ip.ip().to_string()).unwrap_or_default()
+    };
+    let target_order = match scan_options.target_seed() {
+        Some(random_seed) => format!("random, seed {}", random_seed),
+        None => "ascending".to_string()
+    };
+    let vlan_text = match scan_options.vlan_id {
+        Some(vlan_id) => vlan_id.to_string(),
+        None => "none".to_string()
+    };
+
+    println!();
+    println!("Scan plan (dry-run, no requests will be sent)");
+    println!();
+    println!("  Interface      {} (source IPv4 {})", selected_interface.name, source_ip);
+    println!("  Networks       {}", network_list);
+    println!("  Targets        {} hosts", network_size);
+    if let Some(inventory) = scan_options.inventory() {
+        println!("  Inventory      {} devices (unicast ARP requests)", inventory.device_count());
+    }
+    println!("  Target order   {}", target_order);
+    println!("  Profile        {}", scan_options.profile.name());
+    println!("  VLAN           {}", vlan_text);
+    println!("  Retry count    {}", scan_options.retry_count);
+    println!("  Requests       {} {}", estimations.request_count, describe_request_kind(scan_options));
+    println!("  Request size   {} bytes", estimations.request_size);
+    println!("  Rate           {}, burst of {}", format_packet_rate(estimations.packet_rate), scan_options.burst_size);
+    println!("  Bandwidth      {} bytes/s", estimations.bandwidth);
+    println!("  Timeout        {}ms", scan_options.timeout_ms);
+    println!("  Duration       {} (estimated)", time::format_milliseconds(estimations.duration_ms));
+    println!("  Hostnames      {}", describe_name_resolution(scan_options));
+    println!("  TCP ports      {}", describe_port_probes(scan_options));
+    println!("  Privileges     {}", privilege::describe_privileges());
+    println!();
+}
+
+/**
+ * Describe the TCP ports probed on each discovered host after the scan.
+ */
+fn describe_port_probes(scan_options: &ScanOptions) -> String {
+
+    match scan_options.probe_ports.len() {
+        0 => "none".to_string(),
+        port_count => format!("{} ports per host ({}, timeout {}ms)", port_count, format_port_list(&scan_options.probe_ports), scan_options.port_timeout_ms)
+    }
+}
+
+/**
+ * Describe the hostname sources and the DNS resolver used after the scan.
+ */
+fn describe_name_resolution(scan_options: &ScanOptions) -> String {
+
+    if !scan_options.resolve_hostname {
+        return "disabled".to_string();
+    }
+
+    let mut descriptions: Vec<String> = scan_options.name_sources.iter().map(|name_source| match name_source {
+        NameSource::Dns if !scan_options.dns_servers.is_empty() => {
+            let server_list = scan_options.dns_servers.iter().map(|server| server.to_string()).collect::<Vec<String>>();
+            format!("dns ({})", server_list.join(", "))
+        },
+        NameSource::Dns => "dns (system resolver)".to_string(),
+        name_source => name_source.name().to_string()
+    }).collect();
+
+    if scan_options.hosts_file.is_some() {
+        descriptions.push("hosts file".to_string());
+    }
+    if scan_options.forward_confirm {
+        descriptions.push("forward confirmation".to_string());
+    }
+    descriptions.join(", ")
+}
+
+/**
+ * Describe the requests sent to each target, ICMP requests are optional.
+ */
+pub fn describe_request_kind(scan_options: &ScanOptions) -> String {
+    match scan_options.icmp_probe {
+        Some(icmp_probe) => format!("ARP and ICMP {} requests", icmp_probe.name()),
+        None => "ARP requests".to_string()
+    }
+}
+
+/**
+ * Format the packet rate used to pace ARP requests, no packet rate means that
+ * the requests are not limited.
+ */
+pub fn format_packet_rate(packet_rate: Option<f64>) -> String {
+    match packet_rate {
+        Some(packet_rate) if packet_rate < 1.0 => format!("{:.3} packets/s", packet_rate),
+        Some(packet_rate) => format!("{:.0} packets/s", packet_rate),
+        None => "no rate limit".to_string()
     }
 }
 
@@ -154,187 +349,298 @@ pub fn compute_network_size(ip_networks: &[&IpNetwork]) -> u128 {
 }
 
 /**
- * Display the scan results on stdout with a table. The 'final_result' vector
- * contains all items that will be displayed.
+ * Format the hostname cell of the result table. Names that were not found by
+ * reverse DNS are followed by their source (mDNS, NetBIOS, ...), and names
+ * failing the forward confirmation are flagged.
  */
-pub fn display_scan_results(response_summary: ResponseSummary, mut target_details: Vec<TargetDetails>, options: &ScanOptions) {
+pub fn format_hostname(detail: &TargetDetails, options: &ScanOptions) -> String {
 
-    target_details.sort_by_key(|item| item.ipv4);
+    let hostname = match (&detail.hostname, detail.hostname_source) {
+        (Some(hostname), Some(NameSource::Dns)) | (Some(hostname), None) => hostname.clone(),
+        (Some(hostname), Some(name_source)) => format!("{} ({})", hostname, name_source.name()),
+        (None, _) if !options.resolve_hostname => "(disabled)".to_string(),
+        (None, _) => String::new()
+    };
 
-    let mut hostname_len = 15;
-    let mut vendor_len = 15;
-    for detail in target_details.iter() {
+    match detail.hostname_confirmed {
+        Some(false) => format!("{} (mismatch)", hostname),
+        _ => hostname
+    }
+}
 
-        if let Some(hostname) = &detail.hostname {
-            if hostname.len() > hostname_len {
-                hostname_len = hostname.len();
-            }
-        }
+/**
+ * Write the DHCP details captured during the scan in a second table, this
+ * table is only written if at least one host sent a DHCP message.
+ */
+fn write_dhcp_fingerprints(out: &mut dyn Write, target_details: &[TargetDetails]) -> io::Result<()> {
+
+    let dhcp_rows: Vec<(String, String, String, String)> = target_details.iter()
+        .filter_map(|detail| detail.dhcp.as_ref().map(|fingerprint| (
+            detail.ipv4.to_string(),
+            fingerprint.hostname.clone().unwrap_or_default(),
+            fingerprint.vendor_class.clone().unwrap_or_default(),
+            fingerprint.guess_os().unwrap_or_default()
+        )))
+        .collect();
 
-        if let Some(vendor) = &detail.vendor {
-            if vendor.len() > vendor_len {
-                vendor_len = vendor.len();
-            }
-        }
+    if dhcp_rows.is_empty() {
+        return Ok(());
     }
 
-    if !target_details.is_empty() {
-        println!();
-        println!("| IPv4            | MAC               | {: <h_max$} | {: <v_max$} |", "Hostname", "Vendor", h_max=hostname_len, v_max=vendor_len);
-        println!("|-----------------|-------------------|-{:-<h_max$}-|-{:-<v_max$}-|", "", "", h_max=hostname_len, v_max=vendor_len);
-    }
+    let hostname_len = dhcp_rows.iter().map(|row| row.1.len()).max().unwrap_or(0).max(13);
+    let class_len = dhcp_rows.iter().map(|row| row.2.len()).max().unwrap_or(0).max(12);
+    let os_len = dhcp_rows.iter().map(|row| row.3.len()).max().unwrap_or(0).max(8);
 
-    for detail in target_details.iter() {
+    writeln!(out)?;
+    writeln!(out, "| IPv4            | {: <h_max$} | {: <c_max$} | {: <o_max$} |", "DHCP hostname", "Vendor class", "OS guess", h_max=hostname_len, c_max=class_len, o_max=os_len)?;
+    writeln!(out, "|-----------------|-{:-<h_max$}-|-{:-<c_max$}-|-{:-<o_max$}-|", "", "", "", h_max=hostname_len, c_max=class_len, o_max=os_len)?;
+    for (ipv4, hostname, vendor_class, os_guess) in dhcp_rows {
+        writeln!(out, "| {: <15} | {: <h_max$} | {: <c_max$} | {: <o_max$} |", ipv4, hostname, vendor_class, os_guess, h_max=hostname_len, c_max=class_len, o_max=os_len)?;
+    }
+    Ok(())
+}
 
-        let hostname: &str = match &detail.hostname {
-            Some(hostname) => hostname,
-            None if !options.resolve_hostname => "(disabled)",
-            None => ""
-        };
-        let vendor: &str = match &detail.vendor {
-            Some(vendor) => vendor,
-            None => ""
+/**
+ * Write the inventory verification, with the expected and found MAC address
+ * of each inventory entry.
+ */
+fn write_inventory_checks(out: &mut dyn Write, inventory_checks: &[InventoryCheck], colored: bool) -> io::Result<()> {
+
+    writeln!(out)?;
+    writeln!(out, "| IPv4            | Expected MAC      | Found MAC         | Status   |")?;
+    writeln!(out, "|-----------------|-------------------|-------------------|----------|")?;
+    for check in inventory_checks {
+
+        let found_mac = check.found_mac.map(|found_mac| found_mac.to_string()).unwrap_or_default();
+        let status = format!("{: <8}", check.status.name());
+        let colored_status = match check.status {
+            InventoryStatus::Present => paint(Green, status, colored),
+            InventoryStatus::Missing | InventoryStatus::Mismatch => paint(Red, status, colored)
         };
-        println!("| {: <15} | {: <18} | {: <h_max$} | {: <v_max$} |", detail.ipv4, detail.mac, hostname, vendor, h_max=hostname_len, v_max=vendor_len);
+        writeln!(out, "| {: <15} | {: <17} | {: <17} | {} |", check.ipv4, check.expected_mac, found_mac, colored_status)?;
     }
+    Ok(())
+}
 
-    println!();
-    print!("ARP scan finished, ");
-    let target_count = target_details.len();
-    match target_count {
-        0 => print!("{}", Red.paint("no hosts found")),
-        1 => print!("1 host found"),
-        _ => print!("{} hosts found", target_count)
+/**
+ * Hosts answering ICMP requests without any ARP reply are flagged, since they
+ * may hide behind a proxy ARP device or filter ARP broadcasts.
+ */
+fn format_discovery_method(found_by: DiscoveryMethod) -> String {
+    match found_by {
+        DiscoveryMethod::Icmp => "icmp (no ARP)".to_string(),
+        found_by => found_by.name().to_string()
     }
-    let seconds_duration = (response_summary.duration_ms as f32) / (1000_f32);
-    println!(" in {:.3} seconds", seconds_duration);
+}
 
-    match response_summary.packet_count {
-        0 => print!("No packets received, "),
-        1 => print!("1 packet received, "),
-        _ => print!("{} packets received, ", response_summary.packet_count)
-    };
-    match response_summary.arp_count {
-        0 => println!("no ARP packets filtered"),
-        1 => println!("1 ARP packet filtered"),
-        _ => println!("{} ARP packets filtered", response_summary.arp_count)
-    };
-    println!();
+/**
+ * Format baseline violations as 'kind:severity' items, hosts without any
+ * violation are shown as 'ok' in the result table.
+ */
+fn format_violations(violations: &[BaselineViolation]) -> String {
+    match violations.is_empty() {
+        true => "ok".to_string(),
+        false => join_violations(violations)
+    }
 }
 
-#[derive(Serialize)]
-struct SerializableResultItem {
-    ipv4: String,
-    mac: String,
-    hostname: String,
-    vendor: String
+pub fn join_violations(violations: &[BaselineViolation]) -> String {
+    violations.iter()
+        .map(|violation| format!("{}:{}", violation.kind.name(), violation.severity.name()))
+        .collect::<Vec<String>>()
+        .join(";")
 }
 
-#[derive(Serialize)]
-struct SerializableGlobalResult {
-    packet_count: usize,
-    arp_count: usize,
-    duration_ms: u128,
-    results: Vec<SerializableResultItem>
+pub fn format_port_list(ports: &[u16]) -> String {
+    ports.iter().map(|port| port.to_string()).collect::<Vec<String>>().join(",")
 }
 
 /**
- * Transforms an ARP scan result (including KPI and target details) to a structure
- * that can be serialized for export (JSON, YAML, CSV, ...)
+ * Apply a terminal color to a text, colors are only used on stdout.
  */
-fn get_serializable_result(response_summary: ResponseSummary, target_details: Vec<TargetDetails>) -> SerializableGlobalResult {
-
-    let exportable_results: Vec<SerializableResultItem> = target_details.into_iter()
-        .map(|detail| {
-
-            let hostname = match &detail.hostname {
-                Some(hostname) => hostname.clone(),
-                None => String::from("")
-            };
-
-            let vendor = match &detail.vendor {
-                Some(vendor) => vendor.clone(),
-                None => String::from("")
-            };
-
-            SerializableResultItem {
-                ipv4: format!("{}", detail.ipv4),
-                mac: format!("{}", detail.mac),
-                hostname,
-                vendor
-            }
-        })
-        .collect();
-
-    SerializableGlobalResult {
-        packet_count: response_summary.packet_count,
-        arp_count: response_summary.arp_count,
-        duration_ms: response_summary.duration_ms,
-        results: exportable_results
+fn paint(color: Color, text: String, colored: bool) -> String {
+    match colored {
+        true => color.paint(text).to_string(),
+        false => text
     }
 }
 
 /**
- * Export the scan results as a JSON string with response details (timings, ...)
- * and ARP results from the local network.
+ * List the columns of the result table. Selected columns ('--columns') are
+ * shown as given, otherwise optional columns are only shown when they hold
+ * details: the device class needs a fingerprint database, the discovery
+ * method needs ICMP requests, violations need a baseline and open ports
+ * need a port list.
  */
-pub fn export_to_json(response_summary: ResponseSummary, mut target_details: Vec<TargetDetails>) -> String {
+pub fn list_table_columns(target_details: &[TargetDetails], options: &ScanOptions) -> Vec<ResultColumn> {
 
-    target_details.sort_by_key(|item| item.ipv4);
+    if let Some(columns) = &options.columns {
+        return columns.clone();
+    }
 
-    let global_result = get_serializable_result(response_summary, target_details);
+    let mut columns = vec![ResultColumn::Ipv4, ResultColumn::Mac, ResultColumn::Hostname, ResultColumn::Vendor];
+    if target_details.iter().any(|detail| detail.device_class.is_some()) {
+        columns.push(ResultColumn::DeviceClass);
+    }
+    if options.icmp_probe.is_some() {
+        columns.push(ResultColumn::FoundBy);
+    }
+    if options.baseline_file.is_some() {
+        columns.push(ResultColumn::BaselineViolations);
+    }
+    if !options.probe_ports.is_empty() {
+        columns.push(ResultColumn::OpenPorts);
+    }
+    columns
+}
 
-    serde_json::to_string(&global_result).unwrap_or_else(|err| {
-        eprintln!("Could not export JSON results ({})", err);
-        process::exit(1);
-    })
+/**
+ * Format the value of a host for a result table column.
+ */
+pub fn format_column_value(detail: &TargetDetails, column: ResultColumn, options: &ScanOptions) -> String {
+
+    let dhcp = detail.dhcp.as_ref();
+    match column {
+        ResultColumn::Ipv4 => detail.ipv4.to_string(),
+        ResultColumn::Mac => detail.mac.to_string(),
+        ResultColumn::Hostname => format_hostname(detail, options),
+        ResultColumn::HostnameSource => detail.hostname_source.map(|name_source| name_source.name().to_string()).unwrap_or_default(),
+        ResultColumn::HostnameConfirmed => match detail.hostname_confirmed {
+            Some(true) => "yes".to_string(),
+            Some(false) => "no".to_string(),
+            None => String::new()
+        },
+        ResultColumn::Vendor => detail.vendor.clone().unwrap_or_default(),
+        ResultColumn::DeviceClass => detail.device_class.clone().unwrap_or_default(),
+        ResultColumn::FoundBy => format_discovery_method(detail.found_by),
+        ResultColumn::BaselineViolations => format_violations(&detail.violations),
+        ResultColumn::OpenPorts => format_port_list(&detail.open_ports),
+        ResultColumn::DhcpHostname => dhcp.and_then(|fingerprint| fingerprint.hostname.clone()).unwrap_or_default(),
+        ResultColumn::DhcpVendorClass => dhcp.and_then(|fingerprint| fingerprint.vendor_class.clone()).unwrap_or_default(),
+        ResultColumn::DhcpParameters => dhcp.map(|fingerprint| fingerprint.format_parameter_list()).unwrap_or_default(),
+        ResultColumn::OsGuess => dhcp.and_then(|fingerprint| fingerprint.guess_os()).unwrap_or_default(),
+        ResultColumn::Latency => detail.behaviour.min_latency_us.map(|latency_us| format!("{:.2}ms", (latency_us as f64) / 1000.0)).unwrap_or_default()
+    }
 }
 
 /**
- * Export the scan results as a YAML string with response details (timings, ...)
- * and ARP results from the local network.
+ * Write the scan results with a table. The 'target_details' slice contains
+ * all items that will be written, already sorted and filtered.
  */
-pub fn export_to_yaml(response_summary: ResponseSummary, mut target_details: Vec<TargetDetails>) -> String {
+pub fn write_scan_results(out: &mut dyn Write, response_summary: &ResponseSummary, target_details: &[TargetDetails], inventory_checks: Option<&[InventoryCheck]>, options: &ScanOptions, colored: bool) -> io::Result<()> {
 
-    target_details.sort_by_key(|item| item.ipv4);
+    let columns = list_table_columns(target_details, options);
+    let rows: Vec<Vec<String>> = target_details.iter()
+        .map(|detail| columns.iter().map(|column| format_column_value(detail, *column, options)).collect())
+        .collect();
 
-    let global_result = get_serializable_result(response_summary, target_details);
+    // Addresses, hostnames and vendors keep a minimal width, so that
+    // successive scans are printed with similar tables.
+    let column_lengths: Vec<usize> = columns.iter().enumerate()
+        .map(|(index, column)| {
+            let minimal_len = match column {
+                ResultColumn::Ipv4 | ResultColumn::Hostname | ResultColumn::Vendor => 15,
+                ResultColumn::Mac => 17,
+                _ => 0
+            };
+            rows.iter().map(|row| row[index].len()).max().unwrap_or(0).max(column.title().len()).max(minimal_len)
+        })
+        .collect();
 
-    serde_yaml::to_string(&global_result).unwrap_or_else(|err| {
-        eprintln!("Could not export YAML results ({})", err);
-        process::exit(1);
-    })
-}
+    if !target_details.is_empty() {
+        writeln!(out)?;
+        write!(out, "|")?;
+        for (column, column_len) in columns.iter().zip(column_lengths.iter()) {
+            write!(out, " {: <c_max$} |", column.title(), c_max=column_len)?;
+        }
+        writeln!(out)?;
+        write!(out, "|")?;
+        for column_len in column_lengths.iter() {
+            write!(out, "-{:-<c_max$}-|", "", c_max=column_len)?;
+        }
+        writeln!(out)?;
+    }
 
-/**
- * Export the scan results as a CSV string with response details (timings, ...)
- * and ARP results from the local network.
- */
-pub fn export_to_csv(response_summary: ResponseSummary, mut target_details: Vec<TargetDetails>) -> String {
+    for row in rows.iter() {
 
-    target_details.sort_by_key(|item| item.ipv4);
+        write!(out, "|")?;
+        for (value, column_len) in row.iter().zip(column_lengths.iter()) {
+            write!(out, " {: <c_max$} |", value, c_max=column_len)?;
+        }
+        writeln!(out)?;
+    }
 
-    let global_result = get_serializable_result(response_summary, target_details);
+    write_dhcp_fingerprints(out, target_details)?;
 
-    let mut wtr = csv::Writer::from_writer(vec![]);
+    if let Some(inventory_checks) = inventory_checks {
+        write_inventory_checks(out, inventory_checks, colored)?;
+    }
 
-    for result in global_result.results {
-        wtr.serialize(result).unwrap_or_else(|err| {
-            eprintln!("Could not serialize result to CSV ({})", err);
-            process::exit(1);
-        });
+    writeln!(out)?;
+    write!(out, "ARP scan finished, ")?;
+    let target_count = target_details.len();
+    match target_count {
+        0 => write!(out, "{}", paint(Red, "no hosts found".to_string(), colored)),
+        1 => write!(out, "1 host found"),
+        _ => write!(out, "{} hosts found", target_count)
+    }?;
+    let seconds_duration = (response_summary.duration_ms as f32) / (1000_f32);
+    writeln!(out, " in {:.3} seconds", seconds_duration)?;
+
+    match response_summary.packet_count {
+        0 => write!(out, "No packets received, "),
+        1 => write!(out, "1 packet received, "),
+        _ => write!(out, "{} packets received, ", response_summary.packet_count)
+    }?;
+    match response_summary.arp_count {
+        0 => writeln!(out, "no ARP packets filtered"),
+        1 => writeln!(out, "1 ARP packet filtered"),
+        _ => writeln!(out, "{} ARP packets filtered", response_summary.arp_count)
+    }?;
+    match response_summary.request_rate {
+        Some(request_rate) => writeln!(out, "{} {} sent ({:.0} packets/s)", response_summary.request_count, describe_request_kind(options), request_rate),
+        None => writeln!(out, "{} {} sent", response_summary.request_count, describe_request_kind(options))
+    }?;
+
+    if options.baseline_file.is_some() {
+
+        let violations: Vec<&BaselineViolation> = target_details.iter().flat_map(|detail| detail.violations.iter()).collect();
+        let count_severity = |severity: Severity| violations.iter().filter(|violation| violation.severity == severity).count();
+        match violations.len() {
+            0 => writeln!(out, "{}", paint(Green, "No baseline violation".to_string(), colored)),
+            1 => writeln!(out, "{}", paint(Red, format!("1 baseline violation ({})", violations[0].severity.name()), colored)),
+            violation_count => writeln!(out, "{}", paint(Red, format!("{} baseline violations ({} high, {} medium, {} low)", violation_count, count_severity(Severity::High), count_severity(Severity::Medium), count_severity(Severity::Low)), colored))
+        }?;
     }
-    wtr.flush().unwrap_or_else(|err| {
-        eprintln!("Could not flush CSV writer buffer ({})", err);
-        process::exit(1);
-    });
 
-    let convert_writer = wtr.into_inner().unwrap_or_else(|err| {
-        eprintln!("Could not convert final CSV result ({})", err);
-        process::exit(1);
-    });
-    String::from_utf8(convert_writer).unwrap_or_else(|err| {
-        eprintln!("Could not convert final CSV result to text ({})", err);
-        process::exit(1);
-    })
+    let icmp_only_count = target_details.iter().filter(|detail| detail.found_by == DiscoveryMethod::Icmp).count();
+    match icmp_only_count {
+        0 => Ok(()),
+        1 => writeln!(out, "{}", paint(Red, "1 host answered ICMP without ARP reply".to_string(), colored)),
+        _ => writeln!(out, "{}", paint(Red, format!("{} hosts answered ICMP without ARP reply", icmp_only_count), colored))
+    }?;
+
+    if let Some(inventory_checks) = inventory_checks {
+
+        let count_status = |status: InventoryStatus| inventory_checks.iter().filter(|check| check.status == status).count();
+        let summary = format!("Inventory: {} present, {} missing, {} mismatch", count_status(InventoryStatus::Present), count_status(InventoryStatus::Missing), count_status(InventoryStatus::Mismatch));
+        match count_status(InventoryStatus::Present) == inventory_checks.len() {
+            true => writeln!(out, "{}", paint(Green, summary, colored)),
+            false => writeln!(out, "{}", paint(Red, summary, colored))
+        }?;
+    }
+
+    match response_summary.conflict_count {
+        0 => Ok(()),
+        1 => writeln!(out, "{}", paint(Red, "1 IPv4 address announced by several MAC addresses".to_string(), colored)),
+        conflict_count => writeln!(out, "{}", paint(Red, format!("{} IPv4 addresses announced by several MAC addresses", conflict_count), colored))
+    }?;
+
+    let mismatch_count = target_details.iter().filter(|detail| detail.hostname_confirmed == Some(false)).count();
+    match mismatch_count {
+        0 => Ok(()),
+        1 => writeln!(out, "{}", paint(Red, "1 hostname failed forward confirmation".to_string(), colored)),
+        _ => writeln!(out, "{}", paint(Red, format!("{} hostnames failed forward confirmation", mismatch_count), colored))
+    }?;
+    writeln!(out)
 }